@@ -2,37 +2,51 @@ use std::collections::VecDeque;
 
 use nanocv::{Img, ImgSize, ImgBuf, ImgMut};
 
-use crate::RgbImage;
+use crate::{RgbImage, GrayImage, PreviewImage};
 
 // ============================================ PUBLIC =============================================
 
-pub fn rgb_image_to_bytes(image: &RgbImage<u16>) -> Vec<u8> {
+pub fn preview_image_to_bytes(image: &PreviewImage) -> Vec<u8> {
     let mut writer = BytesWriter::new();
+    let channels = image.channels();
 
-    writer.write_header(image.width(), image.height());
-    writer.write_channel(image.red());
-    writer.write_channel(image.green());
-    writer.write_channel(image.blue());
+    let kind = match image {
+        PreviewImage::Rgb(_) => HEADER_RGB,
+        PreviewImage::Gray(_) => HEADER_GRAY,
+    };
+
+    writer.write_header(kind, image.width(), image.height(), channels.len());
+
+    for channel in channels {
+        writer.write_channel(channel);
+    }
 
     writer.into_buffer()
 }
 
-pub fn rgb_image_from_bytes(bytes: Vec<u8>) -> Result<RgbImage<u16>, String> {
+pub fn preview_image_from_bytes(bytes: Vec<u8>) -> Result<PreviewImage, String> {
     let mut reader = BytesReader::new(bytes);
 
-    reader.read_header()?;
+    let kind = reader.read_header()?;
     let dimensions = reader.read_dimensions()?;
-    let red = reader.read_channel(dimensions)?;
-    let green = reader.read_channel(dimensions)?;
-    let blue = reader.read_channel(dimensions)?;
 
-    RgbImage::from(red, green, blue)
+    Ok(match kind {
+        HEADER_GRAY => PreviewImage::Gray(GrayImage::from(reader.read_channel(dimensions)?)),
+        _ => {
+            let red = reader.read_channel(dimensions)?;
+            let green = reader.read_channel(dimensions)?;
+            let blue = reader.read_channel(dimensions)?;
+            PreviewImage::Rgb(RgbImage::from(red, green, blue)?)
+        }
+    })
 }
 
 // =========================================== PRIVATE =============================================
 
 const HEADER_1: u16 = 45812;
-const HEADER_2: u16 = 19724;
+/// Second header word determines the image kind
+const HEADER_RGB: u16 = 19724;
+const HEADER_GRAY: u16 = 19725;
 
 struct BytesWriter {
     buffer: Vec<u8>
@@ -45,10 +59,10 @@ impl BytesWriter {
         }
     }
 
-    fn write_header(&mut self, width: usize, height: usize) {
-        self.buffer.reserve(((width*height)*3 + 4)*2);
+    fn write_header(&mut self, kind: u16, width: usize, height: usize, channels: usize) {
+        self.buffer.reserve(((width*height)*channels + 4)*2);
         self.write_u16(HEADER_1);
-        self.write_u16(HEADER_2);
+        self.write_u16(kind);
         self.write_u16(width as u16);
         self.write_u16(height as u16);
     }
@@ -83,13 +97,14 @@ impl BytesReader {
         Self { buffer: VecDeque::from(bytes) }
     }
 
-    fn read_header(&mut self) -> Result<(), String> {
+    /// Returns the image kind
+    fn read_header(&mut self) -> Result<u16, String> {
         let header1 = self.read_u16()?;
         let header2 = self.read_u16()?;
 
-        match (header1, header2) == (HEADER_1, HEADER_2) {
-            true => Ok(()),
-            false => Err(format!("Invalid header: [{}, {}]", header1, header2))
+        match (header1, header2) {
+            (HEADER_1, HEADER_RGB | HEADER_GRAY) => Ok(header2),
+            _ => Err(format!("Invalid header: [{}, {}]", header1, header2))
         }
     }
 
@@ -141,15 +156,27 @@ mod tests {
     fn test_to_bytes_and_back() {
         let size = ImgSize::new(5, 4);
 
-        let image = RgbImage::from(
+        let image = PreviewImage::Rgb(RgbImage::from(
             ImgBuf::from_vec(size, vec![1u16; 20]),
             ImgBuf::from_vec(size, vec![2u16; 20]),
             ImgBuf::from_vec(size, vec![3u16; 20]),
-        ).expect("Invalid data size");
+        ).expect("Invalid data size"));
+
+        let bytes = preview_image_to_bytes(&image);
+
+        let converted = preview_image_from_bytes(bytes).expect("Conversion failed");
+        assert_eq!(image, converted);
+    }
+
+    #[test]
+    fn test_gray_to_bytes_and_back() {
+        let size = ImgSize::new(3, 2);
+        let image = PreviewImage::Gray(GrayImage::from(ImgBuf::from_vec(size, vec![7u16; 6])));
 
-        let bytes = rgb_image_to_bytes(&image);
+        let bytes = preview_image_to_bytes(&image);
+        assert_eq!(bytes.len(), (4 + 6)*2);
 
-        let converted = rgb_image_from_bytes(bytes).expect("Conversion failed");
+        let converted = preview_image_from_bytes(bytes).expect("Conversion failed");
         assert_eq!(image, converted);
     }
 }
\ No newline at end of file
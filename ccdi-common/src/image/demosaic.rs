@@ -0,0 +1,349 @@
+use ccdi_imager_interface::{BayerPattern, CfaColor};
+use nanocv::{ImgSize, ImgBuf, ImgMut};
+
+use crate::{RawImage, RgbImage, RenderingType};
+
+use super::{lookup::{Offset, scale_lookup_table, source_range}, grid::draw_thirds_grid};
+
+// ============================================ PUBLIC =============================================
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DemosaicMethod {
+    /// Missing colors are averaged from the nearest pixels of the same color
+    Bilinear,
+    /// Green is interpolated along edges, red and blue follow green using color differences
+    EdgeAware,
+}
+
+/// Demosaic the full resolution image and scale it to the output size, full image is
+/// downscaled by averaging all pixels covered by the output pixel, 1:1 views show
+/// single chip pixels
+pub fn demosaic_scale(
+    input: &RawImage,
+    pattern: BayerPattern,
+    size: ImgSize,
+    rendering: RenderingType,
+    method: DemosaicMethod,
+) -> RgbImage<u16> {
+    let mosaic = Mosaic::new(input, pattern);
+
+    let [r, g, b] = match rendering {
+        RenderingType::FullImage => area_average(&mosaic, size, method),
+        other => sample_pixels(&mosaic, size, other, method),
+    };
+
+    let mut image = RgbImage::from(r, g, b).expect("Logic error");
+
+    if rendering == RenderingType::Corners1x {
+        draw_thirds_grid(&mut image.channels_mut());
+    }
+
+    image
+}
+
+// =========================================== PRIVATE =============================================
+
+struct Mosaic<'a> {
+    data: &'a [u16],
+    width: usize,
+    height: usize,
+    pattern: BayerPattern,
+}
+
+impl<'a> Mosaic<'a> {
+    fn new(input: &'a RawImage, pattern: BayerPattern) -> Self {
+        Self {
+            data: &input.data,
+            width: input.params.area.width,
+            height: input.params.area.height,
+            pattern,
+        }
+    }
+
+    fn size(&self) -> ImgSize {
+        ImgSize::new(self.width, self.height)
+    }
+
+    /// Pixels outside of the image are mirrored, which keeps the color pattern phase
+    fn value(&self, x: isize, y: isize) -> i32 {
+        let x = reflect(x, self.width);
+        let y = reflect(y, self.height);
+        self.data.get(y*self.width + x).copied().unwrap_or(0) as i32
+    }
+
+    fn color(&self, x: isize, y: isize) -> CfaColor {
+        self.pattern.color_at(x.rem_euclid(2) as usize, y.rem_euclid(2) as usize)
+    }
+
+    fn rgb(&self, x: usize, y: usize, method: DemosaicMethod) -> [i32; 3] {
+        let (x, y) = (x as isize, y as isize);
+
+        match method {
+            DemosaicMethod::Bilinear => self.bilinear(x, y),
+            DemosaicMethod::EdgeAware => self.edge_aware(x, y),
+        }
+    }
+
+    fn bilinear(&self, x: isize, y: isize) -> [i32; 3] {
+        let value = self.value(x, y);
+
+        match self.color(x, y) {
+            CfaColor::Green => {
+                let horizontal = (self.value(x - 1, y) + self.value(x + 1, y))/2;
+                let vertical = (self.value(x, y - 1) + self.value(x, y + 1))/2;
+
+                compose([
+                    (CfaColor::Green, value),
+                    (self.color(x + 1, y), horizontal),
+                    (self.color(x, y + 1), vertical),
+                ])
+            },
+            color => {
+                let green = (
+                    self.value(x - 1, y) + self.value(x + 1, y) +
+                    self.value(x, y - 1) + self.value(x, y + 1)
+                )/4;
+
+                let diagonal = (
+                    self.value(x - 1, y - 1) + self.value(x + 1, y - 1) +
+                    self.value(x - 1, y + 1) + self.value(x + 1, y + 1)
+                )/4;
+
+                compose([(color, value), (CfaColor::Green, green), (opposite(color), diagonal)])
+            }
+        }
+    }
+
+    fn edge_aware(&self, x: isize, y: isize) -> [i32; 3] {
+        let value = self.value(x, y);
+        // Color difference to green varies slower than the colors themselves
+        let difference = |x: isize, y: isize| self.value(x, y) - self.green(x, y);
+
+        match self.color(x, y) {
+            CfaColor::Green => {
+                let horizontal = (difference(x - 1, y) + difference(x + 1, y))/2;
+                let vertical = (difference(x, y - 1) + difference(x, y + 1))/2;
+
+                compose([
+                    (CfaColor::Green, value),
+                    (self.color(x + 1, y), value + horizontal),
+                    (self.color(x, y + 1), value + vertical),
+                ])
+            },
+            color => {
+                let green = self.green(x, y);
+
+                let diagonal = (
+                    difference(x - 1, y - 1) + difference(x + 1, y - 1) +
+                    difference(x - 1, y + 1) + difference(x + 1, y + 1)
+                )/4;
+
+                compose([
+                    (color, value),
+                    (CfaColor::Green, green),
+                    (opposite(color), green + diagonal),
+                ])
+            }
+        }
+    }
+
+    /// Green interpolated in the direction of the smaller gradient
+    fn green(&self, x: isize, y: isize) -> i32 {
+        let value = self.value(x, y);
+
+        if self.color(x, y) == CfaColor::Green {
+            return value
+        }
+
+        let (left, right) = (self.value(x - 1, y), self.value(x + 1, y));
+        let (up, down) = (self.value(x, y - 1), self.value(x, y + 1));
+        let laplace_h = 2*value - self.value(x - 2, y) - self.value(x + 2, y);
+        let laplace_v = 2*value - self.value(x, y - 2) - self.value(x, y + 2);
+
+        let gradient_h = (left - right).abs() + laplace_h.abs();
+        let gradient_v = (up - down).abs() + laplace_v.abs();
+        let estimate_h = (left + right)/2 + laplace_h/4;
+        let estimate_v = (up + down)/2 + laplace_v/4;
+
+        match gradient_h.cmp(&gradient_v) {
+            std::cmp::Ordering::Less => estimate_h,
+            std::cmp::Ordering::Greater => estimate_v,
+            std::cmp::Ordering::Equal => (estimate_h + estimate_v)/2,
+        }
+    }
+}
+
+fn area_average(mosaic: &Mosaic, size: ImgSize, method: DemosaicMethod) -> [ImgBuf<u16>; 3] {
+    let input = mosaic.size();
+    let columns = (0..size.x).map(|x| source_range(x, input.x, size.x)).collect::<Vec<_>>();
+    let mut channels = new_channels(size);
+
+    for line in 0..size.y {
+        // Image is flipped vertically the same way as in the fast preview
+        let rows = source_range(size.y - 1 - line, input.y, size.y);
+
+        for (column, range) in columns.iter().enumerate() {
+            let mut sum = [0i64; 3];
+
+            for y in rows.clone() {
+                for x in range.clone() {
+                    let rgb = mosaic.rgb(x, y, method);
+                    sum.iter_mut().zip(rgb).for_each(|(sum, value)| *sum += value as i64);
+                }
+            }
+
+            let count = (rows.len()*range.len()).max(1) as i64;
+
+            for (channel, sum) in channels.iter_mut().zip(sum) {
+                channel.line_mut(line)[column] = clamp(sum/count);
+            }
+        }
+    }
+
+    channels
+}
+
+fn sample_pixels(
+    mosaic: &Mosaic,
+    size: ImgSize,
+    rendering: RenderingType,
+    method: DemosaicMethod,
+) -> [ImgBuf<u16>; 3] {
+    let table = scale_lookup_table(mosaic.size(), size, Offset { x: 0, y: 0 }, rendering, 1);
+    let mut channels = new_channels(ImgSize::new(table.x.len(), table.y.len()));
+
+    for (line, &y) in table.y.iter().enumerate() {
+        for (column, &x) in table.x.iter().enumerate() {
+            let rgb = mosaic.rgb(x, y, method);
+
+            for (channel, value) in channels.iter_mut().zip(rgb) {
+                channel.line_mut(line)[column] = clamp(value as i64);
+            }
+        }
+    }
+
+    channels
+}
+
+fn new_channels(size: ImgSize) -> [ImgBuf<u16>; 3] {
+    [
+        ImgBuf::<u16>::new_init(size, 0),
+        ImgBuf::<u16>::new_init(size, 0),
+        ImgBuf::<u16>::new_init(size, 0),
+    ]
+}
+
+fn reflect(position: isize, length: usize) -> usize {
+    let last = length as isize - 1;
+
+    let reflected = match position {
+        position if position < 0 => -position,
+        position if position > last => 2*last - position,
+        position => position,
+    };
+
+    reflected.clamp(0, last.max(0)) as usize
+}
+
+fn opposite(color: CfaColor) -> CfaColor {
+    match color {
+        CfaColor::Red => CfaColor::Blue,
+        CfaColor::Blue => CfaColor::Red,
+        CfaColor::Green => CfaColor::Green,
+    }
+}
+
+fn compose(values: [(CfaColor, i32); 3]) -> [i32; 3] {
+    let mut rgb = [0; 3];
+
+    for (color, value) in values {
+        let index = match color {
+            CfaColor::Red => 0,
+            CfaColor::Green => 1,
+            CfaColor::Blue => 2,
+        };
+
+        rgb[index] = value;
+    }
+
+    rgb
+}
+
+fn clamp(value: i64) -> u16 {
+    value.clamp(0, u16::MAX as i64) as u16
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use crate::test_image;
+
+    use super::*;
+
+    const PATTERNS: [BayerPattern; 4] = [
+        BayerPattern::Rggb, BayerPattern::Bggr, BayerPattern::Grbg, BayerPattern::Gbrg
+    ];
+
+    const METHODS: [DemosaicMethod; 2] = [DemosaicMethod::Bilinear, DemosaicMethod::EdgeAware];
+
+    /// Mosaic of a scene where the top half has the first and the bottom half the second color
+    fn mosaic_image(
+        pattern: BayerPattern, width: usize, height: usize, top: [u16; 3], bottom: [u16; 3]
+    ) -> RawImage {
+        let data = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let rgb = if y < height/2 { top } else { bottom };
+
+                match pattern.color_at(x, y) {
+                    CfaColor::Red => rgb[0],
+                    CfaColor::Green => rgb[1],
+                    CfaColor::Blue => rgb[2],
+                }
+            })
+            .collect();
+
+        test_image(width, height, data)
+    }
+
+    fn pixel(image: &RgbImage<u16>, x: usize, y: usize) -> [u16; 3] {
+        [image.red().line_ref(y)[x], image.green().line_ref(y)[x], image.blue().line_ref(y)[x]]
+    }
+
+    #[test]
+    fn uniform_color_is_restored_for_all_patterns() {
+        let color = [1000, 2000, 3000];
+
+        for pattern in PATTERNS {
+            let input = mosaic_image(pattern, 8, 8, color, color);
+
+            for method in METHODS {
+                let mosaic = Mosaic::new(&input, pattern);
+
+                for (x, y) in (0..8).flat_map(|y| (0..8).map(move |x| (x, y))) {
+                    assert_eq!(mosaic.rgb(x, y, method), [1000, 2000, 3000]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn full_image_is_averaged_and_flipped() {
+        let top = [1000, 2000, 3000];
+        let bottom = [4000, 5000, 6000];
+        let input = mosaic_image(BayerPattern::Rggb, 16, 16, top, bottom);
+
+        let image = demosaic_scale(
+            &input, BayerPattern::Rggb, ImgSize::new(2, 2),
+            RenderingType::FullImage, DemosaicMethod::EdgeAware
+        );
+
+        // Pixels near the color edge are mixed, averaged values stay close to the scene colors
+        let close = |actual: [u16; 3], expected: [u16; 3]| actual.iter().zip(expected)
+            .all(|(actual, expected)| (*actual as i32 - expected as i32).abs() < 300);
+
+        assert!(close(pixel(&image, 0, 0), bottom));
+        assert!(close(pixel(&image, 1, 1), top));
+    }
+}
@@ -0,0 +1,65 @@
+use nanocv::{ImgBuf, Img, ImgMut};
+use serde_derive::{Serialize, Deserialize};
+
+use crate::RgbImage;
+
+// ============================================ PUBLIC =============================================
+
+/// Single channel image of a monochrome chip
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GrayImage<T> {
+    luminance: ImgBuf<T>,
+}
+
+impl<T> GrayImage<T> {
+    pub fn from(luminance: ImgBuf<T>) -> Self {
+        Self { luminance }
+    }
+
+    pub fn width(&self) -> usize {
+        self.luminance.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.luminance.height()
+    }
+
+    pub fn luminance(&self) -> &dyn Img<T> {
+        &self.luminance
+    }
+
+    pub fn luminance_mut(&mut self) -> &mut dyn ImgMut<T> {
+        &mut self.luminance
+    }
+}
+
+/// Preview image sent to clients, color or single channel
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum PreviewImage {
+    Rgb(RgbImage<u16>),
+    Gray(GrayImage<u16>),
+}
+
+impl PreviewImage {
+    pub fn width(&self) -> usize {
+        match self {
+            PreviewImage::Rgb(image) => image.width(),
+            PreviewImage::Gray(image) => image.width(),
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match self {
+            PreviewImage::Rgb(image) => image.height(),
+            PreviewImage::Gray(image) => image.height(),
+        }
+    }
+
+    /// Image channels, red, green and blue for color images, luminance for gray images
+    pub fn channels(&self) -> Vec<&dyn Img<u16>> {
+        match self {
+            PreviewImage::Rgb(image) => vec![image.red(), image.green(), image.blue()],
+            PreviewImage::Gray(image) => vec![image.luminance()],
+        }
+    }
+}
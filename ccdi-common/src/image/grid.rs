@@ -1,14 +1,12 @@
 use nanocv::ImgMut;
 
-use crate::RgbImage;
-
 // ============================================ PUBLIC =============================================
 
-pub fn draw_thirds_grid(image: &mut RgbImage<u16>) {
-    let width_third = image.width()/3;
-    let height_third = image.height()/3;
+pub fn draw_thirds_grid(channels: &mut [&mut dyn ImgMut<u16>]) {
+    for channel in channels.iter_mut().map(|channel| &mut **channel) {
+        let width_third = channel.width()/3;
+        let height_third = channel.height()/3;
 
-    for channel in image.channels_mut() {
         vertical_line(channel, width_third, u16::MAX);
         vertical_line(channel, width_third*2, u16::MAX);
         horizontal_line(channel, height_third, u16::MAX);
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use nanocv::ImgSize;
 
 use crate::RenderingType;
@@ -15,27 +17,37 @@ pub struct Offset {
     pub y: usize,
 }
 
+/// Cell is the size of a single color cell in input pixels, 2 for bayer
+/// mosaic, 1 for images where each pixel contains all channels
 pub fn scale_lookup_table(
-    input: ImgSize, output: ImgSize, offset: Offset, rendering: RenderingType
+    input: ImgSize, output: ImgSize, offset: Offset, rendering: RenderingType, cell: usize
 ) -> LookupTable {
-    let half_input = ImgSize { x: input.x/2, y: input.y/2 };
+    let cell_input = ImgSize { x: input.x/cell, y: input.y/cell };
 
     apply_offsets(
         match rendering {
-            RenderingType::FullImage => lookup_table_full_image(half_input, output),
-            RenderingType::Center1x => lookup_table_center(half_input, output),
-            RenderingType::Corners1x => lookup_table_corners(half_input, output),
+            RenderingType::FullImage => lookup_table_full_image(cell_input, output),
+            RenderingType::Center1x => lookup_table_center(cell_input, output),
+            RenderingType::Corners1x => lookup_table_corners(cell_input, output),
         },
-        offset
+        offset,
+        cell
     )
 }
 
+/// Input pixels covered by the output pixel, at least one pixel when upscaling
+pub fn source_range(index: usize, input: usize, output: usize) -> Range<usize> {
+    let start = (index*input/output).min(input.saturating_sub(1));
+    let end = ((index + 1)*input/output).clamp(start + 1, input.max(1));
+    start..end
+}
+
 // =========================================== PRIVATE =============================================
 
-fn apply_offsets(table: LookupTable, offset: Offset) -> LookupTable {
+fn apply_offsets(table: LookupTable, offset: Offset, cell: usize) -> LookupTable {
     LookupTable {
-        x: table.x.into_iter().map(|x| x*2 + offset.x).collect(),
-        y: table.y.into_iter().rev().map(|y| y*2 + offset.y).collect(),
+        x: table.x.into_iter().map(|x| x*cell + offset.x).collect(),
+        y: table.y.into_iter().rev().map(|y| y*cell + offset.y).collect(),
     }
 }
 
@@ -128,4 +140,31 @@ mod tests {
         assert_eq!(table.x, vec![0, 1, 8, 9, 16, 17]);
         assert_eq!(table.y, vec![0, 9, 17]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_scale_lookup_bayer_cell() {
+        let input = ImgSize::new(16, 8);
+        let output = ImgSize::new(4, 2);
+        let offset = Offset { x: 1, y: 0 };
+        let table = scale_lookup_table(input, output, offset, RenderingType::FullImage, 2);
+        assert_eq!(table.x, vec![1, 5, 9, 13]);
+        assert_eq!(table.y, vec![4, 0]);
+    }
+
+    #[test]
+    fn test_scale_lookup_single_pixel_cell() {
+        let input = ImgSize::new(8, 4);
+        let output = ImgSize::new(4, 2);
+        let offset = Offset { x: 0, y: 0 };
+        let table = scale_lookup_table(input, output, offset, RenderingType::FullImage, 1);
+        assert_eq!(table.x, vec![0, 2, 4, 6]);
+        assert_eq!(table.y, vec![2, 0]);
+    }
+
+    #[test]
+    fn source_ranges_cover_input() {
+        assert_eq!(source_range(0, 10, 3), 0..3);
+        assert_eq!(source_range(2, 10, 3), 6..10);
+        assert_eq!(source_range(3, 2, 4), 1..2);
+    }
+}
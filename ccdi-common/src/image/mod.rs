@@ -1,9 +1,15 @@
 mod rgb;
+mod gray;
+mod mono;
 mod scale_fast;
 mod lookup;
 mod grid;
 mod binary;
+mod demosaic;
 
 pub use rgb::RgbImage;
+pub use gray::{GrayImage, PreviewImage};
+pub use mono::mono_scale;
 pub use scale_fast::debayer_scale_fast;
-pub use binary::{rgb_image_to_bytes, rgb_image_from_bytes};
\ No newline at end of file
+pub use demosaic::{demosaic_scale, DemosaicMethod};
+pub use binary::{preview_image_to_bytes, preview_image_from_bytes};
\ No newline at end of file
@@ -0,0 +1,106 @@
+use nanocv::{ImgSize, ImgBuf, ImgMut};
+
+use crate::{RawImage, RenderingType};
+
+use super::{
+    gray::GrayImage, grid::draw_thirds_grid,
+    lookup::{Offset, scale_lookup_table, source_range}
+};
+
+// ============================================ PUBLIC =============================================
+
+/// Scale the full resolution luminance of a monochrome (or binned) image, full image is
+/// downscaled by averaging all pixels covered by the output pixel, 1:1 views show
+/// single chip pixels
+pub fn mono_scale(input: &RawImage, size: ImgSize, rendering: RenderingType) -> GrayImage<u16> {
+    let luminance = match rendering {
+        RenderingType::FullImage => area_average(input, size),
+        other => sample_pixels(input, size, other),
+    };
+
+    let mut image = GrayImage::from(luminance);
+
+    if rendering == RenderingType::Corners1x {
+        draw_thirds_grid(&mut [image.luminance_mut()]);
+    }
+
+    image
+}
+
+// =========================================== PRIVATE =============================================
+
+fn input_size(input: &RawImage) -> ImgSize {
+    ImgSize::new(input.params.area.width, input.params.area.height)
+}
+
+fn area_average(input: &RawImage, size: ImgSize) -> ImgBuf<u16> {
+    let input_size = input_size(input);
+    let columns = (0..size.x).map(|x| source_range(x, input_size.x, size.x)).collect::<Vec<_>>();
+    let mut result = ImgBuf::<u16>::new_init(size, 0);
+
+    for line in 0..size.y {
+        // Image is flipped vertically the same way as the color previews
+        let rows = source_range(size.y - 1 - line, input_size.y, size.y);
+        let dst = result.line_mut(line);
+
+        for (pixel, range) in dst.iter_mut().zip(columns.iter()) {
+            let mut sum = 0u64;
+
+            for y in rows.clone() {
+                let row = &input.data[y*input_size.x..(y + 1)*input_size.x];
+                sum += row[range.clone()].iter().map(|value| *value as u64).sum::<u64>();
+            }
+
+            let count = (rows.len()*range.len()).max(1) as u64;
+            *pixel = (sum/count) as u16;
+        }
+    }
+
+    result
+}
+
+fn sample_pixels(input: &RawImage, size: ImgSize, rendering: RenderingType) -> ImgBuf<u16> {
+    let width = input.params.area.width;
+    let table = scale_lookup_table(input_size(input), size, Offset { x: 0, y: 0 }, rendering, 1);
+    let mut result = ImgBuf::<u16>::new_init(ImgSize::new(table.x.len(), table.y.len()), 0);
+
+    for (line, &y) in table.y.iter().enumerate() {
+        let src = &input.data[y*width..(y + 1)*width];
+
+        for (pixel, &x) in result.line_mut(line).iter_mut().zip(table.x.iter()) {
+            *pixel = src[x];
+        }
+    }
+
+    result
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pixel value is 100 times the row plus the column
+    fn gradient_image(width: usize, height: usize) -> RawImage {
+        let data = (0..width*height).map(|index| (index/width*100 + index%width) as u16);
+        crate::test_image(width, height, data.collect())
+    }
+
+    #[test]
+    fn full_image_is_averaged_and_flipped() {
+        let image = mono_scale(&gradient_image(4, 4), ImgSize::new(2, 2), RenderingType::FullImage);
+
+        assert_eq!(image.luminance().line_ref(0), &[250, 252]);
+        assert_eq!(image.luminance().line_ref(1), &[50, 52]);
+    }
+
+    #[test]
+    fn center_view_keeps_single_pixels() {
+        let image = mono_scale(&gradient_image(8, 8), ImgSize::new(2, 2), RenderingType::Center1x);
+
+        assert_eq!(image.width(), 2);
+        assert_eq!(image.luminance().line_ref(0), &[302, 303]);
+        assert_eq!(image.luminance().line_ref(1), &[202, 203]);
+    }
+}
@@ -1,4 +1,5 @@
-use nanocv::{ImgSize, ImgBuf, ImgMut};
+use ccdi_imager_interface::{BayerPattern, CfaColor};
+use nanocv::{ImgSize, ImgBuf, Img, ImgMut};
 
 use crate::{RawImage, RgbImage, RenderingType};
 
@@ -6,17 +7,26 @@ use super::{lookup::{Offset, LookupTable, scale_lookup_table}, grid::draw_thirds
 
 // ============================================ PUBLIC =============================================
 
+/// Each bayer cell is rendered as a single pixel by picking the cell pixels directly
 pub fn debayer_scale_fast(
-    input: &RawImage, size: ImgSize, rendering: RenderingType
+    input: &RawImage, pattern: BayerPattern, size: ImgSize, rendering: RenderingType
 ) -> RgbImage<u16> {
-    let offsets = &OFFSET_GRBG;
-    let r = resize_channel(input, size, offsets.r, rendering);
-    let g = resize_channel(input, size, offsets.g1, rendering);
-    let b = resize_channel(input, size, offsets.b, rendering);
+    let (offsets, cell) = (channel_offsets(pattern), 2);
+
+    let r = resize_channel(input, size, offsets.r, rendering, cell);
+    let b = resize_channel(input, size, offsets.b, rendering, cell);
+
+    let g = match offsets.g1 == offsets.g2 {
+        true => resize_channel(input, size, offsets.g1, rendering, cell),
+        false => average(
+            resize_channel(input, size, offsets.g1, rendering, cell),
+            &resize_channel(input, size, offsets.g2, rendering, cell),
+        ),
+    };
     let mut image = RgbImage::from(r, g, b).expect("Logic error");
 
     if rendering == RenderingType::Corners1x {
-        draw_thirds_grid(&mut image);
+        draw_thirds_grid(&mut image.channels_mut());
     }
 
     image
@@ -28,10 +38,11 @@ fn resize_channel(
     image: &RawImage,
     output_size: ImgSize,
     offset: Offset,
-    rendering: RenderingType
+    rendering: RenderingType,
+    cell: usize,
 ) -> ImgBuf<u16> {
     let input_size = ImgSize::new(image.params.area.width, image.params.area.height);
-    let lookup = scale_lookup_table(input_size, output_size, offset, rendering);
+    let lookup = scale_lookup_table(input_size, output_size, offset, rendering, cell);
     scale_with_lookup_table(image, &lookup)
 }
 
@@ -61,11 +72,33 @@ struct ChannelOffsets {
     b: Offset
 }
 
-const OFFSET_GRBG: ChannelOffsets = ChannelOffsets {
-    r: Offset { x: 1, y: 0 },
-    g1: Offset { x: 1, y: 1 },
-    g2: Offset { x: 0, y: 0 },
-    b: Offset { x: 0, y: 1 },
-};
+fn channel_offsets(pattern: BayerPattern) -> ChannelOffsets {
+    let cell = [(0, 0), (1, 0), (0, 1), (1, 1)];
+    let find = |color: CfaColor| cell.iter()
+        .filter(|(x, y)| pattern.color_at(*x, *y) == color)
+        .map(|&(x, y)| Offset { x, y })
+        .collect::<Vec<_>>();
+
+    let greens = find(CfaColor::Green);
+
+    ChannelOffsets {
+        r: find(CfaColor::Red)[0],
+        g1: greens[0],
+        g2: greens[1],
+        b: find(CfaColor::Blue)[0],
+    }
+}
+
+fn average(mut first: ImgBuf<u16>, second: &ImgBuf<u16>) -> ImgBuf<u16> {
+    for line in 0..first.height() {
+        let src = second.line_ref(line);
+
+        for (dst, src) in first.line_mut(line).iter_mut().zip(src) {
+            *dst = ((*dst as u32 + *src as u32)/2) as u16;
+        }
+    }
+
+    first
+}
 
 
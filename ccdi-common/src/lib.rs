@@ -2,8 +2,12 @@ mod helpers;
 mod messages;
 mod image;
 mod file;
+#[cfg(any(test, feature = "test-util"))]
+mod test_util;
 
 pub use helpers::{to_string, log_err};
 pub use messages::*;
 pub use image::*;
-pub use file::*;
\ No newline at end of file
+pub use file::*;
+#[cfg(any(test, feature = "test-util"))]
+pub use test_util::*;
\ No newline at end of file
@@ -1,10 +1,16 @@
 use std::sync::Arc;
 
-use ccdi_imager_interface::{ImagerProperties, ExposureParams};
+use ccdi_imager_interface::{
+    ImagerProperties, ExposureParams, Binning, ExposureArea, FrameType, DeviceDescriptor,
+    BayerPattern
+};
 use nanocv::ImgSize;
 use serde_derive::{Serialize, Deserialize};
 
-use crate::{RgbImage, RenderingType, StorageState, StorageDetail};
+use crate::{
+    PreviewImage, FocusMetrics, RenderingType, DemosaicQuality, StorageState, StorageDetail,
+    SequenceView, ImageMetadata, Coordinates, FocuserView, TelemetrySample
+};
 
 use super::gui_config::GuiConfig;
 
@@ -14,15 +20,29 @@ use super::gui_config::GuiConfig;
 pub enum ClientMessage {
     Reconnect,
     View(ViewState),
-    RgbImage(Arc<RgbImage<u16>>),
+    Image(Arc<PreviewImage>),
+    FocusMetrics(FocusMetrics),
+    /// New telemetry samples, the whole history when a client connects
+    Telemetry(Vec<TelemetrySample>),
 }
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct RawImage {
     pub params: ExposureParams,
+    pub metadata: ImageMetadata,
     pub data: Vec<u16>
 }
 
+impl RawImage {
+    /// Color filter array pattern of the image data, the chip pattern shifted
+    /// by the origin of the region of interest
+    pub fn bayer_pattern(&self) -> Option<BayerPattern> {
+        let area = &self.params.area;
+        self.metadata.bayer_pattern.map(|pattern| pattern.shifted(area.x, area.y))
+    }
+}
+
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct ViewState {
     pub detail: String,
@@ -30,7 +50,12 @@ pub struct ViewState {
     pub camera_properties: Option<Arc<ImagerProperties>>,
     pub camera_params: CameraParams,
     pub storage_detail: StorageDetail,
+    pub sequence: SequenceView,
+    pub focuser: FocuserView,
+    pub cameras: CameraListView,
     pub config: GuiConfig,
+    /// Present once power off was requested
+    pub shutdown: Option<ShutdownProgress>,
 }
 
 impl Default for ViewState {
@@ -41,11 +66,44 @@ impl Default for ViewState {
             camera_properties: None,
             camera_params: Default::default(),
             storage_detail: Default::default(),
+            sequence: Default::default(),
+            focuser: Default::default(),
+            cameras: Default::default(),
             config: GuiConfig::default(),
+            shutdown: None,
         }
     }
 }
 
+/// Cameras present on the bus, the selected camera is connected whenever it is present
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct CameraListView {
+    pub devices: Vec<DeviceDescriptor>,
+    /// Camera chosen by the user, the first listed camera is used when missing
+    pub selected: Option<i32>,
+    pub connected: Option<i32>,
+}
+
+/// Steps done before the power off command is executed, in order
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ShutdownStage {
+    /// Waiting for queued images to be written
+    Flushing,
+    /// Cooler ramps the chip up towards ambient
+    WarmingUp,
+    PoweringOff,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ShutdownProgress {
+    pub stage: ShutdownStage,
+    /// Images waiting to be written
+    pub queue_depth: usize,
+    pub chip_temperature: Option<f32>,
+    /// Temperature the chip is warmed up to
+    pub target_temperature: Option<f64>,
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct CameraParams {
     pub loop_enabled: bool,
@@ -56,6 +114,19 @@ pub struct CameraParams {
     pub temperature: f64,
     pub trigger_required: bool,
     pub heating_pwm: f64,
+    pub binning: Binning,
+    /// Region of interest in unbinned chip pixels, None means the full chip
+    pub roi: Option<ExposureArea>,
+    /// Index of the camera read mode
+    pub read_mode: usize,
+    /// Index of the filter wheel position
+    pub filter: usize,
+    pub frame_type: FrameType,
+    /// Coordinates of the imaged object, if known
+    pub coordinates: Option<Coordinates>,
+    /// Name of the imaged object written to the OBJECT keyword, if known
+    pub object: Option<String>,
+    pub demosaic: DemosaicQuality,
 }
 
 impl CameraParams {
@@ -69,6 +140,14 @@ impl CameraParams {
             temperature: 25.0,
             trigger_required: false,
             heating_pwm: 0.0,
+            binning: Binning::default(),
+            roi: None,
+            read_mode: 0,
+            filter: 0,
+            frame_type: FrameType::Light,
+            coordinates: None,
+            object: None,
+            demosaic: DemosaicQuality::Fast,
         }
     }
 }
@@ -88,6 +167,8 @@ pub struct LogicStatus {
     pub required: ConnectionState,
     pub loop_enabled: ConnectionState,
     pub save: ConnectionState,
+    /// Chip temperature stable at the target, `Connecting` while it settles
+    pub cooling: ConnectionState,
 }
 
 impl Default for LogicStatus {
@@ -100,6 +181,7 @@ impl Default for LogicStatus {
             storage: StorageState::Unknown,
             save: ConnectionState::Disconnected,
             loop_enabled: ConnectionState::Disconnected,
+            cooling: ConnectionState::Disconnected,
         }
     }
 }
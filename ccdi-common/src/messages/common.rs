@@ -25,4 +25,16 @@ impl StorageState {
 pub struct StorageCapacity {
     pub total_gigabytes: f64,
     pub free_gigabytes: f64,
+    pub level: SpaceLevel,
+}
+
+/// Free space compared to the configured thresholds
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SpaceLevel {
+    #[default]
+    Sufficient,
+    /// User should be warned
+    Low,
+    /// Capture must not continue on this storage
+    Critical,
 }
\ No newline at end of file
@@ -0,0 +1,16 @@
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+/// Star measurements of a single frame, sizes are in chip pixels,
+/// metrics are zero when no stars were detected
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct FocusMetrics {
+    pub star_count: usize,
+    /// Median half flux radius
+    pub hfr: f64,
+    /// Median full width at half maximum
+    pub fwhm: f64,
+    /// Median star eccentricity, 0 for round stars
+    pub eccentricity: f64,
+}
@@ -0,0 +1,68 @@
+use ccdi_focuser_interface::{FocuserProperties, FocuserStatus};
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum FocuserMessage {
+    /// Move to the absolute position in focuser steps
+    MoveTo(i32),
+    /// Move relative to the current position
+    MoveBy(i32),
+    Halt,
+    StartAutofocus,
+    StopAutofocus,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct FocuserView {
+    /// Missing when no focuser is connected
+    pub properties: Option<FocuserProperties>,
+    pub status: Option<FocuserStatus>,
+    pub autofocus: AutofocusView,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum AutofocusState {
+    #[default]
+    Idle,
+    Running,
+    Finished,
+    Failed,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct AutofocusView {
+    pub state: AutofocusState,
+    pub detail: String,
+    /// Number of positions to be measured
+    pub total_points: usize,
+    /// Positions measured so far
+    pub points: Vec<FocusPoint>,
+    /// Curve fitted to the measured points when finished
+    pub curve: Option<FocusCurve>,
+    pub best_position: Option<i32>,
+}
+
+/// Median half flux radius measured at a focuser position
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FocusPoint {
+    pub position: i32,
+    pub hfr: f64,
+    pub star_count: usize,
+}
+
+/// Hyperbola `hfr = a*sqrt(1 + ((position - center)/b)^2)` fitted to the measured points,
+/// `a` is the best achievable HFR and `a/b` the slope of the asymptotes
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FocusCurve {
+    pub a: f64,
+    pub b: f64,
+    pub center: f64,
+}
+
+impl FocusCurve {
+    pub fn hfr_at(&self, position: f64) -> f64 {
+        self.a*(1.0 + ((position - self.center)/self.b).powi(2)).sqrt()
+    }
+}
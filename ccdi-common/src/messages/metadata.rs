@@ -0,0 +1,52 @@
+use ccdi_imager_interface::BayerPattern;
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+/// Camera and observatory state captured at exposure start
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub camera_name: String,
+    /// Chip pixel width in micrometers
+    pub pixel_width: f32,
+    /// Chip pixel height in micrometers
+    pub pixel_height: f32,
+    /// Chip temperature in degrees celsius
+    pub temperature: Option<f32>,
+    /// Requested chip temperature in degrees celsius
+    pub target_temperature: f64,
+    /// Color filter array pattern of the chip, None for binned or mono images
+    pub bayer_pattern: Option<BayerPattern>,
+    pub filter: Option<String>,
+    /// Name of the imaged object, if known
+    pub object: Option<String>,
+    pub coordinates: Option<Coordinates>,
+    pub optics: OpticsConfig,
+    pub site: Option<SiteConfig>,
+}
+
+/// Equatorial coordinates of the image center in degrees
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Coordinates {
+    pub ra: f64,
+    pub dec: f64,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct OpticsConfig {
+    pub telescope: String,
+    /// Focal length in millimeters
+    pub focal_length: Option<f64>,
+    /// Aperture diameter in millimeters
+    pub aperture: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SiteConfig {
+    /// Latitude in degrees, positive north
+    pub latitude: f64,
+    /// Longitude in degrees, positive east
+    pub longitude: f64,
+    /// Elevation above sea level in meters
+    pub elevation: f64,
+}
@@ -5,6 +5,11 @@ mod storage;
 mod common;
 mod gui_config;
 mod io;
+mod sequence;
+mod metadata;
+mod focus;
+mod focuser;
+mod telemetry;
 
 pub use client::*;
 pub use state::*;
@@ -12,4 +17,9 @@ pub use process::*;
 pub use storage::*;
 pub use common::*;
 pub use gui_config::*;
-pub use io::*;
\ No newline at end of file
+pub use io::*;
+pub use sequence::*;
+pub use metadata::*;
+pub use focus::*;
+pub use focuser::*;
+pub use telemetry::*;
\ No newline at end of file
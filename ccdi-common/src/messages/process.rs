@@ -3,7 +3,7 @@ use std::sync::Arc;
 use nanocv::ImgSize;
 use serde_derive::{Serialize, Deserialize};
 
-use crate::{RawImage, RenderingType};
+use crate::{RawImage, RenderingType, DemosaicQuality};
 
 // ============================================ PUBLIC =============================================
 
@@ -18,4 +18,5 @@ pub struct ConvertRawImage {
     pub image: Arc<RawImage>,
     pub size: ImgSize,
     pub rendering: RenderingType,
+    pub quality: DemosaicQuality,
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+use ccdi_imager_interface::{Binning, FrameType};
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SequenceMessage {
+    SetPlan(ExposurePlan),
+    Start,
+    Pause,
+    Resume,
+    /// Abort the current step and continue with the next one
+    Skip,
+    Stop,
+}
+
+/// Ordered list of exposure steps executed by the sequencer
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct ExposurePlan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl ExposurePlan {
+    pub fn total_frames(&self) -> usize {
+        self.steps.iter().map(|step| step.count).sum()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub count: usize,
+    pub time: f64,
+    pub gain: u16,
+    pub binning: Binning,
+    pub frame_type: FrameType,
+    /// Storage directory the frames are saved into
+    pub directory: String,
+}
+
+impl Default for PlanStep {
+    fn default() -> Self {
+        Self {
+            count: 10,
+            time: 60.0,
+            gain: 0,
+            binning: Binning::default(),
+            frame_type: FrameType::Light,
+            directory: String::from("default"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SequenceState {
+    #[default]
+    Idle,
+    Running,
+    Paused,
+    Finished,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SequenceProgress {
+    pub state: SequenceState,
+    /// Index of the current step
+    pub step: usize,
+    /// Frames finished in the current step
+    pub frame: usize,
+    /// Frames finished in the whole plan
+    pub frames_done: usize,
+    pub frames_total: usize,
+    /// Estimated time to finish the plan in seconds
+    pub eta: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SequenceView {
+    pub plan: ExposurePlan,
+    pub progress: SequenceProgress,
+}
@@ -1,8 +1,12 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::SystemTime};
 
+use ccdi_imager_interface::{Binning, ExposureArea, FrameType};
 use serde_derive::{Serialize, Deserialize};
 
-use crate::{RgbImage, StorageState, StorageMessage, StorageDetail};
+use crate::{
+    PreviewImage, StorageState, StorageMessage, StorageDetail, SequenceMessage, Coordinates,
+    FocuserMessage, FocusMetrics
+};
 
 // ============================================ PUBLIC =============================================
 
@@ -11,17 +15,26 @@ pub enum StateMessage {
     ExposureMessage(ExposureCommand),
     CameraParam(CameraParamMessage),
     ClientConnected,
-    ImageDisplayed(Arc<RgbImage<u16>>),
+    ImageDisplayed(Arc<PreviewImage>),
+    /// Focus metrics measured on the frame started at the given time
+    FocusMeasured(SystemTime, FocusMetrics),
     UpdateStorageState(StorageState),
     TriggerValueChanged(bool),
     StorageMessage(StorageMessage),
     UpdateStorageDetail(StorageDetail),
+    SequenceMessage(SequenceMessage),
+    FocuserMessage(FocuserMessage),
+    /// Connect the camera with the given id and keep it selected after restart
+    SelectCamera(i32),
+    /// List the cameras offered for selection again
+    RefreshCameras,
     PowerOff,
 }
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum ExposureCommand {
     Start,
+    Stop,
 }
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -33,6 +46,14 @@ pub enum CameraParamMessage {
     SetHeatingPwm(f64),
     SetRenderingType(RenderingType),
     SetTriggerRequired(bool),
+    SetBinning(Binning),
+    SetRoi(Option<ExposureArea>),
+    SetReadMode(usize),
+    SetFilter(usize),
+    SetFrameType(FrameType),
+    SetCoordinates(Option<Coordinates>),
+    SetObject(Option<String>),
+    SetDemosaicQuality(DemosaicQuality),
 }
 
 #[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -40,4 +61,14 @@ pub enum RenderingType {
     FullImage,
     Center1x,
     Corners1x,
+}
+
+/// Color reconstruction of preview images, better quality needs more processing time
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum DemosaicQuality {
+    /// One pixel per bayer cell, suitable for live preview on slow devices
+    #[default]
+    Fast,
+    Bilinear,
+    EdgeAware,
 }
\ No newline at end of file
@@ -12,6 +12,8 @@ pub enum StorageMessage {
     DisableStore,
     ProcessImage(Arc<RawImage>),
     SetDirectory(String),
+    /// Compression of XISF files saved from now on
+    SetCompression(XisfCompression),
 }
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -20,7 +22,22 @@ pub struct StorageDetail {
     pub counter: usize,
     pub storage_log: Vec<StorageLogRecord>,
     pub storage_enabled: bool,
+    /// Storage root images are currently written to
+    pub storage_root: String,
     pub state: StorageState,
+    /// Frames waiting to be written or being written
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    pub compression: XisfCompression,
+}
+
+/// Compression of the XISF image data block
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum XisfCompression {
+    #[default]
+    None,
+    Lz4,
+    Zlib,
 }
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -42,7 +59,11 @@ impl Default for StorageDetail {
             counter: 0,
             storage_log: Vec::new(),
             storage_enabled: false,
+            storage_root: String::new(),
             state: StorageState::Unknown,
+            queue_depth: 0,
+            queue_capacity: 0,
+            compression: XisfCompression::None,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,98 @@
+use std::{collections::VecDeque, time::SystemTime};
+
+use ccdi_imager_interface::Telemetry;
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+/// Samples kept for the charts, twelve hours at `TELEMETRY_PERIOD`
+pub const TELEMETRY_HISTORY_LENGTH: usize = 1440;
+/// Seconds between telemetry samples
+pub const TELEMETRY_PERIOD: f64 = 30.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TelemetrySample {
+    pub time: SystemTime,
+    pub telemetry: Telemetry,
+}
+
+/// Ring buffer of telemetry samples, oldest first, the oldest sample is dropped
+/// when the capacity is reached
+#[derive(Clone, PartialEq, Debug)]
+pub struct TelemetryHistory {
+    samples: VecDeque<TelemetrySample>,
+    capacity: usize,
+}
+
+impl TelemetryHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    /// Append samples newer than the last one, samples already present are skipped
+    /// so that a history sent again after a reconnection is not duplicated
+    pub fn extend(&mut self, samples: impl IntoIterator<Item = TelemetrySample>) {
+        for sample in samples {
+            if self.samples.back().is_some_and(|last| last.time >= sample.time) {
+                continue
+            }
+
+            if self.samples.len() >= self.capacity {
+                self.samples.pop_front();
+            }
+
+            self.samples.push_back(sample);
+        }
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &TelemetrySample> {
+        self.samples.iter()
+    }
+
+    pub fn to_vec(&self) -> Vec<TelemetrySample> {
+        self.samples.iter().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+impl Default for TelemetryHistory {
+    fn default() -> Self {
+        Self::new(TELEMETRY_HISTORY_LENGTH)
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn sample(seconds: u64) -> TelemetrySample {
+        TelemetrySample {
+            time: SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+            telemetry: Telemetry { chip_temperature: seconds as f32, ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn oldest_samples_are_dropped_and_duplicates_skipped() {
+        let mut history = TelemetryHistory::new(3);
+        history.extend([sample(1), sample(2)]);
+        history.extend([sample(1), sample(2), sample(3), sample(4)]);
+
+        let times = history.samples()
+            .map(|sample| sample.telemetry.chip_temperature)
+            .collect::<Vec<_>>();
+
+        assert_eq!(times, vec![2.0, 3.0, 4.0]);
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::SystemTime;
+
+use ccdi_imager_interface::{ExposureParams, ExposureArea, Binning, FrameType};
+
+use crate::{RawImage, ImageMetadata};
+
+// ============================================ PUBLIC =============================================
+
+/// Unbinned one second light exposure of the given size at the chip origin
+pub fn test_exposure_params(width: usize, height: usize) -> ExposureParams {
+    ExposureParams {
+        gain: 0,
+        time: 1.0,
+        area: ExposureArea { x: 0, y: 0, width, height },
+        binning: Binning::default(),
+        read_mode: None,
+        frame_type: FrameType::Light,
+        start_time: SystemTime::UNIX_EPOCH,
+    }
+}
+
+/// Image of the exposure given by `test_exposure_params` with default metadata
+pub fn test_image(width: usize, height: usize, data: Vec<u16>) -> RawImage {
+    RawImage {
+        params: test_exposure_params(width, height),
+        metadata: ImageMetadata::default(),
+        data,
+    }
+}
@@ -0,0 +1,132 @@
+use std::{fmt, os::raw::{c_char, c_int}};
+
+use crate::{api::GBP_CONNECTED, ffi::{CameraApi, buffer_text}};
+
+// ============================================ PUBLIC =============================================
+
+/// Low level camera driver error
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CameraError {
+    /// Camera could not be initialized, gxccd gives no reason without a camera instance
+    ConnectFailed,
+    /// Camera was released by `CameraDriver::close`
+    Closed,
+    UnableToConvertCString,
+    /// Camera call failed or was rejected before calling the camera
+    Call { kind: ErrorKind, message: String },
+}
+
+/// Error classes deciding how the failure is handled
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    /// Camera is not reachable anymore
+    Disconnected,
+    /// Camera did not respond in time
+    Timeout,
+    /// Camera does not support the requested value or feature
+    InvalidParameter,
+    /// Camera or its USB interface is occupied
+    Busy,
+    Other,
+}
+
+impl CameraError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CameraError::ConnectFailed | CameraError::Closed => ErrorKind::Disconnected,
+            CameraError::UnableToConvertCString => ErrorKind::Other,
+            CameraError::Call { kind, .. } => *kind,
+        }
+    }
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::ConnectFailed => write!(formatter, "Camera could not be initialized"),
+            CameraError::Closed => write!(formatter, "Camera was closed"),
+            CameraError::UnableToConvertCString => write!(formatter, "Invalid camera string"),
+            CameraError::Call { message, .. } => write!(formatter, "{}", message),
+        }
+    }
+}
+
+/// Error of a failed call described by the last error message of the camera, the call
+/// failed because of a disconnect when the camera does not report being connected
+pub fn last_error(api: &dyn CameraApi) -> CameraError {
+    const MAX_LEN: usize = 256;
+    let mut buffer: [c_char; MAX_LEN] = [0; MAX_LEN];
+    api.get_last_error(&mut buffer);
+    let message = buffer_text(&buffer).unwrap_or_default().trim().to_owned();
+
+    let message = match message.is_empty() {
+        true => String::from("Unknown camera error"),
+        false => message,
+    };
+
+    // Checked after reading the message, the check replaces the last error
+    let kind = match is_connected(api) {
+        false => ErrorKind::Disconnected,
+        true => classify(&message),
+    };
+
+    CameraError::Call { kind, message }
+}
+
+/// Request rejected by the driver before calling the camera
+pub fn invalid_parameter(message: String) -> CameraError {
+    CameraError::Call { kind: ErrorKind::InvalidParameter, message }
+}
+
+/// Gxccd calls return zero on success
+pub fn check_result(api: &dyn CameraApi, result: i32) -> Result<(), CameraError> {
+    match result {
+        0 => Ok(()),
+        _ => Err(last_error(api)),
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+fn is_connected(api: &dyn CameraApi) -> bool {
+    let mut connected = false;
+    api.get_boolean_parameter(GBP_CONNECTED as c_int, &mut connected) == 0 && connected
+}
+
+/// Failure of a connected camera, gxccd reports no error codes, so libusb error names
+/// and gxccd messages are the only hint. Invalid parameters known to the driver are
+/// rejected before the call, other rejections are recognized by the message.
+fn classify(message: &str) -> ErrorKind {
+    let message = message.to_lowercase();
+    let contains = |patterns: &[&str]| patterns.iter().any(|pattern| message.contains(pattern));
+
+    if contains(&["timeout", "timed out"]) {
+        ErrorKind::Timeout
+    } else if contains(&["busy"]) {
+        ErrorKind::Busy
+    } else if contains(&["invalid", "not supported", "not implemented", "does not have"]) {
+        ErrorKind::InvalidParameter
+    } else {
+        ErrorKind::Other
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gxccd_messages_are_classified() {
+        assert_eq!(classify("Invalid binning"), ErrorKind::InvalidParameter);
+        assert_eq!(classify("Camera does not have filters"), ErrorKind::InvalidParameter);
+        assert_eq!(classify("initialize(): Connection timeout"), ErrorKind::Timeout);
+        assert_eq!(classify("Out of memory"), ErrorKind::Other);
+
+        let transfer = "libusb_bulk_transfer() failed with res = -7, LIBUSB_ERROR_TIMEOUT";
+        assert_eq!(classify(transfer), ErrorKind::Timeout);
+        let transfer = "libusb_bulk_transfer() failed with res = -6, LIBUSB_ERROR_BUSY";
+        assert_eq!(classify(transfer), ErrorKind::Busy);
+    }
+}
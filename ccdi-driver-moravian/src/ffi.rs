@@ -0,0 +1,179 @@
+use std::{ffi::c_void, os::raw::{c_char, c_int}, sync::Mutex};
+
+use crate::api::*;
+
+// ============================================ PUBLIC =============================================
+
+/// Calls of the gxccd library for a single camera, return codes follow the library,
+/// 0 on success and -1 on error. Implemented by `GxccdCamera` and by test doubles,
+/// so that the driver logic can be tested without the library or a camera.
+///
+/// Implementations are `Send` but not `Sync`, a camera is used from one thread at a
+/// time. Callers sharing a camera between threads wrap the `CameraDriver` in a `Mutex`,
+/// which also keeps the last error of the camera paired with the call that failed.
+pub trait CameraApi: Send {
+    fn get_value(&self, index: c_int, value: &mut f32) -> c_int;
+    fn get_integer_parameter(&self, index: c_int, value: &mut c_int) -> c_int;
+    fn get_boolean_parameter(&self, index: c_int, value: &mut bool) -> c_int;
+    fn get_string_parameter(&self, index: c_int, buffer: &mut [c_char]) -> c_int;
+    fn enumerate_read_modes(&self, index: c_int, buffer: &mut [c_char]) -> c_int;
+    fn enumerate_filters(
+        &self, index: c_int, buffer: &mut [c_char], color: &mut u32, offset: &mut c_int
+    ) -> c_int;
+    fn set_filter(&self, index: c_int) -> c_int;
+    fn start_exposure(
+        &self, time: f64, use_shutter: bool, x: c_int, y: c_int, w: c_int, h: c_int
+    ) -> c_int;
+    fn abort_exposure(&self, download: bool) -> c_int;
+    fn image_ready(&self, ready: &mut bool) -> c_int;
+    fn read_image(&self, buffer: &mut [u16]) -> c_int;
+    fn set_gain(&self, gain: u16) -> c_int;
+    fn set_read_mode(&self, mode: c_int) -> c_int;
+    fn set_binning(&self, x: c_int, y: c_int) -> c_int;
+    fn set_temperature(&self, temperature: f32) -> c_int;
+    fn set_temperature_ramp(&self, ramp: f32) -> c_int;
+    fn get_last_error(&self, buffer: &mut [c_char]);
+}
+
+/// Camera instance of the gxccd library, released when dropped
+pub struct GxccdCamera {
+    camera_ptr: *mut camera_t,
+}
+
+/// The library does not bind a camera instance to the thread that created it,
+/// exclusive access is guaranteed by `CameraApi` not being `Sync`
+unsafe impl Send for GxccdCamera {}
+
+impl GxccdCamera {
+    pub fn initialize_usb(id: i32) -> Option<Self> {
+        let camera_ptr = unsafe { gxccd_initialize_usb(id) };
+
+        match camera_ptr.is_null() {
+            true => None,
+            false => Some(Self { camera_ptr }),
+        }
+    }
+}
+
+impl Drop for GxccdCamera {
+    fn drop(&mut self) {
+        unsafe { gxccd_release(self.camera_ptr) }
+    }
+}
+
+impl CameraApi for GxccdCamera {
+    fn get_value(&self, index: c_int, value: &mut f32) -> c_int {
+        unsafe { gxccd_get_value(self.camera_ptr, index, value) }
+    }
+
+    fn get_integer_parameter(&self, index: c_int, value: &mut c_int) -> c_int {
+        unsafe { gxccd_get_integer_parameter(self.camera_ptr, index, value) }
+    }
+
+    fn get_boolean_parameter(&self, index: c_int, value: &mut bool) -> c_int {
+        unsafe { gxccd_get_boolean_parameter(self.camera_ptr, index, value) }
+    }
+
+    fn get_string_parameter(&self, index: c_int, buffer: &mut [c_char]) -> c_int {
+        unsafe {
+            gxccd_get_string_parameter(self.camera_ptr, index, buffer.as_mut_ptr(), buffer.len())
+        }
+    }
+
+    fn enumerate_read_modes(&self, index: c_int, buffer: &mut [c_char]) -> c_int {
+        unsafe {
+            gxccd_enumerate_read_modes(self.camera_ptr, index, buffer.as_mut_ptr(), buffer.len())
+        }
+    }
+
+    fn enumerate_filters(
+        &self, index: c_int, buffer: &mut [c_char], color: &mut u32, offset: &mut c_int
+    ) -> c_int {
+        unsafe {
+            gxccd_enumerate_filters(
+                self.camera_ptr, index, buffer.as_mut_ptr(), buffer.len(), color, offset
+            )
+        }
+    }
+
+    fn set_filter(&self, index: c_int) -> c_int {
+        unsafe { gxccd_set_filter(self.camera_ptr, index) }
+    }
+
+    fn start_exposure(
+        &self, time: f64, use_shutter: bool, x: c_int, y: c_int, w: c_int, h: c_int
+    ) -> c_int {
+        unsafe { gxccd_start_exposure(self.camera_ptr, time, use_shutter, x, y, w, h) }
+    }
+
+    fn abort_exposure(&self, download: bool) -> c_int {
+        unsafe { gxccd_abort_exposure(self.camera_ptr, download) }
+    }
+
+    fn image_ready(&self, ready: &mut bool) -> c_int {
+        unsafe { gxccd_image_ready(self.camera_ptr, ready) }
+    }
+
+    fn read_image(&self, buffer: &mut [u16]) -> c_int {
+        let size = std::mem::size_of_val(buffer);
+        unsafe { gxccd_read_image(self.camera_ptr, buffer.as_mut_ptr() as *mut c_void, size) }
+    }
+
+    fn set_gain(&self, gain: u16) -> c_int {
+        unsafe { gxccd_set_gain(self.camera_ptr, gain) }
+    }
+
+    fn set_read_mode(&self, mode: c_int) -> c_int {
+        unsafe { gxccd_set_read_mode(self.camera_ptr, mode) }
+    }
+
+    fn set_binning(&self, x: c_int, y: c_int) -> c_int {
+        unsafe { gxccd_set_binning(self.camera_ptr, x, y) }
+    }
+
+    fn set_temperature(&self, temperature: f32) -> c_int {
+        unsafe { gxccd_set_temperature(self.camera_ptr, temperature) }
+    }
+
+    fn set_temperature_ramp(&self, ramp: f32) -> c_int {
+        unsafe { gxccd_set_temperature_ramp(self.camera_ptr, ramp) }
+    }
+
+    fn get_last_error(&self, buffer: &mut [c_char]) {
+        unsafe { gxccd_get_last_error(self.camera_ptr, buffer.as_mut_ptr(), buffer.len()) }
+    }
+}
+
+/// Ids of all cameras connected by USB, the C callback collects them into a static
+/// list, so that enumerations from several threads are serialized
+pub fn read_raw_camera_ids() -> Vec<i32> {
+    let _lock = ENUMERATION.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    unsafe {
+        reset_camera_ids();
+        gxccd_enumerate_usb(Some(enumerate_usb_callback));
+        (0..get_camera_count()).map(|index| get_camera_id(index)).collect()
+    }
+}
+
+/// Text of a buffer filled by the library, the whole buffer is used when the
+/// terminating NUL is missing, `None` when the text is not valid UTF-8
+pub fn buffer_text(buffer: &[c_char]) -> Option<String> {
+    let bytes = buffer.iter()
+        .take_while(|&&char| char != 0)
+        .map(|&char| char as u8)
+        .collect();
+
+    String::from_utf8(bytes).ok()
+}
+
+// =========================================== PRIVATE =============================================
+
+static ENUMERATION: Mutex<()> = Mutex::new(());
+
+extern "C" {
+    fn enumerate_usb_callback(arg1: c_int);
+    fn get_camera_count() -> c_int;
+    fn get_camera_id(index: c_int) -> c_int;
+    fn reset_camera_ids();
+}
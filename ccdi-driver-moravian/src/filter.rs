@@ -0,0 +1,64 @@
+use std::os::raw::c_char;
+use super::*;
+
+/// Filter of the filter wheel controlled by the camera
+#[derive(Clone, PartialEq, Debug)]
+pub struct FilterInfo {
+    pub name: String,
+    /// Color hint for displaying the filter name, 0xRRGGBB
+    pub color: u32,
+    /// Focuser shift when the filter is selected, micrometers or focuser steps
+    pub offset: i32,
+}
+
+pub fn enumerate_filters(api: &dyn CameraApi) -> Result<Vec<FilterInfo>, CameraError> {
+    const MAX_LEN: usize = 256;
+    let mut result: Vec<FilterInfo> = Vec::new();
+
+    // Driver returns -1 for the first index past the last filter
+    for index in 0.. {
+        let mut buffer: [c_char; MAX_LEN] = [0; MAX_LEN];
+        let mut color: u32 = 0;
+        let mut offset: i32 = 0;
+
+        if api.enumerate_filters(index, &mut buffer, &mut color, &mut offset) != 0 {
+            break
+        }
+
+        let name = buffer_text(&buffer).ok_or(CameraError::UnableToConvertCString)?;
+        result.push(FilterInfo { name, color, offset })
+    }
+
+    Ok(result)
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{MockCamera, MockState};
+
+    use super::*;
+
+    fn filters(names: &[&str]) -> Result<Vec<FilterInfo>, CameraError> {
+        let filters = names.iter().map(|name| name.to_string()).collect();
+        let (camera, _state) = MockCamera::new(MockState { filters, ..Default::default() });
+        enumerate_filters(&camera)
+    }
+
+    fn filter(name: &str, offset: i32) -> FilterInfo {
+        FilterInfo { name: name.to_string(), color: 0xff0000, offset }
+    }
+
+    #[test]
+    fn all_filters_are_enumerated_with_offsets() {
+        assert_eq!(filters(&["L", "R", "Ha"]), Ok(vec![
+            filter("L", 0), filter("R", 10), filter("Ha", 20)
+        ]));
+    }
+
+    #[test]
+    fn camera_without_filters_returns_empty_list() {
+        assert_eq!(filters(&[]), Ok(vec![]));
+    }
+}
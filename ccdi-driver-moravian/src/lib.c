@@ -1,13 +1,22 @@
-static int last_camera_id = -1;
+#define MAX_CAMERAS 16
+
+static int camera_ids[MAX_CAMERAS];
+static int camera_count = 0;
 
 void enumerate_usb_callback(int camera_id) {
-    last_camera_id = camera_id;
+    if (camera_count < MAX_CAMERAS) {
+        camera_ids[camera_count++] = camera_id;
+    }
+}
+
+int get_camera_count() {
+    return camera_count;
 }
 
-int get_last_camera_id() {
-    return last_camera_id;
+int get_camera_id(int index) {
+    return (index >= 0 && index < camera_count) ? camera_ids[index] : -1;
 }
 
-void reset_last_camera_id() {
-    last_camera_id = -1;
-}
\ No newline at end of file
+void reset_camera_ids() {
+    camera_count = 0;
+}
@@ -1,42 +1,63 @@
 mod api;
+mod ffi;
 mod read;
 mod read_mode;
+mod filter;
 mod image;
+mod error;
+#[cfg(test)]
+mod mock;
 
-use std::ffi::c_void;
-
-use crate::api::*;
+use api::*;
 use read::*;
 use read_mode::enumerate_read_modes;
+use filter::enumerate_filters;
+use error::{check_result, invalid_parameter};
+use ffi::{GxccdCamera, read_raw_camera_ids, buffer_text};
+
+pub use ffi::CameraApi;
+pub use filter::FilterInfo;
+pub use error::{CameraError, ErrorKind};
 
 // ============================================ PUBLIC =============================================
 
+/// Connected camera, released when closed or dropped. The driver is `Send` but not
+/// `Sync`, see `CameraApi` for sharing it between threads.
 pub struct CameraDriver {
-    camera_ptr: *mut camera_t
+    api: Option<Box<dyn CameraApi>>,
 }
 
-/// Low level camera driver
-#[derive(Debug)]
-pub enum CameraError {
-    Unspecified,
-    UnableToConvertCString,
+pub fn get_any_camera_id() -> Option<i32> {
+    enumerate_usb_cameras().into_iter().next()
 }
 
-pub fn get_any_camera_id() -> Option<i32> {
-    let raw_id = read_raw_camera_id();
-    if raw_id < 0 { None } else { Some(raw_id) }
+/// Ids of all connected USB cameras, the id identifies the camera across reconnections
+pub fn enumerate_usb_cameras() -> Vec<i32> {
+    read_raw_camera_ids().into_iter().filter(|id| *id >= 0).collect()
 }
 
 pub fn connect_usb_camera(id: i32) -> Result<CameraDriver, CameraError> {
-    let camera_ptr = unsafe { gxccd_initialize_usb(id) };
-
-    match camera_ptr.is_null() {
-        true => Err(CameraError::Unspecified),
-        false => Ok(CameraDriver { camera_ptr })
-    }
+    GxccdCamera::initialize_usb(id)
+        .map(|camera| CameraDriver::new(Box::new(camera)))
+        .ok_or(CameraError::ConnectFailed)
 }
 
 impl CameraDriver {
+    /// Driver calling the given implementation of the library, used by tests
+    pub fn new(api: Box<dyn CameraApi>) -> Self {
+        Self { api: Some(api) }
+    }
+
+    /// Release the camera, later calls fail with `CameraError::Closed`,
+    /// closing an already closed camera does nothing
+    pub fn close(&mut self) {
+        self.api = None;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.api.is_none()
+    }
+
     read_float_value_fn!(read_chip_temperature, GV_CHIP_TEMPERATURE);
     read_float_value_fn!(read_hot_temperature, GV_HOT_TEMPERATURE);
     read_float_value_fn!(read_camera_temperature, GV_CAMERA_TEMPERATURE);
@@ -51,61 +72,232 @@ impl CameraDriver {
     read_int_value_fn!(read_min_exposure, GIP_MINIMAL_EXPOSURE);
     read_int_value_fn!(read_max_exposure, GIP_MAXIMAL_EXPOSURE);
     read_int_value_fn!(read_max_gain, GIP_MAX_GAIN);
+    read_int_value_fn!(read_pixel_width, GIP_PIXEL_W);
+    read_int_value_fn!(read_pixel_height, GIP_PIXEL_D);
+    read_int_value_fn!(read_max_binning_x, GIP_MAX_BINNING_X);
+    read_int_value_fn!(read_max_binning_y, GIP_MAX_BINNING_Y);
+    read_int_value_fn!(read_read_mode_count, GIP_READ_MODES);
+    read_int_value_fn!(read_filter_count, GIP_FILTERS);
+
+    read_string_value_fn!(read_camera_description, GSP_CAMERA_DESCRIPTION);
+    read_string_value_fn!(read_camera_serial, GSP_CAMERA_SERIAL);
+
+    read_bool_value_fn!(read_is_color, GBP_RGB);
+    read_bool_value_fn!(read_debayer_x_odd, GBP_DEBAYER_X_ODD);
+    read_bool_value_fn!(read_debayer_y_odd, GBP_DEBAYER_Y_ODD);
+    read_bool_value_fn!(read_has_filters, GBP_FILTERS);
+    read_bool_value_fn!(read_has_power_utilization, GBP_POWER_UTILIZATION);
 
     pub fn enumerate_read_modes(&self) -> Result<Vec<String>, CameraError> {
-        enumerate_read_modes(self.camera_ptr)
+        enumerate_read_modes(self.api()?)
+    }
+
+    /// Filters of the filter wheel, empty when the camera does not control a filter wheel
+    pub fn enumerate_filters(&self) -> Result<Vec<FilterInfo>, CameraError> {
+        match self.read_has_filters()? {
+            false => Ok(vec![]),
+            true => enumerate_filters(self.api()?),
+        }
+    }
+
+    /// Filter is the index of the filter returned by `enumerate_filters`
+    pub fn set_filter(&self, filter: usize) -> Result<(), CameraError> {
+        check_range("filter", filter, 0, self.read_filter_count()? - 1)?;
+        self.call(|api| api.set_filter(filter as i32))
     }
 
     pub fn start_exposure(
         &self, time: f64, use_shutter: bool, x: usize, y: usize, w: usize, h: usize
     ) -> Result<(), CameraError> {
-        let result = unsafe {
-            gxccd_start_exposure(
-                self.camera_ptr,  time, use_shutter, x as i32, y as i32, w as i32, h as i32
-            )
-        };
+        self.call(|api| {
+            api.start_exposure(time, use_shutter, x as i32, y as i32, w as i32, h as i32)
+        })
+    }
 
-        if result == 0 { Ok(()) } else { Err(CameraError::Unspecified) }
+    /// Abort current exposure, image is downloaded into the camera buffer if `download`
+    /// is true, otherwise it is discarded
+    pub fn abort_exposure(&self, download: bool) -> Result<(), CameraError> {
+        self.call(|api| api.abort_exposure(download))
     }
 
     pub fn image_ready(&self) -> Result<bool, CameraError> {
-        let mut image_ready: [bool; 1] = [false; 1];
-        let result = unsafe { gxccd_image_ready(self.camera_ptr, image_ready.as_mut_ptr()) };
-        if result == 0 { Ok(image_ready[0]) } else { Err(CameraError::Unspecified) }
+        let mut ready = false;
+        self.call(|api| api.image_ready(&mut ready))?;
+        Ok(ready)
     }
 
     pub fn read_image(&self, pixel_count: usize) -> Result<Vec<u16>, CameraError> {
         let mut buffer = vec![0u16; pixel_count];
+        self.call(|api| api.read_image(&mut buffer))?;
+        Ok(buffer)
+    }
 
-        let result = unsafe {
-            gxccd_read_image(
-                self.camera_ptr, buffer.as_mut_ptr() as *mut c_void, 2*buffer.len()
-            )
-        };
-        if result == 0 { Ok(buffer) } else { Err(CameraError::Unspecified) }
+    pub fn set_gain(&self, gain: u16) -> Result<(), CameraError> {
+        self.call(|api| api.set_gain(gain))
     }
 
-    pub fn disconnect(self) {
-        unsafe { gxccd_release(self.camera_ptr); }
+    /// Mode is the index of the mode returned by `enumerate_read_modes`
+    pub fn set_read_mode(&self, mode: usize) -> Result<(), CameraError> {
+        check_range("read mode", mode, 0, self.read_read_mode_count()? - 1)?;
+        self.call(|api| api.set_read_mode(mode as i32))
     }
 
-    pub fn set_gain(&self, gain: u16) -> Result<(), CameraError> {
-        convert_simple(unsafe { gxccd_set_gain(self.camera_ptr, gain) })
+    pub fn set_binning(&self, x: usize, y: usize) -> Result<(), CameraError> {
+        check_range("horizontal binning", x, 1, self.read_max_binning_x()?)?;
+        check_range("vertical binning", y, 1, self.read_max_binning_y()?)?;
+        self.call(|api| api.set_binning(x as i32, y as i32))
     }
 
     pub fn set_temperature(&self, temperature: f32) -> Result<(), CameraError> {
-        convert_simple(unsafe { gxccd_set_temperature(self.camera_ptr, temperature) })
+        self.call(|api| api.set_temperature(temperature))
     }
 
     pub fn set_temperature_ramp(&self, deg_per_minute: f32) -> Result<(), CameraError> {
-        convert_simple(unsafe { gxccd_set_temperature_ramp(self.camera_ptr, deg_per_minute) })
+        self.call(|api| api.set_temperature_ramp(deg_per_minute))
+    }
+
+    /// gxccd has no call switching the cooler off, it only regulates the chip towards the
+    /// target of `gxccd_set_temperature` at the speed of `gxccd_set_temperature_ramp`.
+    /// With the target at ambient the cooler is not driven anymore once the chip got there,
+    /// a target above ambient could make the regulation heat the chip.
+    pub fn cooler_off(&self, ambient: f32, deg_per_minute: f32) -> Result<(), CameraError> {
+        self.set_temperature_ramp(deg_per_minute)?;
+        self.set_temperature(ambient)
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+impl CameraDriver {
+    fn api(&self) -> Result<&dyn CameraApi, CameraError> {
+        self.api.as_deref().ok_or(CameraError::Closed)
+    }
+
+    /// Failed calls are described by the last error of the camera
+    fn call(&self, action: impl FnOnce(&dyn CameraApi) -> i32) -> Result<(), CameraError> {
+        let api = self.api()?;
+        check_result(api, action(api))
     }
 }
 
-fn convert_simple(result: i32) -> Result<(), CameraError> {
-    convert_result((), result)
+/// Values the camera does not support are rejected without calling it
+fn check_range(name: &str, value: usize, min: i32, max: i32) -> Result<(), CameraError> {
+    match i32::try_from(value).is_ok_and(|value| (min..=max).contains(&value)) {
+        true => Ok(()),
+        false => Err(invalid_parameter(format!("Invalid {} {}", name, value))),
+    }
 }
 
-fn convert_result<T>(value: T, result: i32) -> Result<T, CameraError> {
-    if result == 0 { Ok(value) } else { Err(CameraError::Unspecified) }
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::mock::{MockCamera, MockState};
+
+    use super::*;
+
+    fn driver(state: MockState) -> (CameraDriver, Arc<Mutex<MockState>>) {
+        let (camera, state) = MockCamera::new(state);
+        (CameraDriver::new(Box::new(camera)), state)
+    }
+
+    fn released(state: &Mutex<MockState>) -> usize {
+        state.lock().unwrap().released
+    }
+
+    #[test]
+    fn camera_is_released_once() {
+        let (mut camera, state) = driver(Default::default());
+        assert_eq!(released(&state), 0);
+
+        camera.close();
+        camera.close();
+        assert_eq!(released(&state), 1);
+        assert_eq!(camera.set_gain(10), Err(CameraError::Closed));
+
+        drop(camera);
+        assert_eq!(released(&state), 1);
+
+        let (camera, state) = driver(Default::default());
+        drop(camera);
+        assert_eq!(released(&state), 1);
+    }
+
+    #[test]
+    fn driver_can_be_moved_to_another_thread() {
+        let (camera, state) = driver(MockState { image: vec![1, 2, 3], ..Default::default() });
+        let image = std::thread::spawn(move || camera.read_image(4)).join().unwrap();
+        assert_eq!(image, Ok(vec![1, 2, 3, 0]));
+        assert_eq!(released(&state), 1);
+    }
+
+    #[test]
+    fn failed_call_reports_last_error() {
+        let failure = Some(String::from("Camera was unplugged"));
+        let (camera, _state) = driver(MockState { failure, unplugged: true, ..Default::default() });
+        let error = camera.start_exposure(1.0, true, 0, 0, 10, 10).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Disconnected);
+        assert_eq!(error.to_string(), "Camera was unplugged");
+
+        let failure = Some(String::from("libusb_bulk_transfer() failed, LIBUSB_ERROR_TIMEOUT"));
+        let (camera, _state) = driver(MockState { failure, ..Default::default() });
+        assert_eq!(camera.image_ready().unwrap_err().kind(), ErrorKind::Timeout);
+
+        let (camera, _state) = driver(MockState { image: vec![0; 10], ..Default::default() });
+        assert_eq!(camera.read_image(4).unwrap_err().to_string(), "Image buffer too short");
+    }
+
+    #[test]
+    fn unsupported_values_are_rejected_before_the_call() {
+        let (camera, _state) = driver(MockState {
+            integers: [
+                (GIP_MAX_BINNING_X as i32, 4), (GIP_MAX_BINNING_Y as i32, 4),
+                (GIP_READ_MODES as i32, 2), (GIP_FILTERS as i32, 0),
+            ].into(),
+            read_modes: vec![String::from("Normal"), String::from("Low Noise")],
+            ..Default::default()
+        });
+
+        assert_eq!(camera.set_binning(4, 1), Ok(()));
+        assert_eq!(camera.set_read_mode(1), Ok(()));
+
+        for error in [camera.set_binning(5, 1), camera.set_read_mode(2), camera.set_filter(0)] {
+            assert_eq!(error.unwrap_err().kind(), ErrorKind::InvalidParameter);
+        }
+
+        let error = camera.set_binning(0, 1).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid horizontal binning 0");
+    }
+
+    #[test]
+    fn parameters_are_read() {
+        let (camera, _state) = driver(MockState {
+            values: [(GV_CHIP_TEMPERATURE as i32, -10.5)].into(),
+            integers: [(GIP_CHIP_W as i32, 9576)].into(),
+            booleans: [(GBP_FILTERS as i32, true)].into(),
+            strings: [
+                (GSP_CAMERA_DESCRIPTION as i32, String::from("C3-61000 Pro  ")),
+                (GSP_CAMERA_SERIAL as i32, String::from("3210")),
+            ].into(),
+            read_modes: vec![String::from("Normal"), String::from("Low Noise")],
+            filters: vec![String::from("L"), String::from("Ha")],
+            ..Default::default()
+        });
+
+        assert_eq!(camera.read_chip_temperature(), Ok(-10.5));
+        assert_eq!(camera.read_chip_width(), Ok(9576));
+        assert_eq!(camera.read_camera_description(), Ok(String::from("C3-61000 Pro")));
+        assert_eq!(camera.read_camera_serial(), Ok(String::from("3210")));
+        assert_eq!(camera.read_chip_height().unwrap_err().to_string(), "Invalid index");
+
+        let modes = camera.enumerate_read_modes().unwrap();
+        assert_eq!(modes, vec![String::from("Normal"), String::from("Low Noise")]);
+
+        let filters = camera.enumerate_filters().unwrap();
+        let names = filters.iter().map(|filter| filter.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["L", "Ha"]);
+        assert_eq!(filters[1].offset, 10);
+    }
 }
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, os::raw::{c_char, c_int}, sync::{Arc, Mutex, MutexGuard}};
+
+use crate::{api::GBP_CONNECTED, ffi::CameraApi};
+
+// ============================================ PUBLIC =============================================
+
+/// Camera answering gxccd calls from a state shared with the test
+pub struct MockCamera {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+pub struct MockState {
+    pub values: HashMap<c_int, f32>,
+    pub integers: HashMap<c_int, c_int>,
+    pub booleans: HashMap<c_int, bool>,
+    pub strings: HashMap<c_int, String>,
+    pub read_modes: Vec<String>,
+    pub filters: Vec<String>,
+    pub image: Vec<u16>,
+    /// Every call fails with this error when set
+    pub failure: Option<String>,
+    /// Camera reports not being connected, calls fail only with `failure`
+    pub unplugged: bool,
+    pub last_error: String,
+    /// Number of times the camera was released
+    pub released: usize,
+}
+
+impl MockCamera {
+    pub fn new(state: MockState) -> (Self, Arc<Mutex<MockState>>) {
+        let state = Arc::new(Mutex::new(state));
+        (Self { state: state.clone() }, state)
+    }
+}
+
+impl Drop for MockCamera {
+    fn drop(&mut self) {
+        self.state().released += 1;
+    }
+}
+
+impl CameraApi for MockCamera {
+    fn get_value(&self, index: c_int, value: &mut f32) -> c_int {
+        self.answer(|state| {
+            *value = *state.values.get(&index).ok_or("Invalid index")?;
+            Ok(())
+        })
+    }
+
+    fn get_integer_parameter(&self, index: c_int, value: &mut c_int) -> c_int {
+        self.answer(|state| {
+            *value = *state.integers.get(&index).ok_or("Invalid index")?;
+            Ok(())
+        })
+    }
+
+    fn get_boolean_parameter(&self, index: c_int, value: &mut bool) -> c_int {
+        // Connection state is reported also while other calls fail
+        if index == GBP_CONNECTED as c_int {
+            *value = !self.state().unplugged;
+            return 0
+        }
+
+        self.answer(|state| {
+            *value = *state.booleans.get(&index).ok_or("Invalid index")?;
+            Ok(())
+        })
+    }
+
+    fn get_string_parameter(&self, index: c_int, buffer: &mut [c_char]) -> c_int {
+        self.answer(|state| {
+            write_text(state.strings.get(&index).ok_or("Invalid index")?, buffer);
+            Ok(())
+        })
+    }
+
+    fn enumerate_read_modes(&self, index: c_int, buffer: &mut [c_char]) -> c_int {
+        self.answer(|state| {
+            write_text(item(&state.read_modes, index)?, buffer);
+            Ok(())
+        })
+    }
+
+    fn enumerate_filters(
+        &self, index: c_int, buffer: &mut [c_char], color: &mut u32, offset: &mut c_int
+    ) -> c_int {
+        self.answer(|state| {
+            write_text(item(&state.filters, index)?, buffer);
+            *color = 0xff0000;
+            *offset = index*10;
+            Ok(())
+        })
+    }
+
+    fn set_filter(&self, index: c_int) -> c_int {
+        self.answer(|state| item(&state.filters, index).map(|_| ()))
+    }
+
+    fn start_exposure(
+        &self, _time: f64, _use_shutter: bool, _x: c_int, _y: c_int, _w: c_int, _h: c_int
+    ) -> c_int {
+        self.answer(|_| Ok(()))
+    }
+
+    fn abort_exposure(&self, _download: bool) -> c_int {
+        self.answer(|_| Ok(()))
+    }
+
+    fn image_ready(&self, ready: &mut bool) -> c_int {
+        self.answer(|_| {
+            *ready = true;
+            Ok(())
+        })
+    }
+
+    fn read_image(&self, buffer: &mut [u16]) -> c_int {
+        self.answer(|state| {
+            let target = buffer.get_mut(..state.image.len()).ok_or("Image buffer too short")?;
+            target.copy_from_slice(&state.image);
+            Ok(())
+        })
+    }
+
+    fn set_gain(&self, _gain: u16) -> c_int {
+        self.answer(|_| Ok(()))
+    }
+
+    fn set_read_mode(&self, mode: c_int) -> c_int {
+        self.answer(|state| item(&state.read_modes, mode).map(|_| ()))
+    }
+
+    fn set_binning(&self, _x: c_int, _y: c_int) -> c_int {
+        self.answer(|_| Ok(()))
+    }
+
+    fn set_temperature(&self, _temperature: f32) -> c_int {
+        self.answer(|_| Ok(()))
+    }
+
+    fn set_temperature_ramp(&self, _ramp: f32) -> c_int {
+        self.answer(|_| Ok(()))
+    }
+
+    fn get_last_error(&self, buffer: &mut [c_char]) {
+        write_text(&self.state().last_error, buffer);
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+impl MockCamera {
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Run the call and store its error as the last error, as the library does
+    fn answer(&self, call: impl FnOnce(&mut MockState) -> Result<(), &'static str>) -> c_int {
+        let mut state = self.state();
+
+        let result = match state.failure.clone() {
+            Some(failure) => Err(failure),
+            None => call(&mut state).map_err(String::from),
+        };
+
+        match result {
+            Ok(()) => 0,
+            Err(error) => {
+                state.last_error = error;
+                -1
+            }
+        }
+    }
+}
+
+fn item(items: &[String], index: c_int) -> Result<&String, &'static str> {
+    usize::try_from(index).ok().and_then(|index| items.get(index)).ok_or("Invalid index")
+}
+
+/// Copy the text with the terminating NUL, truncated to the buffer like the library does
+fn write_text(text: &str, buffer: &mut [c_char]) {
+    let length = text.len().min(buffer.len().saturating_sub(1));
+
+    for (target, byte) in buffer.iter_mut().zip(text.bytes().take(length)) {
+        *target = byte as c_char;
+    }
+
+    if let Some(terminator) = buffer.get_mut(length) {
+        *terminator = 0;
+    }
+}
@@ -1,69 +1,69 @@
-use crate::CameraError;
-use crate::api::*;
-use std::os::raw::c_int;
+use crate::{CameraError, error::check_result, ffi::{CameraApi, buffer_text}};
+use std::os::raw::{c_int, c_char};
 
 // ============================================ PUBLIC =============================================
 
-extern "C" {
-    fn enumerate_usb_callback(arg1: c_int);
-    fn get_last_camera_id() -> c_int;
-    fn reset_last_camera_id();
-}
-
-pub fn read_raw_camera_id() -> i32 {
-    unsafe {
-        reset_last_camera_id();
-        gxccd_enumerate_usb(Some(enumerate_usb_callback));
-        get_last_camera_id()
-    }
-}
-
 #[macro_export]
 macro_rules! read_float_value_fn {
     ($name: ident, $register: expr) => {
         pub fn $name(&self) -> Result<f32, CameraError> {
-            read_float_value(self.camera_ptr, $register)
+            read_float_value(self.api()?, $register)
         }
     };
 }
 
-pub fn read_float_value(
-    camera_ptr: *mut camera_t,
-    register: u32
-) -> Result<f32, CameraError> {
+pub fn read_float_value(api: &dyn CameraApi, register: u32) -> Result<f32, CameraError> {
     let mut value: f32 = 0.0;
-
-    let code = unsafe {
-        gxccd_get_value(camera_ptr, register as c_int, &mut value)
-    };
-
-    match code {
-        0 => Ok(value),
-        _other => Err(CameraError::Unspecified),
-    }
+    let code = api.get_value(register as c_int, &mut value);
+    check_result(api, code).map(|_| value)
 }
 
 #[macro_export]
 macro_rules! read_int_value_fn {
     ($name: ident, $register: expr) => {
         pub fn $name(&self) -> Result<i32, CameraError> {
-            read_int_value(self.camera_ptr, $register)
+            read_int_value(self.api()?, $register)
         }
     };
 }
 
-pub fn read_int_value(
-    camera_ptr: *mut camera_t,
-    register: u32
-) -> Result<i32, CameraError> {
+pub fn read_int_value(api: &dyn CameraApi, register: u32) -> Result<i32, CameraError> {
     let mut value: c_int = 0;
+    let code = api.get_integer_parameter(register as c_int, &mut value);
+    check_result(api, code).map(|_| value)
+}
+
+#[macro_export]
+macro_rules! read_bool_value_fn {
+    ($name: ident, $register: expr) => {
+        pub fn $name(&self) -> Result<bool, CameraError> {
+            read_bool_value(self.api()?, $register)
+        }
+    };
+}
 
-    let code = unsafe {
-        gxccd_get_integer_parameter(camera_ptr, register as c_int, &mut value)
+pub fn read_bool_value(api: &dyn CameraApi, register: u32) -> Result<bool, CameraError> {
+    let mut value = false;
+    let code = api.get_boolean_parameter(register as c_int, &mut value);
+    check_result(api, code).map(|_| value)
+}
+
+#[macro_export]
+macro_rules! read_string_value_fn {
+    ($name: ident, $register: expr) => {
+        pub fn $name(&self) -> Result<String, CameraError> {
+            read_string_value(self.api()?, $register)
+        }
     };
+}
 
-    match code {
-        0 => Ok(value),
-        _other => Err(CameraError::Unspecified),
-    }
-}
\ No newline at end of file
+pub fn read_string_value(api: &dyn CameraApi, register: u32) -> Result<String, CameraError> {
+    const MAX_LEN: usize = 256;
+    let mut buffer: [c_char; MAX_LEN] = [0; MAX_LEN];
+    let code = api.get_string_parameter(register as c_int, &mut buffer);
+    check_result(api, code)?;
+
+    buffer_text(&buffer)
+        .map(|value| value.trim().to_owned())
+        .ok_or(CameraError::UnableToConvertCString)
+}
@@ -1,38 +1,49 @@
-use std::{os::raw::c_char, ffi::CStr};
+use std::os::raw::c_char;
 use super::*;
 
-pub fn enumerate_read_modes(camera_ptr: *mut camera_t) -> Result<Vec<String>, CameraError> {
+/// Driver returns -1 for the first index past the last read mode
+pub fn enumerate_read_modes(api: &dyn CameraApi) -> Result<Vec<String>, CameraError> {
     const MAX_LEN: usize = 256;
     let mut result: Vec<String> = Vec::new();
 
-    let mut index = 0;
-    let mut read_result = 1;
-
-    while read_result > 0 {
+    for index in 0.. {
         let mut buffer: [c_char; MAX_LEN] = [0; MAX_LEN];
 
-        read_result = unsafe {
-            gxccd_enumerate_read_modes(
-                camera_ptr,
-                index,
-                buffer.as_mut_ptr(),
-                buffer.len()
-            )
-        };
+        if api.enumerate_read_modes(index, &mut buffer) != 0 {
+            break
+        }
+
+        result.push(buffer_text(&buffer).ok_or(CameraError::UnableToConvertCString)?)
+    }
 
-        index += 1;
+    Ok(result)
+}
 
-        if read_result != 0 {
-            let c_str = unsafe { CStr::from_ptr(buffer.as_ptr())};
+// ============================================= TEST ==============================================
 
-            let rust_str = match c_str.to_str() {
-                Ok(value) => value,
-                Err(_) => return Err(CameraError::UnableToConvertCString)
-            };
+#[cfg(test)]
+mod tests {
+    use crate::mock::{MockCamera, MockState};
 
-            result.push(rust_str.to_owned())
-        }
+    use super::*;
+
+    fn read_modes(names: &[&str]) -> Result<Vec<String>, CameraError> {
+        let read_modes = names.iter().map(|name| name.to_string()).collect();
+        let (camera, _state) = MockCamera::new(MockState { read_modes, ..Default::default() });
+        enumerate_read_modes(&camera)
     }
 
-    Ok(result)
-}
\ No newline at end of file
+    #[test]
+    fn all_read_modes_are_enumerated() {
+        // Library returns 0 for every read mode, not the number of modes left
+        let modes = read_modes(&["Normal", "Low Noise", "Fast"]);
+        assert_eq!(modes, Ok(vec![
+            String::from("Normal"), String::from("Low Noise"), String::from("Fast")
+        ]));
+    }
+
+    #[test]
+    fn camera_without_read_modes_returns_empty_list() {
+        assert_eq!(read_modes(&[]), Ok(vec![]));
+    }
+}
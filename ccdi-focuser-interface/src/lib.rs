@@ -0,0 +1,41 @@
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+pub trait FocuserDriver {
+    fn list_devices(&mut self) -> Result<Vec<FocuserDescriptor>, String>;
+    fn connect_device(&mut self, descriptor: &FocuserDescriptor) -> Result<Box<dyn FocuserDevice>, String>;
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FocuserDescriptor {
+    pub id: i32,
+    pub name: String,
+}
+
+pub trait FocuserDevice {
+    fn read_properties(&mut self) -> Result<FocuserProperties, String>;
+    fn read_status(&mut self) -> Result<FocuserStatus, String>;
+    /// Start moving to the absolute position in focuser steps, returns immediately
+    fn move_to(&mut self, position: i32) -> Result<(), String>;
+    /// Stop any movement in progress
+    fn halt(&mut self) -> Result<(), String>;
+    fn close(&mut self);
+}
+
+/// Focuser description that does not change while connected
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FocuserProperties {
+    pub name: String,
+    /// Highest reachable position, lowest position is zero
+    pub max_position: i32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FocuserStatus {
+    /// Current position in focuser steps
+    pub position: i32,
+    pub moving: bool,
+    /// Focuser temperature in degrees celsius, if the focuser has a sensor
+    pub temperature: Option<f32>,
+}
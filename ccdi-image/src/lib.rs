@@ -2,6 +2,9 @@ mod raw_to_bmp;
 mod stats;
 mod plot;
 
-pub use raw_to_bmp::{rgb_image_to_bmp, Transform, TransformFunction};
-pub use plot::render_histogram_as_bmp;
+pub use raw_to_bmp::{preview_image_to_bmp, Transform, TransformFunction};
+pub use plot::{
+    render_histogram_as_bmp, render_focus_plot_as_bmp, render_autofocus_plot_as_bmp,
+    render_temperature_plot_as_bmp, render_cooler_plot_as_bmp
+};
 pub use stats::*;
\ No newline at end of file
@@ -1,30 +1,103 @@
-use ccdi_common::to_string;
+use ccdi_common::{to_string, FocusMetrics, FocusPoint, FocusCurve, TelemetrySample};
 use image::DynamicImage;
-use std::{cmp::{min, max}, io::Cursor};
+use std::{cmp::max, io::Cursor, ops::Range};
 use plotters::{prelude::*, backend::RGBPixel};
 
-use crate::ImageStats;
+use crate::{ImageStats, Channel};
 
 // ============================================ PUBLIC =============================================
 
 pub fn render_histogram_as_bmp(stats: &ImageStats, height: usize) -> Result<Vec<u8>, String> {
-    let (width, height) = (stats.r.bins.len(), height);
+    let width = stats.channels.first().map(|channel| channel.bins.len()).unwrap_or(0);
     let mut buffer = vec![0; width*height*3];
     render_plot_to_buffer(&mut buffer, width, height, stats)?;
     save_buffer_as_bmp(&buffer, width, height)
 }
 
+/// HFR (yellow) and FWHM (cyan) of frames with detected stars, oldest frame on the left
+pub fn render_focus_plot_as_bmp(
+    history: &[FocusMetrics],
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0; width*height*3];
+    render_focus_to_buffer(&mut buffer, width, height, history)?;
+    save_buffer_as_bmp(&buffer, width, height)
+}
+
+/// Autofocus HFR measurements (yellow) against focuser position with the fitted
+/// curve (cyan), positions without stars are skipped
+pub fn render_autofocus_plot_as_bmp(
+    points: &[FocusPoint],
+    curve: Option<&FocusCurve>,
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0; width*height*3];
+    render_autofocus_to_buffer(&mut buffer, width, height, points, curve)?;
+    save_buffer_as_bmp(&buffer, width, height)
+}
+
+/// Chip (yellow), cooler hot side (red) and ambient (cyan) temperatures in degrees celsius,
+/// time runs in hours up to the last sample at zero
+pub fn render_temperature_plot_as_bmp(
+    samples: &[TelemetrySample],
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, String> {
+    let series: [(TelemetryValue, RGBColor); 3] = [
+        (|sample| sample.telemetry.environment_temperature, CYAN),
+        (|sample| sample.telemetry.hot_temperature, RED),
+        (|sample| Some(sample.telemetry.chip_temperature), YELLOW),
+    ];
+
+    let values = samples.iter()
+        .flat_map(|sample| series.iter().filter_map(|(value, _)| value(sample)))
+        .collect::<Vec<_>>();
+
+    let min_y = values.iter().copied().fold(f32::MAX, f32::min);
+    let max_y = values.iter().copied().fold(f32::MIN, f32::max);
+
+    let range = match min_y <= max_y {
+        true => min_y - 1.0..max_y + 1.0,
+        false => 0.0..1.0,
+    };
+
+    let mut buffer = vec![0; width*height*3];
+    render_telemetry_to_buffer(&mut buffer, width, height, samples, &series, range)?;
+    save_buffer_as_bmp(&buffer, width, height)
+}
+
+/// Cooler power (yellow) and fan speed (cyan) in percent of their maximum,
+/// time runs in hours up to the last sample at zero
+pub fn render_cooler_plot_as_bmp(
+    samples: &[TelemetrySample],
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, String> {
+    let series: [(TelemetryValue, RGBColor); 2] = [
+        (|sample| sample.telemetry.fan_speed.map(|speed| speed*100.0), CYAN),
+        (|sample| sample.telemetry.cooler_power.map(|power| power*100.0), YELLOW),
+    ];
+
+    let mut buffer = vec![0; width*height*3];
+    render_telemetry_to_buffer(&mut buffer, width, height, samples, &series, 0.0..100.0)?;
+    save_buffer_as_bmp(&buffer, width, height)
+}
+
 // =========================================== PRIVATE =============================================
 
+type TelemetryValue = fn(&TelemetrySample) -> Option<f32>;
+
 fn render_plot_to_buffer(
     buffer: &mut [u8],
     width: usize,
     height: usize,
     stats: &ImageStats
 ) -> Result<(), String> {
-    let min_x = min(min(stats.r.min, stats.g.min), stats.b.min) as f32;
-    let max_x = max(max(stats.r.max, stats.g.max), stats.b.max) as f32;
-    let max_y = max(max(stats.r.max_count(), stats.g.max_count()), stats.b.max_count()) as f32;
+    let min_x = stats.total.min as f32;
+    let max_x = stats.total.max as f32;
+    let max_y = stats.max_count() as f32;
 
     let area = BitMapBackend::<RGBPixel>::with_buffer(
         buffer, (width as u32, height as u32)
@@ -36,21 +109,146 @@ fn render_plot_to_buffer(
         .build_cartesian_2d(min_x..max_x, 0.0..max_y)
         .map_err(to_string)?;
 
+    // Red is drawn last to stay on top
+    for histogram in stats.channels.iter().rev() {
+        chart.draw_series(
+            LineSeries::new(histogram_to_points(histogram), &channel_color(histogram.channel))
+        ).map_err(to_string)?;
+    }
+
+    Ok(())
+}
+
+fn channel_color(channel: Channel) -> RGBColor {
+    match channel {
+        Channel::Red => RED,
+        Channel::Green => GREEN,
+        Channel::Blue => RGBColor(0, 100, 255),
+        Channel::Luminance => WHITE,
+    }
+}
+
+fn render_focus_to_buffer(
+    buffer: &mut [u8],
+    width: usize,
+    height: usize,
+    history: &[FocusMetrics],
+) -> Result<(), String> {
+    let max_x = max(1, history.len()) as f32;
+    let max_y = history.iter()
+        .map(|metrics| metrics.hfr.max(metrics.fwhm) as f32)
+        .fold(1.0, f32::max)*1.1;
+
+    let area = BitMapBackend::<RGBPixel>::with_buffer(
+        buffer, (width as u32, height as u32)
+    ).into_drawing_area();
+
+    area.fill(&BLACK).unwrap();
+
+    let mut chart = ChartBuilder::on(&area)
+        .build_cartesian_2d(0.0..max_x, 0.0..max_y)
+        .map_err(to_string)?;
+
+    let points = |value: fn(&FocusMetrics) -> f64| history.iter()
+        .enumerate()
+        .filter(|(_, metrics)| metrics.star_count > 0)
+        .map(|(index, metrics)| (index as f32 + 0.5, value(metrics) as f32))
+        .collect::<Vec<_>>();
+
     chart.draw_series(
-        LineSeries::new(histogram_to_points(&stats.b), &RGBColor(0, 100, 255))
+        LineSeries::new(points(|metrics| metrics.fwhm), &CYAN)
     ).map_err(to_string)?;
 
     chart.draw_series(
-        LineSeries::new(histogram_to_points(&stats.g), &GREEN)
+        LineSeries::new(points(|metrics| metrics.hfr), &YELLOW)
     ).map_err(to_string)?;
 
+    Ok(())
+}
+
+fn render_autofocus_to_buffer(
+    buffer: &mut [u8],
+    width: usize,
+    height: usize,
+    points: &[FocusPoint],
+    curve: Option<&FocusCurve>,
+) -> Result<(), String> {
+    let measured = points.iter()
+        .filter(|point| point.star_count > 0)
+        .map(|point| (point.position as f32, point.hfr as f32))
+        .collect::<Vec<_>>();
+
+    let min_x = measured.iter().map(|point| point.0).fold(f32::MAX, f32::min);
+    let max_x = measured.iter().map(|point| point.0).fold(f32::MIN, f32::max);
+    let (min_x, max_x) = match min_x < max_x {
+        true => (min_x, max_x),
+        false => (min_x.min(0.0), min_x.max(0.0) + 1.0),
+    };
+
+    let max_y = measured.iter().map(|point| point.1).fold(1.0, f32::max)*1.1;
+
+    let area = BitMapBackend::<RGBPixel>::with_buffer(
+        buffer, (width as u32, height as u32)
+    ).into_drawing_area();
+
+    area.fill(&BLACK).unwrap();
+
+    let mut chart = ChartBuilder::on(&area)
+        .build_cartesian_2d(min_x..max_x, 0.0..max_y)
+        .map_err(to_string)?;
+
+    if let Some(curve) = curve {
+        let samples = (0..=width)
+            .map(|index| min_x + (max_x - min_x)*index as f32/max(1, width) as f32)
+            .map(|position| (position, curve.hfr_at(position as f64) as f32));
+
+        chart.draw_series(LineSeries::new(samples, &CYAN)).map_err(to_string)?;
+    }
+
     chart.draw_series(
-        LineSeries::new(histogram_to_points(&stats.r), &RED)
+        measured.iter().map(|point| Circle::new(*point, 3, YELLOW.filled()))
     ).map_err(to_string)?;
 
     Ok(())
 }
 
+fn render_telemetry_to_buffer(
+    buffer: &mut [u8],
+    width: usize,
+    height: usize,
+    samples: &[TelemetrySample],
+    series: &[(TelemetryValue, RGBColor)],
+    range: Range<f32>,
+) -> Result<(), String> {
+    let last = samples.last().map(|sample| sample.time);
+
+    let hours = |sample: &TelemetrySample| -> f32 {
+        let age = last.and_then(|last| last.duration_since(sample.time).ok()).unwrap_or_default();
+        -age.as_secs_f32()/3600.0
+    };
+
+    let min_x = samples.first().map(hours).unwrap_or(0.0).min(-0.1);
+
+    let area = BitMapBackend::<RGBPixel>::with_buffer(
+        buffer, (width as u32, height as u32)
+    ).into_drawing_area();
+
+    area.fill(&BLACK).unwrap();
+
+    let mut chart = ChartBuilder::on(&area)
+        .build_cartesian_2d(min_x..0.0, range)
+        .map_err(to_string)?;
+
+    for (value, color) in series {
+        let points = samples.iter()
+            .filter_map(|sample| value(sample).map(|value| (hours(sample), value)));
+
+        chart.draw_series(LineSeries::new(points, color)).map_err(to_string)?;
+    }
+
+    Ok(())
+}
+
 fn save_buffer_as_bmp(buffer: &[u8], width: usize, height: usize) -> Result<Vec<u8>, String> {
     let mut dynamic = DynamicImage::new_rgb8(width as u32, height as u32);
     let mut offset = 0;
@@ -1,6 +1,6 @@
 use std::{io::Cursor, cmp::{min, max}};
 
-use ccdi_common::RgbImage;
+use ccdi_common::PreviewImage;
 use image::DynamicImage;
 
 // ============================================ PUBLIC =============================================
@@ -19,17 +19,21 @@ pub enum TransformFunction {
     Log2,
 }
 
-pub fn rgb_image_to_bmp(image: &RgbImage<u16>, transform: Transform) -> Result<Vec<u8>, String> {
+/// Gray images are rendered with the same value in all color channels
+pub fn preview_image_to_bmp(image: &PreviewImage, transform: Transform) -> Result<Vec<u8>, String> {
     let mut dynamic = DynamicImage::new_rgb8(image.width() as u32, image.height() as u32);
+    let channels = image.channels();
+    let channel = |index: usize| channels[index.min(channels.len() - 1)];
+    let (red, green, blue) = (channel(0), channel(1), channel(2));
 
     if let Some(ref mut gray) = dynamic.as_mut_rgb8() {
         // TODO: use enumerate_rows_mut
         for (x, y, pixel) in gray.enumerate_pixels_mut() {
 
             *pixel = image::Rgb([
-                to_8bit(transform, image.red().line_ref(y as usize)[x as usize] as i32),
-                to_8bit(transform, image.green().line_ref(y as usize)[x as usize] as i32),
-                to_8bit(transform, image.blue().line_ref(y as usize)[x as usize] as i32),
+                to_8bit(transform, red.line_ref(y as usize)[x as usize] as i32),
+                to_8bit(transform, green.line_ref(y as usize)[x as usize] as i32),
+                to_8bit(transform, blue.line_ref(y as usize)[x as usize] as i32),
             ]);
         }
     } else {
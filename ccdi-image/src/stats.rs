@@ -1,6 +1,6 @@
 use std::cmp::{min, max};
 
-use ccdi_common::RgbImage;
+use ccdi_common::PreviewImage;
 use nanocv::Img;
 
 // ============================================ PUBLIC =============================================
@@ -8,9 +8,16 @@ use nanocv::Img;
 #[derive(Clone, PartialEq, Debug)]
 pub struct ImageStats {
     pub total: ChannelStats,
-    pub r: Histogram,
-    pub g: Histogram,
-    pub b: Histogram,
+    /// Red, green and blue histograms for color images, single luminance histogram otherwise
+    pub channels: Vec<Histogram>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Luminance,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -21,6 +28,7 @@ pub struct ChannelStats {
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Histogram {
+    pub channel: Channel,
     pub bins: Vec<u32>,
     pub min: u16,
     pub max: u16,
@@ -38,22 +46,36 @@ impl Histogram {
 
 impl ImageStats {
     pub fn min_pixel_value(&self) -> u16 {
-        let (r, g, b) = (self.r.min, self.g.min, self.b.min);
-        r.min(g.min(b))
+        self.channels.iter().map(|channel| channel.min).min().unwrap_or(0)
     }
 
     pub fn abg_min_pixel_value(&self) -> u16 {
-        let (r, g, b) = (self.r.min, self.g.min, self.b.min);
-        (r + g + b)/3
+        let sum = self.channels.iter().map(|channel| channel.min as u32).sum::<u32>();
+        (sum/max(1, self.channels.len() as u32)) as u16
+    }
+
+    pub fn max_count(&self) -> usize {
+        self.channels.iter().map(|channel| channel.max_count()).max().unwrap_or(0)
     }
 }
 
-pub fn compute_image_stats(image: &RgbImage<u16>, size: usize) -> ImageStats {
-    let r = compute_histogram(image.red(), size, compute_channel_stats(image.red()));
-    let g = compute_histogram(image.green(), size, compute_channel_stats(image.green()));
-    let b = compute_histogram(image.blue(), size, compute_channel_stats(image.blue()));
-    let total = combine_stats(&r.stats(), &combine_stats(&b.stats(), &g.stats()));
-    ImageStats { r, g, b, total }
+pub fn compute_image_stats(image: &PreviewImage, size: usize) -> ImageStats {
+    let names: &[Channel] = match image {
+        PreviewImage::Rgb(_) => &[Channel::Red, Channel::Green, Channel::Blue],
+        PreviewImage::Gray(_) => &[Channel::Luminance],
+    };
+
+    let channels = image.channels().into_iter()
+        .zip(names)
+        .map(|(data, name)| compute_histogram(data, size, compute_channel_stats(data), *name))
+        .collect::<Vec<_>>();
+
+    let total = channels.iter()
+        .map(|channel| channel.stats())
+        .reduce(|first, second| combine_stats(&first, &second))
+        .unwrap_or(ChannelStats { min: 0, max: 0 });
+
+    ImageStats { channels, total }
 }
 
 // =========================================== PRIVATE =============================================
@@ -75,7 +97,8 @@ fn compute_channel_stats(channel: &dyn Img<u16>,) -> ChannelStats {
 fn compute_histogram(
     channel: &dyn Img<u16>,
     size: usize,
-    stats: ChannelStats
+    stats: ChannelStats,
+    channel_name: Channel,
 ) -> Histogram {
     let mut bins = vec![0; size];
     let divisor = max(1, stats.max - stats.min) as usize;
@@ -88,7 +111,7 @@ fn compute_histogram(
         }
     }
 
-    Histogram { bins, min: stats.min, max: stats.max }
+    Histogram { channel: channel_name, bins, min: stats.min, max: stats.max }
 }
 
 fn combine_stats(first: &ChannelStats, second: &ChannelStats) -> ChannelStats {
@@ -0,0 +1,167 @@
+use std::time::Instant;
+
+use ccdi_imager_interface::TemperatureRequest;
+
+// ============================================ PUBLIC =============================================
+
+/// Thermoelectric cooler of the demo camera, the set point moves towards the requested
+/// temperature at the requested speed and the chip follows it with a delay, the chip can
+/// not be cooled more than `MAX_DELTA` below ambient nor heated above it
+pub struct Cooler {
+    ambient: f32,
+    chip: f32,
+    set_point: f32,
+    target: f32,
+    /// Degrees celsius per minute, zero or less means no ramp limit
+    speed: f32,
+    updated: Instant,
+}
+
+impl Cooler {
+    pub fn new() -> Self {
+        Self {
+            ambient: AMBIENT,
+            chip: AMBIENT,
+            set_point: AMBIENT,
+            target: AMBIENT,
+            speed: 0.0,
+            updated: Instant::now(),
+        }
+    }
+
+    pub fn request(&mut self, request: TemperatureRequest) {
+        self.update();
+        self.target = request.temperature;
+        self.speed = request.speed;
+    }
+
+    /// Switched off cooler lets the chip drift back to ambient
+    pub fn off(&mut self) {
+        self.update();
+        self.target = self.ambient;
+        self.speed = 0.0;
+    }
+
+    /// Advance the model to the current time
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        self.advance(now.duration_since(self.updated).as_secs_f32());
+        self.updated = now;
+    }
+
+    /// Chip temperature in degrees celsius
+    pub fn chip(&self) -> f32 {
+        self.chip
+    }
+
+    pub fn ambient(&self) -> f32 {
+        self.ambient
+    }
+
+    /// Heat sink temperature, rises with the power dissipated by the cooler
+    pub fn hot_side(&self) -> f32 {
+        self.ambient + HOT_SIDE_RISE*self.power()
+    }
+
+    /// Fraction of the maximum cooler power needed to hold the chip temperature
+    pub fn power(&self) -> f32 {
+        ((self.ambient - self.chip)/MAX_DELTA).clamp(0.0, 1.0)
+    }
+
+    /// Fraction of the maximum fan speed, the fan speeds up with the cooler power
+    pub fn fan(&self) -> f32 {
+        MIN_FAN + (1.0 - MIN_FAN)*self.power()
+    }
+}
+
+impl Default for Cooler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+const AMBIENT: f32 = 20.0;
+/// Largest difference between ambient and chip temperature the cooler can hold
+const MAX_DELTA: f32 = 40.0;
+/// Time constant of the chip following the set point in seconds
+const TIME_CONSTANT: f32 = 20.0;
+/// Heat sink temperature rise at full cooler power
+const HOT_SIDE_RISE: f32 = 15.0;
+/// Fan speed with the cooler off
+const MIN_FAN: f32 = 0.3;
+
+impl Cooler {
+    fn advance(&mut self, seconds: f32) {
+        let target = self.target.clamp(self.ambient - MAX_DELTA, self.ambient);
+
+        self.set_point = match self.speed > 0.0 {
+            false => target,
+            true => {
+                let step = self.speed/60.0*seconds;
+                self.set_point + (target - self.set_point).clamp(-step, step)
+            }
+        };
+
+        let approach = 1.0 - (-seconds/TIME_CONSTANT).exp();
+        self.chip += (self.set_point - self.chip)*approach;
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cooler(temperature: f32, speed: f32) -> Cooler {
+        let mut cooler = Cooler::new();
+        cooler.request(TemperatureRequest { temperature, speed });
+        cooler
+    }
+
+    fn run(cooler: &mut Cooler, seconds: usize) {
+        (0..seconds).for_each(|_| cooler.advance(1.0));
+    }
+
+    #[test]
+    fn chip_follows_ramp() {
+        let mut cooler = cooler(0.0, 6.0);
+        run(&mut cooler, 60);
+
+        assert!((cooler.set_point - 14.0).abs() < 0.01);
+        assert!(cooler.chip() > 14.0 && cooler.chip() < 17.0);
+
+        run(&mut cooler, 600);
+        assert!((cooler.chip() - 0.0).abs() < 0.01);
+        assert!((cooler.power() - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn cooling_is_limited_by_ambient() {
+        let mut cooler = cooler(-50.0, 0.0);
+        run(&mut cooler, 600);
+
+        assert!((cooler.chip() - (AMBIENT - MAX_DELTA)).abs() < 0.01);
+        assert!((cooler.power() - 1.0).abs() < 0.01);
+        assert!(cooler.hot_side() > AMBIENT);
+
+        cooler.request(TemperatureRequest { temperature: 40.0, speed: 0.0 });
+        run(&mut cooler, 600);
+        assert!((cooler.chip() - AMBIENT).abs() < 0.01);
+        assert!(cooler.power() < 0.01);
+    }
+
+    #[test]
+    fn switched_off_cooler_warms_to_ambient() {
+        let mut cooler = cooler(-10.0, 3.0);
+        run(&mut cooler, 1200);
+        assert!(cooler.power() > 0.5);
+
+        cooler.off();
+        run(&mut cooler, 600);
+        assert!((cooler.chip() - AMBIENT).abs() < 0.01);
+        assert!(cooler.power() < 0.01);
+    }
+}
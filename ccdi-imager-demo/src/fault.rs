@@ -0,0 +1,104 @@
+use std::sync::{Arc, Mutex};
+
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+/// Failures injected into the demo camera to exercise recovery of the service, calls are
+/// numbered from 1 and counted across reconnections of the camera
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultScript {
+    /// Number of initial `list_devices` calls that fail
+    pub list_devices_failures: usize,
+    /// Number of initial `connect_device` calls that fail
+    pub connect_failures: usize,
+    /// Downloads during which the camera disconnects, every later call to the device fails
+    pub disconnect_downloads: Vec<usize>,
+    /// Downloads returning only a part of the requested pixels
+    pub truncated_downloads: Vec<usize>,
+    /// Exposures whose image never becomes ready
+    pub stalled_exposures: Vec<usize>,
+}
+
+/// Fault script with call counters shared by the demo driver and its devices
+#[derive(Clone, Default)]
+pub struct Faults {
+    state: Arc<Mutex<FaultState>>,
+}
+
+impl Faults {
+    pub fn new(script: FaultScript) -> Self {
+        let state = FaultState { script, ..Default::default() };
+        Self { state: Arc::new(Mutex::new(state)) }
+    }
+
+    pub fn list_devices(&self) -> Result<(), String> {
+        self.with_state(|state| {
+            state.list_calls += 1;
+
+            match state.list_calls <= state.script.list_devices_failures {
+                true => Err(String::from("Injected list devices failure")),
+                false => Ok(()),
+            }
+        })
+    }
+
+    pub fn connect_device(&self) -> Result<(), String> {
+        self.with_state(|state| {
+            state.connect_calls += 1;
+
+            match state.connect_calls <= state.script.connect_failures {
+                true => Err(String::from("Injected connect failure")),
+                false => Ok(()),
+            }
+        })
+    }
+
+    /// Count a started exposure, returns true if its image must never become ready
+    pub fn start_exposure(&self) -> bool {
+        self.with_state(|state| {
+            state.exposures += 1;
+            state.script.stalled_exposures.contains(&state.exposures)
+        })
+    }
+
+    /// Count a download, returns the failure to be injected into it, if any
+    pub fn download(&self) -> Option<DownloadFault> {
+        self.with_state(|state| {
+            state.downloads += 1;
+
+            if state.script.disconnect_downloads.contains(&state.downloads) {
+                Some(DownloadFault::Disconnect)
+            } else if state.script.truncated_downloads.contains(&state.downloads) {
+                Some(DownloadFault::Truncate)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DownloadFault {
+    Disconnect,
+    Truncate,
+}
+
+// =========================================== PRIVATE =============================================
+
+#[derive(Default)]
+struct FaultState {
+    script: FaultScript,
+    list_calls: usize,
+    connect_calls: usize,
+    exposures: usize,
+    downloads: usize,
+}
+
+impl Faults {
+    fn with_state<T>(&self, action: impl FnOnce(&mut FaultState) -> T) -> T {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        action(&mut state)
+    }
+}
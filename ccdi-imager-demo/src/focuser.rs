@@ -0,0 +1,142 @@
+use std::{sync::{Arc, Mutex}, time::Instant};
+
+use ccdi_focuser_interface::{
+    FocuserDriver, FocuserDevice, FocuserDescriptor, FocuserProperties, FocuserStatus
+};
+
+// ============================================ PUBLIC =============================================
+
+/// Focuser position shared by the demo focuser and the demo camera, the camera blurs
+/// simulated stars by the distance of the focuser from the best focus position
+#[derive(Clone)]
+pub struct DemoFocus {
+    motion: Arc<Mutex<Motion>>,
+}
+
+impl DemoFocus {
+    pub fn new() -> Self {
+        let motion = Motion { from: START_POSITION, to: START_POSITION, started: Instant::now() };
+        Self { motion: Arc::new(Mutex::new(motion)) }
+    }
+
+    pub fn position(&self) -> i32 {
+        self.with_motion(|motion| motion.position())
+    }
+
+    /// Distance from the best focus expressed in chip pixels of blur
+    pub fn defocus(&self) -> f64 {
+        (self.position() - BEST_FOCUS).abs() as f64/STEPS_PER_BLUR_PIXEL
+    }
+}
+
+impl Default for DemoFocus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct DemoFocuserDriver {
+    focus: DemoFocus,
+}
+
+impl DemoFocuserDriver {
+    pub fn new(focus: DemoFocus) -> Self {
+        Self { focus }
+    }
+}
+
+impl FocuserDriver for DemoFocuserDriver {
+    fn list_devices(&mut self) -> Result<Vec<FocuserDescriptor>, String> {
+        Ok(vec![
+            FocuserDescriptor { id: 0, name: String::from("Demo Focuser #0") }
+        ])
+    }
+
+    fn connect_device(
+        &mut self, _descriptor: &FocuserDescriptor
+    ) -> Result<Box<dyn FocuserDevice>, String> {
+        Ok(Box::new(DemoFocuserDevice { focus: self.focus.clone() }))
+    }
+}
+
+pub struct DemoFocuserDevice {
+    focus: DemoFocus,
+}
+
+impl FocuserDevice for DemoFocuserDevice {
+    fn read_properties(&mut self) -> Result<FocuserProperties, String> {
+        Ok(FocuserProperties {
+            name: String::from("CCDI Demo Focuser"),
+            max_position: MAX_POSITION,
+        })
+    }
+
+    fn read_status(&mut self) -> Result<FocuserStatus, String> {
+        self.focus.with_motion(|motion| Ok(FocuserStatus {
+            position: motion.position(),
+            moving: motion.position() != motion.to,
+            temperature: Some(12.5),
+        }))
+    }
+
+    fn move_to(&mut self, position: i32) -> Result<(), String> {
+        if !(0..=MAX_POSITION).contains(&position) {
+            return Err(format!("Position {} out of range 0 - {}", position, MAX_POSITION))
+        }
+
+        self.focus.with_motion(|motion| *motion = Motion {
+            from: motion.position(),
+            to: position,
+            started: Instant::now(),
+        });
+
+        Ok(())
+    }
+
+    fn halt(&mut self) -> Result<(), String> {
+        self.focus.with_motion(|motion| {
+            let position = motion.position();
+            *motion = Motion { from: position, to: position, started: Instant::now() };
+        });
+
+        Ok(())
+    }
+
+    fn close(&mut self) {
+
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+const MAX_POSITION: i32 = 60000;
+const START_POSITION: i32 = 30000;
+const BEST_FOCUS: i32 = 31370;
+const STEPS_PER_SECOND: f64 = 5000.0;
+const STEPS_PER_BLUR_PIXEL: f64 = 150.0;
+
+/// Focuser moves from one position to another at a constant speed
+struct Motion {
+    from: i32,
+    to: i32,
+    started: Instant,
+}
+
+impl Motion {
+    fn position(&self) -> i32 {
+        let travelled = (self.started.elapsed().as_secs_f64()*STEPS_PER_SECOND) as i32;
+        let distance = self.to - self.from;
+
+        match travelled >= distance.abs() {
+            true => self.to,
+            false => self.from + travelled*distance.signum(),
+        }
+    }
+}
+
+impl DemoFocus {
+    fn with_motion<T>(&self, action: impl FnOnce(&mut Motion) -> T) -> T {
+        let mut motion = self.motion.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        action(&mut motion)
+    }
+}
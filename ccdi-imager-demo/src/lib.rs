@@ -1,48 +1,126 @@
-use std::{fmt::Debug, cmp::min};
+mod focuser;
+mod sky;
+mod cooler;
+mod fault;
+
+use std::{fmt::Debug, time::{Instant, Duration}};
 
 use ccdi_imager_interface::{
-    ImagerDriver, ImagerDevice, ImagerProperties, DeviceDescriptor, DeviceProperty, BasicProperties, ExposureParams, TemperatureRequest
+    ImagerDriver, ImagerDevice, ImagerProperties, DeviceDescriptor, DeviceProperty, BasicProperties, ExposureParams, TemperatureRequest,
+    CameraInfo, ImagerError, Telemetry, Filter
 };
 
+use cooler::Cooler;
+use fault::{Faults, DownloadFault};
+use sky::{SkySimulator, Conditions, FILTERS, PATTERN};
+
+pub use focuser::{DemoFocus, DemoFocuserDriver, DemoFocuserDevice};
+pub use fault::FaultScript;
+
 // ============================================ PUBLIC =============================================
 
 pub struct DemoImagerDriver {
-
+    focus: DemoFocus,
+    seed: u64,
+    faults: Faults,
 }
 
 impl DemoImagerDriver {
     pub fn new() -> Self {
-        Self { }
+        Self::with_focus(DemoFocus::new())
+    }
+
+    /// Images are blurred according to the position of the shared demo focuser
+    pub fn with_focus(focus: DemoFocus) -> Self {
+        Self { focus, seed: DEFAULT_SEED, faults: Faults::default() }
+    }
+
+    /// Seed of the image noise, connected devices produce the same images for the same seed
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// Devices fail according to the script, used to test recovery from camera failures
+    pub fn with_faults(self, script: FaultScript) -> Self {
+        Self { faults: Faults::new(script), ..self }
     }
 }
 
 impl ImagerDriver for DemoImagerDriver {
     fn list_devices(&mut self) -> Result<Vec<DeviceDescriptor>, String> {
-        Ok(vec![
-            DeviceDescriptor { id: 0, name: String::from("Demo Camera #0") }
-        ])
+        self.faults.list_devices()?;
+
+        Ok(
+            (0..CAMERA_COUNT)
+                .map(|id| DeviceDescriptor { id, name: format!("Demo Camera #{}", id) })
+                .collect()
+        )
     }
 
-    fn connect_device(&mut self, _descriptor: &DeviceDescriptor) -> Result<Box<dyn ImagerDevice>, String> {
-        Ok(Box::new(DemoImagerDevice { offset: 0.0, temperature: 30.0 }))
+    fn connect_device(&mut self, descriptor: &DeviceDescriptor) -> Result<Box<dyn ImagerDevice>, String> {
+        self.faults.connect_device()?;
+
+        if !(0..CAMERA_COUNT).contains(&descriptor.id) {
+            return Err(format!("Demo camera {} does not exist", descriptor.id))
+        }
+
+        Ok(Box::new(DemoImagerDevice {
+            offset: 0.0,
+            cooler: Cooler::new(),
+            focus: self.focus.clone(),
+            simulator: SkySimulator::new(self.seed + descriptor.id as u64),
+            filter: 0,
+            exposure_end: None,
+            faults: self.faults.clone(),
+            stalled: false,
+            disconnected: false,
+        }))
     }
 }
 
 pub struct DemoImagerDevice {
     offset: f32,
-    temperature: f32,
+    cooler: Cooler,
+    focus: DemoFocus,
+    simulator: SkySimulator,
+    filter: usize,
+    /// Time when the running exposure finishes
+    exposure_end: Option<Instant>,
+    faults: Faults,
+    /// Image of the running exposure never becomes ready
+    stalled: bool,
+    /// Every call fails after an injected disconnect
+    disconnected: bool,
 }
 
 impl ImagerDevice for DemoImagerDevice {
-    fn read_properties(&mut self) -> Result<ImagerProperties, String> {
+    fn read_properties(&mut self) -> Result<ImagerProperties, ImagerError> {
+        self.check_connected()?;
         self.offset += 0.001;
+        self.cooler.update();
         Ok(ImagerProperties {
             basic: BasicProperties {
-                width: 6000,
-                height: 4000,
-                temperature: self.temperature
+                width: WIDTH,
+                height: HEIGHT,
+                temperature: self.cooler.chip()
+            },
+            info: CameraInfo {
+                name: String::from("CCDI Demo Camera"),
+                pixel_width: 3.76,
+                pixel_height: 3.76,
+                bayer_pattern: Some(PATTERN),
+            },
+            other: list_demo_properties(&self),
+            read_modes: demo_read_modes(),
+            filters: demo_filters(),
+            telemetry: Telemetry {
+                chip_temperature: self.cooler.chip(),
+                hot_temperature: Some(self.cooler.hot_side()),
+                environment_temperature: Some(self.cooler.ambient()),
+                cooler_power: Some(self.cooler.power()),
+                supply_voltage: Some(supply_voltage(&self.cooler)),
+                fan_speed: Some(self.cooler.fan()),
             },
-            other: list_demo_properties(&self)
         })
     }
 
@@ -50,33 +128,132 @@ impl ImagerDevice for DemoImagerDevice {
 
     }
 
-    fn start_exposure(&mut self, _params: &ExposureParams) -> Result<(), String> {
+    fn start_exposure(&mut self, params: &ExposureParams) -> Result<(), ImagerError> {
+        self.check_connected()?;
+        let duration = Duration::try_from_secs_f64(params.time)
+            .map_err(|error| ImagerError::invalid_parameter(error.to_string()))?;
+        self.exposure_end = Some(Instant::now() + duration);
+        self.stalled = self.faults.start_exposure();
+        Ok(())
+    }
+
+    fn abort_exposure(&mut self) -> Result<(), ImagerError> {
+        self.check_connected()?;
+        self.exposure_end = None;
+        Ok(())
+    }
+
+    fn image_ready(&mut self, ) -> Result<bool, ImagerError> {
+        self.check_connected()?;
+
+        if self.stalled {
+            return Ok(false)
+        }
+
+        Ok(self.exposure_end.is_some_and(|end| Instant::now() >= end))
+    }
+
+    fn download_image(&mut self, params: &ExposureParams) -> Result<Vec<u16>, ImagerError> {
+        self.check_connected()?;
+
+        if self.exposure_end.take().is_none() {
+            return Err(ImagerError::other("No exposure to download"))
+        }
+
+        let fault = self.faults.download();
+
+        if fault == Some(DownloadFault::Disconnect) {
+            self.disconnected = true;
+            return Err(ImagerError::disconnected("Camera disconnected during download"))
+        }
+
+        self.cooler.update();
+
+        let conditions = Conditions {
+            temperature: self.cooler.chip(),
+            defocus: self.focus.defocus(),
+            filter: self.filter,
+        };
+
+        let mut data = self.simulator.render(params, &conditions);
+
+        if fault == Some(DownloadFault::Truncate) {
+            data.truncate(data.len()/2);
+        }
+
+        Ok(data)
+    }
+
+    fn set_temperature(&mut self, request: TemperatureRequest) -> Result<(), ImagerError> {
+        self.check_connected()?;
+        self.cooler.request(request);
+        Ok(())
+    }
+
+    fn cooler_off(&mut self, _warm_up: TemperatureRequest) -> Result<(), ImagerError> {
+        self.check_connected()?;
+        self.cooler.off();
         Ok(())
     }
 
-    fn image_ready(&mut self, ) -> Result<bool, String> {
-        Ok(true)
+    fn list_read_modes(&mut self) -> Result<Vec<String>, ImagerError> {
+        Ok(demo_read_modes())
     }
 
-    fn download_image(&mut self, params: &ExposureParams) -> Result<Vec<u16>, String> {
-        Ok(generate_test_image(params.area.width, params.area.height))
+    fn set_read_mode(&mut self, mode: usize) -> Result<(), ImagerError> {
+        self.check_connected()?;
+
+        match mode < demo_read_modes().len() {
+            true => Ok(()),
+            false => Err(ImagerError::invalid_parameter(format!("Invalid read mode {}", mode))),
+        }
     }
 
-    fn set_temperature(&mut self, request: TemperatureRequest) -> Result<(), String> {
-        dbg!("Setting temperature: ", request.temperature, request.speed);
-        self.temperature = request.temperature;
+    fn list_filters(&mut self) -> Result<Vec<Filter>, ImagerError> {
+        Ok(demo_filters())
+    }
+
+    fn set_filter(&mut self, filter: usize) -> Result<(), ImagerError> {
+        self.check_connected()?;
+
+        if filter >= FILTERS.len() {
+            return Err(ImagerError::invalid_parameter(format!("Invalid filter {}", filter)))
+        }
+
+        self.filter = filter;
         Ok(())
     }
 }
 
+impl DemoImagerDevice {
+    fn check_connected(&self) -> Result<(), ImagerError> {
+        match self.disconnected {
+            true => Err(ImagerError::disconnected("Camera disconnected")),
+            false => Ok(()),
+        }
+    }
+}
+
+fn demo_read_modes() -> Vec<String> {
+    ["Normal", "Low Noise", "Fast Preview"].iter().map(|name| name.to_string()).collect()
+}
+
+fn demo_filters() -> Vec<Filter> {
+    FILTERS.iter()
+        .map(|filter| Filter { name: filter.name.to_string(), offset: filter.offset })
+        .collect()
+}
+
 fn list_demo_properties(device: &DemoImagerDevice) -> Vec<DeviceProperty> {
+    let cooler = &device.cooler;
+
     vec![
-        prop("Chip Temperature", 1.000 + device.offset),
-        prop("Hot Temperature", 2.000 + device.offset),
-        prop("Camera Temperature", 3.000 + device.offset),
-        prop("Env Temperature", 4.000 + device.offset),
-        prop("Supply Voltage", 5.000 + device.offset),
-        prop("Power Utilization", 6.000 + device.offset),
+        prop_f32("Chip Temperature", cooler.chip(), 2),
+        prop_f32("Hot Temperature", cooler.hot_side(), 2),
+        prop_f32("Camera Temperature", (cooler.ambient() + cooler.hot_side())/2.0, 2),
+        prop_f32("Env Temperature", cooler.ambient(), 2),
+        prop_f32("Supply Voltage", supply_voltage(cooler), 2),
+        prop_f32("Power Utilization", cooler.power(), 2),
         prop("ADC Gain", 7.000 + device.offset),
         prop("Camera ID", 8.000 + device.offset),
         prop("Camera Chip Width", 9.000 + device.offset),
@@ -87,6 +264,11 @@ fn list_demo_properties(device: &DemoImagerDevice) -> Vec<DeviceProperty> {
     ]
 }
 
+/// Supply sags under the load of the cooler
+fn supply_voltage(cooler: &Cooler) -> f32 {
+    12.0 - 0.4*cooler.power()
+}
+
 fn prop<T: Debug>(name: &str, value: T) -> DeviceProperty {
     DeviceProperty {
         name: name.to_owned(),
@@ -94,54 +276,15 @@ fn prop<T: Debug>(name: &str, value: T) -> DeviceProperty {
     }
 }
 
-fn generate_test_image(width: usize, height: usize) -> Vec<u16> {
-    let mut buffer = vec![0u16; width*height];
-    let len = buffer.len();
-
-    let dx = (XMAX - XMIN)/width as f64;
-    let dy = (YMAX - YMIN)/height as f64;
-
-    for y in 0..(height/2) {
-        let lines = &mut buffer[y*width*2 .. min(len, (y+2)*width*2)];
-
-        for x in 0..(width/2) {
-            let (r, g, b) = generate_pixel(x, y, dx, dy);
-            lines[x*2] = g;
-            lines[x*2 + 1] = r;
-            lines[width + x*2] = b;
-            lines[width + x*2 + 1] = g;
-        }
+fn prop_f32(name: &str, value: f32, precision: usize) -> DeviceProperty {
+    DeviceProperty {
+        name: name.to_owned(),
+        value: format!("{:0.prec$}", value, prec = precision)
     }
-
-    buffer
 }
 
-const XMIN: f64 = 0.27085;
-const XMAX: f64 = 0.27100;
-const YMIN: f64 = 0.004570;
-const YMAX: f64 = 0.004755;
-const MAX_ITER: usize = 500;
-
-fn generate_pixel(x: usize, y: usize, dx: f64, dy: f64) -> (u16, u16, u16) {
-    let mut u: f64 = 0.0;
-    let mut v: f64 = 0.0;
-
-    let mut u2: f64 = u*u;
-    let mut v2: f64 = v*v;
-
-    let ry = YMAX - y as f64*dy;
-    let rx = XMIN + x as f64*dx;
-
-    let mut k = 1;
-
-    while k < MAX_ITER && (u2 + v2 < 4.0) {
-        v = 2.0*u*v + ry;
-        u = u2 - v2 + rx;
-        u2 = u*u;
-        v2 = v*v;
-        k += 1;
-    }
-
-    let out = ((k as f64 - 50.0)*100.0).clamp(0.0, 65535.0) as u16;
-    (600*(out % 57), out*2, 2000*(out % 17))
-}
+const WIDTH: usize = 6000;
+const HEIGHT: usize = 4000;
+const DEFAULT_SEED: u64 = 0x5EED;
+/// Several cameras are offered so that camera selection can be tried in demo mode
+const CAMERA_COUNT: i32 = 2;
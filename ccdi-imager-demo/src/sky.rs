@@ -0,0 +1,429 @@
+use std::f64::consts::PI;
+
+use ccdi_imager_interface::{BayerPattern, CfaColor, ExposureParams, FrameType};
+
+// ============================================ PUBLIC =============================================
+
+/// Color filter array of the simulated chip
+pub const PATTERN: BayerPattern = BayerPattern::Grbg;
+
+/// Filters of the simulated filter wheel
+pub const FILTERS: [Filter; 5] = [
+    Filter { name: "L", transmission: [0.95, 0.95, 0.95], sky: 1.0, offset: 0 },
+    Filter { name: "R", transmission: [0.9, 0.05, 0.0], sky: 1.0, offset: 10 },
+    Filter { name: "G", transmission: [0.05, 0.9, 0.05], sky: 1.0, offset: 0 },
+    Filter { name: "B", transmission: [0.0, 0.05, 0.9], sky: 1.0, offset: -10 },
+    Filter { name: "Ha", transmission: [0.3, 0.01, 0.0], sky: 0.05, offset: 40 },
+];
+
+pub struct Filter {
+    pub name: &'static str,
+    /// Fraction of the star light passed to red, green and blue pixels
+    transmission: [f64; 3],
+    /// Sky background is suppressed by narrowband filters more than the stars
+    sky: f64,
+    /// Focuser shift reported by the simulated filter wheel
+    pub offset: i32,
+}
+
+/// State of the simulated camera and optics during the exposure
+pub struct Conditions {
+    /// Chip temperature in degrees celsius
+    pub temperature: f32,
+    /// Distance from the best focus in chip pixels of blur
+    pub defocus: f64,
+    /// Index into `FILTERS`
+    pub filter: usize,
+}
+
+/// Simulates exposures of a synthetic star field, star positions are the same in every
+/// frame while the noise comes from a seeded generator, so that equally seeded simulators
+/// produce identical images
+pub struct SkySimulator {
+    rng: Rng,
+}
+
+impl SkySimulator {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng::new(seed) }
+    }
+
+    pub fn render(&mut self, params: &ExposureParams, conditions: &Conditions) -> Vec<u16> {
+        let chip = Chip::new(params);
+        let filter = FILTERS.get(conditions.filter).unwrap_or(&FILTERS[0]);
+
+        let time = match params.frame_type {
+            FrameType::Bias => 0.0,
+            _ => params.time.max(0.0),
+        };
+
+        let mut electrons = dark_signal(&chip, time, conditions.temperature);
+
+        match params.frame_type {
+            FrameType::Light => {
+                add_sky(&mut electrons, &chip, time, filter);
+                add_stars(&mut electrons, &chip, time, conditions.defocus, filter);
+            },
+            FrameType::Flat => add_flat(&mut electrons, &chip, time, filter),
+            FrameType::Dark | FrameType::Bias => {},
+        }
+
+        self.read_out(&electrons, &chip, params.gain)
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+/// Bias level added by the camera electronics, ADU
+const BIAS: f64 = 500.0;
+const READ_NOISE: f64 = 8.0;
+/// Pixel capacity in electrons
+const FULL_WELL: f64 = 50000.0;
+/// Conversion at zero gain, gain register is in 0.1 dB steps
+const ADU_PER_ELECTRON: f64 = 0.25;
+/// Dark current at 0 °C in electrons per second, doubles every `DARK_DOUBLING` degrees
+const DARK_CURRENT: f64 = 0.1;
+const DARK_DOUBLING: f64 = 6.0;
+/// Fraction of pixels with a dark current `HOT_PIXEL_FACTOR` times higher
+const HOT_PIXEL_FRACTION: f64 = 0.0002;
+const HOT_PIXEL_FACTOR: f64 = 500.0;
+/// Sky background in electrons per second and pixel and its color
+const SKY_RATE: f64 = 20.0;
+const SKY_COLOR: [f64; 3] = [1.1, 1.0, 0.8];
+/// Illumination of flat frames in electrons per second
+const FLAT_RATE: f64 = 20000.0;
+/// One star is placed in each square cell of this size in chip pixels
+const STAR_SPACING: usize = 150;
+/// Flux of the brightest stars in electrons per second
+const STAR_MAX_FLUX: f64 = 150000.0;
+/// Star brightness range in magnitudes
+const STAR_MAGNITUDES: f64 = 6.0;
+/// Gaussian sigma of stars in focus in chip pixels
+const SEEING_SIGMA: f64 = 1.5;
+/// Sigma added per pixel of defocus blur
+const DEFOCUS_SIGMA: f64 = 0.6;
+/// Blur of strongly defocused stars is limited to keep drawing fast
+const MAX_SIGMA: f64 = 25.0;
+
+/// Exposed area with binning, binned pixels sum light of all colors
+struct Chip {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    bin_x: usize,
+    bin_y: usize,
+}
+
+impl Chip {
+    fn new(params: &ExposureParams) -> Self {
+        let area = &params.area;
+
+        Self {
+            x: area.x,
+            y: area.y,
+            width: area.width,
+            height: area.height,
+            bin_x: params.binning.x.max(1),
+            bin_y: params.binning.y.max(1),
+        }
+    }
+
+    fn binned(&self) -> bool {
+        self.bin_x > 1 || self.bin_y > 1
+    }
+
+    /// Number of chip pixels in an image pixel
+    fn pixel_size(&self) -> f64 {
+        (self.bin_x*self.bin_y) as f64
+    }
+
+    /// Chip coordinates of the top left chip pixel of the image pixel
+    fn chip_position(&self, x: usize, y: usize) -> (usize, usize) {
+        ((self.x + x)*self.bin_x, (self.y + y)*self.bin_y)
+    }
+
+    /// Response of the image pixel to light of the given color
+    fn color_weight(&self, x: usize, y: usize, rgb: [f64; 3]) -> f64 {
+        if self.binned() {
+            return (rgb[0] + 2.0*rgb[1] + rgb[2])/4.0
+        }
+
+        let (chip_x, chip_y) = self.chip_position(x, y);
+
+        match PATTERN.color_at(chip_x, chip_y) {
+            CfaColor::Red => rgb[0],
+            CfaColor::Green => rgb[1],
+            CfaColor::Blue => rgb[2],
+        }
+    }
+
+    fn pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+    }
+}
+
+/// Electrons are kept in single precision, a full frame buffer in double precision
+/// would take hundreds of megabytes
+fn dark_signal(chip: &Chip, time: f64, temperature: f32) -> Vec<f32> {
+    let rate = DARK_CURRENT*2f64.powf(temperature as f64/DARK_DOUBLING)*chip.pixel_size();
+    let hot_threshold = (HOT_PIXEL_FRACTION*u32::MAX as f64) as u64;
+
+    chip.pixels()
+        .map(|(x, y)| {
+            let (chip_x, chip_y) = chip.chip_position(x, y);
+
+            let signal = match hash(chip_x, chip_y) & 0xFFFF_FFFF < hot_threshold {
+                true => rate*HOT_PIXEL_FACTOR*time,
+                false => rate*time,
+            };
+
+            signal as f32
+        })
+        .collect()
+}
+
+fn add_sky(electrons: &mut [f32], chip: &Chip, time: f64, filter: &Filter) {
+    let color = multiply(SKY_COLOR, filter.transmission);
+    let rate = SKY_RATE*filter.sky*chip.pixel_size();
+
+    for ((x, y), value) in chip.pixels().zip(electrons.iter_mut()) {
+        *value += (rate*chip.color_weight(x, y, color)*time) as f32;
+    }
+}
+
+fn add_flat(electrons: &mut [f32], chip: &Chip, time: f64, filter: &Filter) {
+    let rate = FLAT_RATE*chip.pixel_size();
+
+    for ((x, y), value) in chip.pixels().zip(electrons.iter_mut()) {
+        *value += (rate*chip.color_weight(x, y, filter.transmission)*time) as f32;
+    }
+}
+
+fn add_stars(electrons: &mut [f32], chip: &Chip, time: f64, defocus: f64, filter: &Filter) {
+    let sigma = (SEEING_SIGMA.powi(2) + (DEFOCUS_SIGMA*defocus).powi(2)).sqrt().min(MAX_SIGMA);
+
+    let (chip_x, chip_y) = chip.chip_position(0, 0);
+    let (chip_width, chip_height) = (chip.width*chip.bin_x, chip.height*chip.bin_y);
+
+    for cell_y in chip_y/STAR_SPACING..=(chip_y + chip_height)/STAR_SPACING {
+        for cell_x in chip_x/STAR_SPACING..=(chip_x + chip_width)/STAR_SPACING {
+            let star = Star::in_cell(cell_x, cell_y);
+
+            let (x, y) = (
+                star.x/chip.bin_x as f64 - chip.x as f64,
+                star.y/chip.bin_y as f64 - chip.y as f64,
+            );
+
+            let color = multiply(star.color, filter.transmission);
+            draw_star(electrons, chip, x, y, sigma/chip.bin_x as f64, star.flux*time, color);
+        }
+    }
+}
+
+/// Star of the synthetic field, derived from the cell hash only
+struct Star {
+    /// Position in chip pixels
+    x: f64,
+    y: f64,
+    /// Electrons per second
+    flux: f64,
+    color: [f64; 3],
+}
+
+impl Star {
+    fn in_cell(cell_x: usize, cell_y: usize) -> Self {
+        let hash = hash(cell_x, cell_y + 0x10_0000);
+        let fraction = |shift: u32| ((hash >> shift) & 0xFFFF) as f64/65535.0;
+        // Temperature from red to blue
+        let temperature = fraction(48);
+
+        Self {
+            x: (cell_x*STAR_SPACING) as f64 + fraction(0)*STAR_SPACING as f64,
+            y: (cell_y*STAR_SPACING) as f64 + fraction(16)*STAR_SPACING as f64,
+            flux: STAR_MAX_FLUX*10f64.powf(-0.4*STAR_MAGNITUDES*fraction(32)),
+            color: [1.3 - 0.6*temperature, 1.0, 0.7 + 0.6*temperature],
+        }
+    }
+}
+
+/// Gaussian profile with the given total number of electrons
+fn draw_star(
+    electrons: &mut [f32], chip: &Chip, x: f64, y: f64, sigma: f64, total: f64, color: [f64; 3]
+) {
+    let reach = (4.0*sigma).ceil() as isize + 1;
+    let (center_x, center_y) = (x.round() as isize, y.round() as isize);
+    let peak = total/(2.0*PI*sigma*sigma);
+
+    for py in (center_y - reach).max(0)..=(center_y + reach).min(chip.height as isize - 1) {
+        for px in (center_x - reach).max(0)..=(center_x + reach).min(chip.width as isize - 1) {
+            let (dx, dy) = ((px as f64 - x)/sigma, (py as f64 - y)/sigma);
+            let (px, py) = (px as usize, py as usize);
+            let value = peak*(-(dx*dx + dy*dy)/2.0).exp()*chip.color_weight(px, py, color);
+            electrons[py*chip.width + px] += value as f32;
+        }
+    }
+}
+
+impl SkySimulator {
+    /// Add shot and read noise, saturate and convert electrons to ADU
+    fn read_out(&mut self, electrons: &[f32], chip: &Chip, gain: u16) -> Vec<u16> {
+        let adu_per_electron = ADU_PER_ELECTRON*10f64.powf(gain as f64/200.0);
+        let capacity = FULL_WELL*chip.pixel_size();
+
+        electrons.iter()
+            .map(|&signal| {
+                let signal = signal as f64;
+                // Poisson noise of larger signals is close to normal
+                let sigma = (signal + READ_NOISE*READ_NOISE).sqrt();
+                // Values below the bias level are kept as read out by the camera
+                let measured = (signal + sigma*self.rng.normal()).min(capacity);
+                (BIAS + measured*adu_per_electron).round().clamp(0.0, u16::MAX as f64) as u16
+            })
+            .collect()
+    }
+}
+
+fn multiply(first: [f64; 3], second: [f64; 3]) -> [f64; 3] {
+    [first[0]*second[0], first[1]*second[1], first[2]*second[2]]
+}
+
+fn hash(x: usize, y: usize) -> u64 {
+    let mut hash = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(0x1656_67B1_9E37_79F9);
+    hash ^ (hash >> 32)
+}
+
+/// SplitMix64 generator with normally distributed values by the Box-Muller transform
+struct Rng {
+    state: u64,
+    spare: Option<f64>,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self { state: seed, spare: None }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+
+    /// Uniform value in (0, 1]
+    fn uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64/(1u64 << 53) as f64
+    }
+
+    fn normal(&mut self) -> f64 {
+        if let Some(value) = self.spare.take() {
+            return value
+        }
+
+        let radius = (-2.0*self.uniform().ln()).sqrt();
+        let angle = 2.0*PI*self.uniform();
+        self.spare = Some(radius*angle.sin());
+        radius*angle.cos()
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use ccdi_common::test_exposure_params;
+
+    use super::*;
+
+    fn params(frame_type: FrameType, time: f64, gain: u16, size: usize) -> ExposureParams {
+        ExposureParams { gain, time, frame_type, ..test_exposure_params(size, size) }
+    }
+
+    fn conditions(temperature: f32, defocus: f64) -> Conditions {
+        Conditions { temperature, defocus, filter: 0 }
+    }
+
+    /// Mean signal above bias of a small frame
+    fn mean_signal(
+        simulator: &mut SkySimulator, frame_type: FrameType, time: f64, gain: u16, temperature: f32
+    ) -> f64 {
+        let image = simulator.render(
+            &params(frame_type, time, gain, 150), &conditions(temperature, 0.0)
+        );
+
+        image.iter().map(|value| *value as f64 - BIAS).sum::<f64>()/image.len() as f64
+    }
+
+    #[test]
+    fn equal_seeds_give_equal_images() {
+        let light = params(FrameType::Light, 1.0, 0, 150);
+        let first = SkySimulator::new(7).render(&light, &conditions(0.0, 0.0));
+        let second = SkySimulator::new(7).render(&light, &conditions(0.0, 0.0));
+        let third = SkySimulator::new(8).render(&light, &conditions(0.0, 0.0));
+
+        assert_eq!(first, second);
+        assert_ne!(first, third);
+    }
+
+    #[test]
+    fn dark_current_scales_with_time_and_temperature() {
+        let mut simulator = SkySimulator::new(1);
+        let short = mean_signal(&mut simulator, FrameType::Dark, 10.0, 0, 12.0);
+        let long = mean_signal(&mut simulator, FrameType::Dark, 20.0, 0, 12.0);
+        let warm = mean_signal(&mut simulator, FrameType::Dark, 10.0, 0, 18.0);
+        let bias = mean_signal(&mut simulator, FrameType::Bias, 10.0, 0, 18.0);
+
+        assert!((long/short - 2.0).abs() < 0.2);
+        assert!((warm/short - 2.0).abs() < 0.2);
+        assert!(bias.abs() < 0.5);
+    }
+
+    #[test]
+    fn gain_scales_signal() {
+        let mut simulator = SkySimulator::new(2);
+        let low = mean_signal(&mut simulator, FrameType::Flat, 0.5, 0, 0.0);
+        let high = mean_signal(&mut simulator, FrameType::Flat, 0.5, 60, 0.0);
+
+        assert!((high/low - 10f64.powf(0.3)).abs() < 0.05);
+    }
+
+    #[test]
+    fn bright_pixels_saturate() {
+        let flat = params(FrameType::Flat, 100.0, 0, 150);
+        let image = SkySimulator::new(3).render(&flat, &conditions(0.0, 0.0));
+        let saturated = (BIAS + FULL_WELL*ADU_PER_ELECTRON) as u16;
+
+        assert_eq!(image.iter().copied().max(), Some(saturated));
+    }
+
+    #[test]
+    fn defocus_spreads_stars() {
+        let light = params(FrameType::Light, 1.0, 0, 600);
+        let focused = SkySimulator::new(4).render(&light, &conditions(0.0, 0.0));
+        let blurred = SkySimulator::new(4).render(&light, &conditions(0.0, 10.0));
+        let peak = |image: &[u16]| image.iter().copied().max().unwrap_or(0) as f64 - BIAS;
+
+        assert!(peak(&focused) > 5.0*peak(&blurred));
+    }
+
+    #[test]
+    fn strongly_defocused_stars_keep_their_light() {
+        let chip = Chip::new(&params(FrameType::Light, 1.0, 0, 600));
+        let star_light = |defocus: f64| {
+            let mut electrons = vec![0.0; chip.width*chip.height];
+            add_stars(&mut electrons, &chip, 1.0, defocus, &FILTERS[0]);
+            electrons.iter().map(|value| *value as f64).sum::<f64>()
+        };
+
+        let focused = star_light(0.0);
+        let blurred = star_light(1000.0);
+
+        assert!(focused > 0.0);
+        assert!(blurred > 0.7*focused);
+    }
+}
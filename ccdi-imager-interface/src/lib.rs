@@ -1,4 +1,4 @@
-use std::time::SystemTime;
+use std::{fmt, time::SystemTime};
 
 use serde_derive::{Serialize, Deserialize};
 
@@ -16,12 +16,82 @@ pub struct DeviceDescriptor {
 }
 
 pub trait ImagerDevice {
-    fn read_properties(&mut self) -> Result<ImagerProperties, String>;
+    fn read_properties(&mut self) -> Result<ImagerProperties, ImagerError>;
     fn close(&mut self);
-    fn start_exposure(&mut self, params: &ExposureParams) -> Result<(), String>;
-    fn image_ready(&mut self, ) -> Result<bool, String>;
-    fn download_image(&mut self, params: &ExposureParams) -> Result<Vec<u16>, String>;
-    fn set_temperature(&mut self, request: TemperatureRequest) -> Result<(), String>;
+    fn start_exposure(&mut self, params: &ExposureParams) -> Result<(), ImagerError>;
+    fn abort_exposure(&mut self) -> Result<(), ImagerError>;
+    fn image_ready(&mut self, ) -> Result<bool, ImagerError>;
+    fn download_image(&mut self, params: &ExposureParams) -> Result<Vec<u16>, ImagerError>;
+    fn set_temperature(&mut self, request: TemperatureRequest) -> Result<(), ImagerError>;
+    /// Stop cooling, cameras that can not switch the cooler off are ramped to the ambient
+    /// temperature of the warm-up request instead
+    fn cooler_off(&mut self, warm_up: TemperatureRequest) -> Result<(), ImagerError>;
+    fn list_read_modes(&mut self) -> Result<Vec<String>, ImagerError>;
+    fn set_read_mode(&mut self, mode: usize) -> Result<(), ImagerError>;
+    /// Filters in the filter wheel, empty when there is no filter wheel
+    fn list_filters(&mut self) -> Result<Vec<Filter>, ImagerError>;
+    /// Select the filter by its index in `list_filters`
+    fn set_filter(&mut self, filter: usize) -> Result<(), ImagerError>;
+}
+
+/// Failure of a device call, the kind decides whether the call is retried,
+/// the device reconnected or the request rejected
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ImagerError {
+    pub kind: ImagerErrorKind,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImagerErrorKind {
+    /// Device is not reachable anymore, it has to be reconnected
+    Disconnected,
+    /// Device did not respond in time
+    Timeout,
+    /// Requested value or feature is not supported by the device
+    InvalidParameter,
+    /// Device is occupied by another operation
+    Busy,
+    Other,
+}
+
+impl ImagerError {
+    pub fn new(kind: ImagerErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+
+    pub fn disconnected(message: impl Into<String>) -> Self {
+        Self::new(ImagerErrorKind::Disconnected, message)
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::new(ImagerErrorKind::Timeout, message)
+    }
+
+    pub fn invalid_parameter(message: impl Into<String>) -> Self {
+        Self::new(ImagerErrorKind::InvalidParameter, message)
+    }
+
+    pub fn busy(message: impl Into<String>) -> Self {
+        Self::new(ImagerErrorKind::Busy, message)
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        Self::new(ImagerErrorKind::Other, message)
+    }
+}
+
+impl fmt::Display for ImagerError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.message)
+    }
+}
+
+/// Errors without a known cause are classified as `Other`
+impl From<String> for ImagerError {
+    fn from(message: String) -> Self {
+        Self::other(message)
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -35,7 +105,38 @@ pub struct TemperatureRequest {
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct ImagerProperties {
     pub basic: BasicProperties,
+    pub info: CameraInfo,
     pub other: Vec<DeviceProperty>,
+    /// Names of read modes supported by the camera, indexed by mode number
+    pub read_modes: Vec<String>,
+    /// Filters in the filter wheel, indexed by filter number
+    pub filters: Vec<Filter>,
+    pub telemetry: Telemetry,
+}
+
+/// Filter of the filter wheel
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Filter {
+    pub name: String,
+    /// Focuser shift stored in the filter wheel, applied when the filter is selected
+    pub offset: i32,
+}
+
+/// Cooling and power diagnostics, values not reported by the camera are `None`
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Telemetry {
+    /// Chip temperature in degrees celsius
+    pub chip_temperature: f32,
+    /// Temperature of the cooler hot side in degrees celsius
+    pub hot_temperature: Option<f32>,
+    /// Ambient air temperature in degrees celsius
+    pub environment_temperature: Option<f32>,
+    /// Fraction of the maximum cooler power, 0.0 to 1.0
+    pub cooler_power: Option<f32>,
+    /// Power supply voltage in volts
+    pub supply_voltage: Option<f32>,
+    /// Fraction of the maximum fan speed, 0.0 to 1.0
+    pub fan_speed: Option<f32>,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
@@ -45,6 +146,18 @@ pub struct BasicProperties {
     pub temperature: f32,
 }
 
+/// Camera description that does not change while connected
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct CameraInfo {
+    pub name: String,
+    /// Chip pixel width in micrometers
+    pub pixel_width: f32,
+    /// Chip pixel height in micrometers
+    pub pixel_height: f32,
+    /// Color filter array of the chip, `None` for monochrome chips
+    pub bayer_pattern: Option<BayerPattern>,
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct DeviceProperty {
     pub name: String,
@@ -55,10 +168,112 @@ pub struct DeviceProperty {
 pub struct ExposureParams {
     pub gain: u16,
     pub time: f64,
+    /// Area of the downloaded image in binned pixels
     pub area: ExposureArea,
+    pub binning: Binning,
+    pub read_mode: Option<ReadMode>,
+    pub frame_type: FrameType,
     pub start_time: SystemTime,
 }
 
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ReadMode {
+    pub index: usize,
+    pub name: String,
+}
+
+/// Number of chip pixels combined into a single image pixel in each direction
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Binning {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Binning {
+    pub fn new(x: usize, y: usize) -> Self {
+        Self { x, y }
+    }
+
+    pub fn is_binned(&self) -> bool {
+        self.x > 1 || self.y > 1
+    }
+}
+
+impl Default for Binning {
+    fn default() -> Self {
+        Self { x: 1, y: 1 }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum FrameType {
+    #[default]
+    Light,
+    Dark,
+    Flat,
+    Bias,
+}
+
+/// Color filter array pattern, colors of the top left 2x2 cell in reading order
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum BayerPattern {
+    Rggb,
+    Bggr,
+    #[default]
+    Grbg,
+    Gbrg,
+}
+
+impl BayerPattern {
+    /// Pattern name as used by the BAYERPAT FITS keyword
+    pub fn name(&self) -> &'static str {
+        match self {
+            BayerPattern::Rggb => "RGGB",
+            BayerPattern::Bggr => "BGGR",
+            BayerPattern::Grbg => "GRBG",
+            BayerPattern::Gbrg => "GBRG",
+        }
+    }
+
+    /// Pattern given by its BAYERPAT name, `None` for unknown names
+    pub fn from_name(name: &str) -> Option<Self> {
+        [BayerPattern::Rggb, BayerPattern::Bggr, BayerPattern::Grbg, BayerPattern::Gbrg]
+            .into_iter()
+            .find(|pattern| pattern.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Color of the pixel at the given image position
+    pub fn color_at(&self, x: usize, y: usize) -> CfaColor {
+        use CfaColor::*;
+
+        let cell = match self {
+            BayerPattern::Rggb => [[Red, Green], [Green, Blue]],
+            BayerPattern::Bggr => [[Blue, Green], [Green, Red]],
+            BayerPattern::Grbg => [[Green, Red], [Blue, Green]],
+            BayerPattern::Gbrg => [[Green, Blue], [Red, Green]],
+        };
+
+        cell[y % 2][x % 2]
+    }
+
+    /// Pattern of an image starting at the given chip position
+    pub fn shifted(&self, x: usize, y: usize) -> Self {
+        [BayerPattern::Rggb, BayerPattern::Bggr, BayerPattern::Grbg, BayerPattern::Gbrg]
+            .into_iter()
+            .find(|pattern| (0..4).all(
+                |i| pattern.color_at(i % 2, i/2) == self.color_at(x + i % 2, y + i/2)
+            ))
+            .unwrap_or(*self)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CfaColor {
+    Red,
+    Green,
+    Blue,
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct ExposureArea {
     pub x: usize,
@@ -1,102 +1,224 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug};
 
-use ccdi_common::to_string;
-use ccdi_driver_moravian::{get_any_camera_id, CameraDriver, connect_usb_camera, CameraError};
+use ccdi_driver_moravian::{
+    enumerate_usb_cameras, CameraDriver, connect_usb_camera, CameraError, ErrorKind
+};
 use ccdi_imager_interface::{
-    ImagerDriver, ImagerDevice, ImagerProperties, DeviceDescriptor, DeviceProperty, BasicProperties, ExposureParams, TemperatureRequest
+    ImagerDriver, ImagerDevice, ImagerProperties, DeviceDescriptor, DeviceProperty, BasicProperties, ExposureParams, TemperatureRequest,
+    CameraInfo, BayerPattern, ImagerError, ImagerErrorKind, Telemetry, Filter, FrameType
 };
+use log::warn;
 
 // ============================================ PUBLIC =============================================
 
 pub struct MoravianImagerDriver {
-
+    /// Names of cameras connected before, cameras are not opened only to read their names,
+    /// they may be owned by another process
+    names: HashMap<i32, String>,
 }
 
 impl MoravianImagerDriver {
     pub fn new() -> Self {
-        Self { }
+        Self { names: HashMap::new() }
     }
 }
 
 impl ImagerDriver for MoravianImagerDriver {
     fn list_devices(&mut self) -> Result<Vec<DeviceDescriptor>, String> {
-        Ok(match get_any_camera_id() {
-            Some(id) => vec![
-                DeviceDescriptor { id, name: String::from("Camera #0") }
-            ],
-            None => vec![],
-        })
+        Ok(
+            enumerate_usb_cameras().into_iter()
+                .map(|id| DeviceDescriptor { id, name: self.camera_name(id) })
+                .collect()
+        )
     }
 
     fn connect_device(
         &mut self,
         descriptor: &DeviceDescriptor
     ) ->  Result<Box<dyn ImagerDevice>, String> {
-        Ok(Box::new(
-            MoravianImagerDevice {
-                device: connect_usb_camera(descriptor.id).map_err(to_string)?
-            }
-        ))
+        let device = connect_usb_camera(descriptor.id).map_err(|error| error.to_string())?;
+
+        match read_camera_name(&device) {
+            Ok(name) => { self.names.insert(descriptor.id, name); },
+            Err(error) => warn!("Could not read name of camera {}: {}", descriptor.id, error),
+        }
+
+        let read_modes = device.enumerate_read_modes().map_err(|error| error.to_string())?;
+        let filters = device.enumerate_filters().map_err(|error| error.to_string())?
+            .into_iter()
+            .map(|filter| Filter { name: filter.name, offset: filter.offset })
+            .collect();
+        let info = read_camera_info(&device).map_err(|error| error.to_string())?;
+        let has_power = device.read_has_power_utilization().map_err(|error| error.to_string())?;
+        Ok(Box::new(MoravianImagerDevice { device, read_modes, filters, info, has_power }))
     }
 }
 
 pub struct MoravianImagerDevice {
-    device: CameraDriver
+    device: CameraDriver,
+    read_modes: Vec<String>,
+    filters: Vec<Filter>,
+    info: CameraInfo,
+    /// Camera reports the cooler power utilization
+    has_power: bool,
 }
 
 impl ImagerDevice for MoravianImagerDevice {
-    fn read_properties(&mut self) -> Result<ImagerProperties, String> {
+    fn read_properties(&mut self) -> Result<ImagerProperties, ImagerError> {
+        let telemetry = read_telemetry(&self.device, self.has_power).map_err(imager_error)?;
+
         Ok(ImagerProperties {
-            basic: read_basic_properties(&self.device).map_err(to_string)?,
-            other: read_all_properties(&self.device).map_err(to_string)?
+            basic: read_basic_properties(&self.device, &telemetry).map_err(imager_error)?,
+            info: self.info.clone(),
+            other: read_all_properties(&self.device, &telemetry).map_err(imager_error)?,
+            read_modes: self.read_modes.clone(),
+            filters: self.filters.clone(),
+            telemetry,
         })
     }
 
     fn close(&mut self) {
-
+        self.device.close()
     }
 
-    fn start_exposure(&mut self, params: &ExposureParams) -> Result<(), String> {
-        self.device.set_gain(params.gain).map_err(to_string)?;
+    fn start_exposure(&mut self, params: &ExposureParams) -> Result<(), ImagerError> {
+        self.device.set_gain(params.gain).map_err(imager_error)?;
+        self.device.set_binning(params.binning.x, params.binning.y).map_err(imager_error)?;
         let (x, y, w, h) = params.area.into_tuple();
-        self.device.start_exposure(params.time, true, x, y, w, h).map_err(to_string)
+        // Dark and bias frames are taken with the mechanical shutter closed
+        let use_shutter = !matches!(params.frame_type, FrameType::Dark | FrameType::Bias);
+        self.device.start_exposure(params.time, use_shutter, x, y, w, h).map_err(imager_error)
+    }
+
+    fn abort_exposure(&mut self) -> Result<(), ImagerError> {
+        self.device.abort_exposure(false).map_err(imager_error)
+    }
+
+    fn image_ready(&mut self, ) -> Result<bool, ImagerError> {
+        self.device.image_ready().map_err(imager_error)
+    }
+
+    fn download_image(&mut self, params: &ExposureParams) -> Result<Vec<u16>, ImagerError> {
+        self.device.read_image(params.area.pixel_count()).map_err(imager_error)
     }
 
-    fn image_ready(&mut self, ) -> Result<bool, String> {
-        self.device.image_ready().map_err(to_string)
+    fn set_temperature(&mut self, request: TemperatureRequest) -> Result<(), ImagerError> {
+        self.device.set_temperature_ramp(request.speed).map_err(imager_error)?;
+        self.device.set_temperature(request.temperature).map_err(imager_error)
     }
 
-    fn download_image(&mut self, params: &ExposureParams) -> Result<Vec<u16>, String> {
-        self.device.read_image(params.area.pixel_count()).map_err(to_string)
+    fn cooler_off(&mut self, warm_up: TemperatureRequest) -> Result<(), ImagerError> {
+        self.device.cooler_off(warm_up.temperature, warm_up.speed).map_err(imager_error)
     }
 
-    fn set_temperature(&mut self, request: TemperatureRequest) -> Result<(), String> {
-        self.device.set_temperature_ramp(request.speed).map_err(to_string)?;
-        self.device.set_temperature(request.temperature).map_err(to_string)
+    fn list_read_modes(&mut self) -> Result<Vec<String>, ImagerError> {
+        Ok(self.read_modes.clone())
+    }
+
+    fn set_read_mode(&mut self, mode: usize) -> Result<(), ImagerError> {
+        self.device.set_read_mode(mode).map_err(imager_error)
+    }
+
+    fn list_filters(&mut self) -> Result<Vec<Filter>, ImagerError> {
+        Ok(self.filters.clone())
+    }
+
+    fn set_filter(&mut self, filter: usize) -> Result<(), ImagerError> {
+        self.device.set_filter(filter).map_err(imager_error)
+    }
+}
+
+impl MoravianImagerDriver {
+    /// Cameras not connected yet are named by their id
+    fn camera_name(&self, id: i32) -> String {
+        self.names.get(&id).cloned().unwrap_or(format!("Camera #{}", id))
     }
 }
 
-fn read_basic_properties(device: &CameraDriver) -> Result<BasicProperties, CameraError> {
+/// Model and serial number, the camera is told apart from others of the same model
+fn read_camera_name(device: &CameraDriver) -> Result<String, CameraError> {
+    Ok(format!("{} ({})", device.read_camera_description()?, device.read_camera_serial()?))
+}
+
+fn read_basic_properties(
+    device: &CameraDriver,
+    telemetry: &Telemetry,
+) -> Result<BasicProperties, CameraError> {
     Ok(BasicProperties{
         width: device.read_chip_width()? as usize,
         height: device.read_chip_height()? as usize,
-        temperature: device.read_chip_temperature()?,
+        temperature: telemetry.chip_temperature,
+    })
+}
+
+fn read_camera_info(device: &CameraDriver) -> Result<CameraInfo, CameraError> {
+    const NANOMETERS_PER_MICROMETER: f32 = 1000.0;
+
+    Ok(CameraInfo {
+        name: device.read_camera_description()?,
+        pixel_width: device.read_pixel_width()? as f32/NANOMETERS_PER_MICROMETER,
+        pixel_height: device.read_pixel_height()? as f32/NANOMETERS_PER_MICROMETER,
+        bayer_pattern: read_bayer_pattern(device)?,
+    })
+}
+
+/// Moravian color chips have RGGB pattern, shifted by a column or a row when the odd flags are set
+fn read_bayer_pattern(device: &CameraDriver) -> Result<Option<BayerPattern>, CameraError> {
+    if !device.read_is_color()? {
+        return Ok(None)
+    }
+
+    Ok(Some(match (device.read_debayer_x_odd()?, device.read_debayer_y_odd()?) {
+        (false, false) => BayerPattern::Rggb,
+        (true, false) => BayerPattern::Grbg,
+        (false, true) => BayerPattern::Gbrg,
+        (true, true) => BayerPattern::Bggr,
+    }))
+}
+
+/// Cameras without a hot side or ambient sensor fail to read them, such values are
+/// `None`, only the chip temperature is required. Gxccd can set the fan speed but does
+/// not report it.
+fn read_telemetry(device: &CameraDriver, has_power: bool) -> Result<Telemetry, CameraError> {
+    Ok(Telemetry {
+        chip_temperature: device.read_chip_temperature()?,
+        hot_temperature: device.read_hot_temperature().ok(),
+        environment_temperature: device.read_environment_temperature().ok(),
+        cooler_power: match has_power {
+            true => device.read_power_utilization().ok(),
+            false => None,
+        },
+        supply_voltage: device.read_supply_voltage().ok(),
+        fan_speed: None,
     })
 }
 
-fn read_all_properties(device: &CameraDriver) -> Result<Vec<DeviceProperty>, CameraError> {
-    Ok(vec![
-        prop_f32("Chip Temperature", device.read_chip_temperature()?, 2),
-        prop_f32("Hot Temperature", device.read_hot_temperature()?, 2),
-        prop_f32("Env Temperature", device.read_environment_temperature()?, 2),
-        prop_f32("Supply Voltage", device.read_supply_voltage()?, 2),
-        prop("Power Utilization", device.read_power_utilization()?),
+/// Sensor values are taken from the telemetry read in the same cycle
+fn read_all_properties(
+    device: &CameraDriver,
+    telemetry: &Telemetry,
+) -> Result<Vec<DeviceProperty>, CameraError> {
+    let sensors = [
+        ("Hot Temperature", telemetry.hot_temperature),
+        ("Env Temperature", telemetry.environment_temperature),
+        ("Supply Voltage", telemetry.supply_voltage),
+        ("Power Utilization", telemetry.cooler_power),
+    ];
+
+    let mut properties = vec![prop_f32("Chip Temperature", telemetry.chip_temperature, 2)];
+
+    properties.extend(sensors.into_iter()
+        .filter_map(|(name, value)| value.map(|value| prop_f32(name, value, 2))));
+
+    properties.extend([
         prop("ADC Gain", device.read_adc_gain()?),
         prop("Camera ID", device.read_camera_id()?),
         prop("Min Exposure Time", device.read_min_exposure()?),
         prop("Max Exposure Time", device.read_max_exposure()?),
         prop("Max Gain", device.read_max_gain()?),
-    ])
+    ]);
+
+    Ok(properties)
 }
 
 fn prop<T: Debug>(name: &str, value: T) -> DeviceProperty {
@@ -111,4 +233,17 @@ fn prop_f32(name: &str, value: f32, precision: usize) -> DeviceProperty {
         name: name.to_owned(),
         value: format!("{:0.prec$}", value, prec = precision)
     }
-}
\ No newline at end of file
+}
+
+/// Keep the classification of the driver so that the caller can decide how to recover
+fn imager_error(error: CameraError) -> ImagerError {
+    let kind = match error.kind() {
+        ErrorKind::Disconnected => ImagerErrorKind::Disconnected,
+        ErrorKind::Timeout => ImagerErrorKind::Timeout,
+        ErrorKind::InvalidParameter => ImagerErrorKind::InvalidParameter,
+        ErrorKind::Busy => ImagerErrorKind::Busy,
+        ErrorKind::Other => ImagerErrorKind::Other,
+    };
+
+    ImagerError::new(kind, error.to_string())
+}
@@ -0,0 +1,221 @@
+use std::{fs::File, io::{BufReader, Read}, path::Path};
+
+use ccdi_common::to_string;
+
+// ============================================ PUBLIC =============================================
+
+/// Keywords of the primary header with values as written in the file,
+/// quotes are removed from string values
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct FitsHeader {
+    keys: Vec<(String, String)>,
+}
+
+impl FitsHeader {
+    pub fn text(&self, name: &str) -> Option<&str> {
+        self.keys.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Numeric value, FITS double precision exponents like `1.5D+01` are accepted
+    pub fn number(&self, name: &str) -> Option<f64> {
+        self.text(name).and_then(|value| value.replace('D', "E").parse().ok())
+    }
+
+    /// Image width and height, only two dimensional images are supported
+    pub fn dimensions(&self) -> Result<(usize, usize), String> {
+        if self.text("SIMPLE") != Some("T") {
+            return Err(String::from("Not a FITS file"))
+        }
+
+        let axis = |name: &str| self.number(name)
+            .filter(|value| *value >= 0.0)
+            .map(|value| value as usize)
+            .ok_or(format!("Missing or invalid {}", name));
+
+        match axis("NAXIS")? {
+            2 => Ok((axis("NAXIS1")?, axis("NAXIS2")?)),
+            naxis => Err(format!("Unsupported number of axes {}", naxis)),
+        }
+    }
+}
+
+/// Read the primary header only, used to scan files without loading the image data
+pub fn read_fits_header(path: &Path) -> Result<FitsHeader, String> {
+    let mut reader = BufReader::new(File::open(path).map_err(to_string)?);
+    read_header(&mut reader)
+}
+
+/// Read the primary image converted to unsigned 16 bit samples, values are scaled
+/// by BZERO and BSCALE and clamped to the 16 bit range
+pub fn read_fits_image(path: &Path) -> Result<(FitsHeader, Vec<u16>), String> {
+    let mut reader = BufReader::new(File::open(path).map_err(to_string)?);
+    let header = read_header(&mut reader)?;
+    let data = read_data(&mut reader, &header)?;
+    Ok((header, data))
+}
+
+// =========================================== PRIVATE =============================================
+
+const BLOCK_SIZE: usize = 2880;
+const CARD_SIZE: usize = 80;
+
+fn read_header(reader: &mut impl Read) -> Result<FitsHeader, String> {
+    let mut keys = Vec::new();
+    let mut block = [0u8; BLOCK_SIZE];
+
+    loop {
+        reader.read_exact(&mut block).map_err(|_| String::from("FITS header is incomplete"))?;
+
+        for card in block.chunks(CARD_SIZE) {
+            // Only ASCII is valid in headers, other bytes must not break the card layout
+            let card = card.iter()
+                .map(|&byte| if byte.is_ascii() { byte as char } else { '?' })
+                .collect::<String>();
+
+            let name = card[..8].trim_end();
+
+            if name == "END" {
+                return Ok(FitsHeader { keys })
+            }
+
+            if card[8..].starts_with("= ") {
+                keys.push((name.to_owned(), parse_value(&card[10..])));
+            }
+        }
+    }
+}
+
+/// String values are quoted with doubled quotes inside, other values end at a comment
+fn parse_value(value: &str) -> String {
+    let value = value.trim_start();
+
+    match value.strip_prefix('\'') {
+        None => value.split('/').next().unwrap_or_default().trim().to_owned(),
+        Some(quoted) => {
+            let mut result = String::new();
+            let mut chars = quoted.chars().peekable();
+
+            while let Some(char) = chars.next() {
+                match (char, chars.peek()) {
+                    ('\'', Some('\'')) => {
+                        result.push('\'');
+                        chars.next();
+                    },
+                    ('\'', _) => break,
+                    (char, _) => result.push(char),
+                }
+            }
+
+            result.trim_end().to_owned()
+        }
+    }
+}
+
+fn read_data(reader: &mut impl Read, header: &FitsHeader) -> Result<Vec<u16>, String> {
+    let (width, height) = header.dimensions()?;
+    let bitpix = header.number("BITPIX").ok_or(String::from("Missing BITPIX"))? as i32;
+    let bzero = header.number("BZERO").unwrap_or(0.0);
+    let bscale = header.number("BSCALE").unwrap_or(1.0);
+
+    let sample_size = match bitpix {
+        8 | 16 | 32 | -32 | -64 => (bitpix.unsigned_abs()/8) as usize,
+        _ => return Err(format!("Unsupported BITPIX {}", bitpix)),
+    };
+
+    let mut bytes = vec![0u8; width*height*sample_size];
+    reader.read_exact(&mut bytes).map_err(|_| String::from("FITS image data is incomplete"))?;
+
+    let raw = |chunk: &[u8]| -> f64 {
+        match bitpix {
+            8 => chunk[0] as f64,
+            16 => i16::from_be_bytes([chunk[0], chunk[1]]) as f64,
+            32 => i32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64,
+            -32 => f32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64,
+            _ => f64::from_be_bytes(chunk.try_into().unwrap_or_default()),
+        }
+    };
+
+    Ok(
+        bytes.chunks(sample_size)
+            .map(|chunk| (bzero + bscale*raw(chunk)).round().clamp(0.0, u16::MAX as f64) as u16)
+            .collect()
+    )
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn card(text: &str) -> String {
+        format!("{:<80}", text)
+    }
+
+    /// File of the header cards followed by the data, also used by tests of the device
+    pub(crate) fn fits(cards: &[&str], data: &[u8]) -> Vec<u8> {
+        let mut header = cards.iter().map(|text| card(text)).collect::<String>();
+        header.push_str(&card("END"));
+
+        let mut bytes = header.into_bytes();
+        bytes.resize(bytes.len().div_ceil(BLOCK_SIZE)*BLOCK_SIZE, b' ');
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn read(bytes: Vec<u8>) -> Result<(FitsHeader, Vec<u16>), String> {
+        let mut reader = Cursor::new(bytes);
+        let header = read_header(&mut reader)?;
+        let data = read_data(&mut reader, &header)?;
+        Ok((header, data))
+    }
+
+    const IMAGE: &[&str] = &["SIMPLE  =                    T", "NAXIS   =                    2",
+        "NAXIS1  =                    2", "NAXIS2  =                    1"];
+
+    #[test]
+    fn header_values_are_parsed() {
+        let cards = [
+            "SIMPLE  =                    T / Standard FITS",
+            "EXPTIME =                 30.5 / [s] Exposure time",
+            "OBJECT  = 'M 31 / Andromeda''s' / Name of the object",
+            "HISTORY   without a value",
+        ];
+
+        let header = read_header(&mut Cursor::new(fits(&cards, &[]))).unwrap();
+        assert_eq!(header.text("SIMPLE"), Some("T"));
+        assert_eq!(header.number("EXPTIME"), Some(30.5));
+        assert_eq!(header.text("OBJECT"), Some("M 31 / Andromeda's"));
+        assert_eq!(header.text("HISTORY"), None);
+        assert_eq!(header.dimensions(), Err(String::from("Missing or invalid NAXIS")));
+    }
+
+    #[test]
+    fn samples_are_scaled_to_unsigned() {
+        let cards = [IMAGE, &["BITPIX  =                   16", "BZERO   =                32768"]]
+            .concat();
+
+        let (_, data) = read(fits(&cards, &[0x80, 0x00, 0x7f, 0xff])).unwrap();
+        assert_eq!(data, vec![0, 65535]);
+
+        let cards = [IMAGE, &["BITPIX  =                  -32", "BSCALE  =                 1000"]]
+            .concat();
+
+        let data = [1.5f32.to_be_bytes(), (-1.0f32).to_be_bytes()].concat();
+        assert_eq!(read(fits(&cards, &data)).unwrap().1, vec![1500, 0]);
+    }
+
+    #[test]
+    fn incomplete_files_are_rejected() {
+        let cards = [IMAGE, &["BITPIX  =                   16"]].concat();
+        assert!(read(fits(&cards, &[0, 0])).is_err());
+
+        let mut bytes = fits(IMAGE, &[]);
+        bytes.truncate(BLOCK_SIZE - 1);
+        assert!(read(bytes).is_err());
+    }
+}
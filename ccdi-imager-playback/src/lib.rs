@@ -0,0 +1,399 @@
+mod fits;
+
+use std::{path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use ccdi_imager_interface::{
+    ImagerDriver, ImagerDevice, ImagerProperties, DeviceDescriptor, DeviceProperty,
+    BasicProperties, ExposureParams, ExposureArea, Binning, TemperatureRequest, CameraInfo,
+    BayerPattern, ImagerError, Telemetry, Filter
+};
+use log::{info, warn};
+
+use fits::{FitsHeader, read_fits_header, read_fits_image};
+
+// ============================================ PUBLIC =============================================
+
+/// Serves previously captured FITS files from a directory as camera frames, files are
+/// played in name order and repeated, every frame takes its recorded exposure time
+pub struct PlaybackImagerDriver {
+    directory: PathBuf,
+}
+
+impl PlaybackImagerDriver {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+}
+
+impl ImagerDriver for PlaybackImagerDriver {
+    fn list_devices(&mut self) -> Result<Vec<DeviceDescriptor>, String> {
+        Ok(match list_fits_files(&self.directory)?.is_empty() {
+            true => vec![],
+            false => vec![
+                DeviceDescriptor { id: 0, name: format!("Playback {}", self.directory.display()) }
+            ],
+        })
+    }
+
+    fn connect_device(
+        &mut self, _descriptor: &DeviceDescriptor
+    ) -> Result<Box<dyn ImagerDevice>, String> {
+        Ok(Box::new(PlaybackImagerDevice::new(&self.directory)?))
+    }
+}
+
+pub struct PlaybackImagerDevice {
+    directory: PathBuf,
+    frames: Vec<Frame>,
+    width: usize,
+    height: usize,
+    info: CameraInfo,
+    /// Index of the frame served by the next exposure
+    next: usize,
+    /// Index of the frame served by the last exposure
+    current: Option<usize>,
+    /// Frame of the running exposure and the time it finishes
+    exposure: Option<(usize, Instant)>,
+    temperature: f32,
+}
+
+impl ImagerDevice for PlaybackImagerDevice {
+    fn read_properties(&mut self) -> Result<ImagerProperties, ImagerError> {
+        Ok(ImagerProperties {
+            basic: BasicProperties {
+                width: self.width,
+                height: self.height,
+                temperature: self.temperature,
+            },
+            info: self.info.clone(),
+            other: self.list_properties(),
+            read_modes: playback_read_modes(),
+            filters: vec![],
+            telemetry: Telemetry { chip_temperature: self.temperature, ..Default::default() },
+        })
+    }
+
+    fn close(&mut self) {
+
+    }
+
+    fn start_exposure(&mut self, params: &ExposureParams) -> Result<(), ImagerError> {
+        let frame = &self.frames[self.next];
+        let time = frame.exposure.unwrap_or(params.time);
+        let duration = Duration::try_from_secs_f64(time)
+            .map_err(|error| ImagerError::invalid_parameter(error.to_string()))?;
+        self.exposure = Some((self.next, Instant::now() + duration));
+        self.current = Some(self.next);
+        self.next = (self.next + 1) % self.frames.len();
+        Ok(())
+    }
+
+    fn abort_exposure(&mut self) -> Result<(), ImagerError> {
+        self.exposure = None;
+        Ok(())
+    }
+
+    fn image_ready(&mut self, ) -> Result<bool, ImagerError> {
+        Ok(self.exposure.is_some_and(|(_, end)| Instant::now() >= end))
+    }
+
+    fn download_image(&mut self, params: &ExposureParams) -> Result<Vec<u16>, ImagerError> {
+        let (index, _) = self.exposure.take().ok_or(ImagerError::other("No exposure to download"))?;
+        let frame = &self.frames[index];
+
+        let (header, data) = read_fits_image(&frame.path)
+            .map_err(|error| format!("{}: {}", frame.path.display(), error))?;
+
+        let (width, height) = header.dimensions()?;
+
+        if (width, height) != (self.width, self.height) {
+            let message = format!("{} changed its dimensions", frame.path.display());
+            return Err(ImagerError::other(message))
+        }
+
+        if let Some(temperature) = frame_temperature(&header) {
+            self.temperature = temperature;
+        }
+
+        extract_area(&data, width, &params.area, params.binning)
+            .map_err(ImagerError::invalid_parameter)
+    }
+
+    fn set_temperature(&mut self, _request: TemperatureRequest) -> Result<(), ImagerError> {
+        Ok(())
+    }
+
+    fn cooler_off(&mut self, _warm_up: TemperatureRequest) -> Result<(), ImagerError> {
+        Ok(())
+    }
+
+    fn list_read_modes(&mut self) -> Result<Vec<String>, ImagerError> {
+        Ok(playback_read_modes())
+    }
+
+    fn set_read_mode(&mut self, mode: usize) -> Result<(), ImagerError> {
+        match mode < playback_read_modes().len() {
+            true => Ok(()),
+            false => Err(ImagerError::invalid_parameter(format!("Invalid read mode {}", mode))),
+        }
+    }
+
+    fn list_filters(&mut self) -> Result<Vec<Filter>, ImagerError> {
+        Ok(vec![])
+    }
+
+    fn set_filter(&mut self, filter: usize) -> Result<(), ImagerError> {
+        Err(ImagerError::invalid_parameter(
+            format!("Invalid filter {}, playback has no filter wheel", filter)
+        ))
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+const EXTENSIONS: [&str; 3] = ["fits", "fit", "fts"];
+/// Reported when frames do not record the chip temperature
+const DEFAULT_TEMPERATURE: f32 = 20.0;
+
+/// File selected for playback with values needed before its data are loaded
+struct Frame {
+    path: PathBuf,
+    /// Recorded exposure time in seconds, requested time is used when missing
+    exposure: Option<f64>,
+}
+
+impl PlaybackImagerDevice {
+    /// Frames are defined by the first readable file, files of other dimensions are skipped
+    fn new(directory: &Path) -> Result<Self, String> {
+        let mut first: Option<(FitsHeader, (usize, usize))> = None;
+        let mut frames = Vec::new();
+
+        for path in list_fits_files(directory)? {
+            let (header, dimensions) = match read_frame_header(&path) {
+                Ok(result) => result,
+                Err(error) => {
+                    warn!("Playback skips {}: {}", path.display(), error);
+                    continue
+                }
+            };
+
+            if first.as_ref().is_some_and(|(_, first)| *first != dimensions) {
+                warn!("Playback skips {}: dimensions differ", path.display());
+                continue
+            }
+
+            let exposure = header.number("EXPTIME").or(header.number("EXPOSURE"));
+            frames.push(Frame { path, exposure });
+            first.get_or_insert((header, dimensions));
+        }
+
+        let (header, (width, height)) = first
+            .ok_or(format!("No readable FITS files in {}", directory.display()))?;
+
+        info!("Playback of {} frames of {}x{} pixels", frames.len(), width, height);
+
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            frames,
+            width,
+            height,
+            info: camera_info(&header),
+            next: 0,
+            current: None,
+            exposure: None,
+            temperature: frame_temperature(&header).unwrap_or(DEFAULT_TEMPERATURE),
+        })
+    }
+
+    fn list_properties(&self) -> Vec<DeviceProperty> {
+        let file_name = self.current
+            .and_then(|index| self.frames[index].path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        vec![
+            prop("Directory", self.directory.display().to_string()),
+            prop("Frames", self.frames.len().to_string()),
+            prop("Current Frame", file_name),
+        ]
+    }
+}
+
+fn prop(name: &str, value: String) -> DeviceProperty {
+    DeviceProperty { name: name.to_owned(), value }
+}
+
+fn playback_read_modes() -> Vec<String> {
+    vec![String::from("Playback")]
+}
+
+fn list_fits_files(directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let is_fits = |path: &Path| path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+
+    let mut files = std::fs::read_dir(directory)
+        .map_err(|error| format!("Could not read {}: {}", directory.display(), error))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_fits(path))
+        .collect::<Vec<_>>();
+
+    files.sort();
+    Ok(files)
+}
+
+fn read_frame_header(path: &Path) -> Result<(FitsHeader, (usize, usize)), String> {
+    let header = read_fits_header(path)?;
+    let dimensions = header.dimensions()?;
+    Ok((header, dimensions))
+}
+
+fn camera_info(header: &FitsHeader) -> CameraInfo {
+    let pixel_width = header.number("XPIXSZ").unwrap_or(0.0) as f32;
+
+    CameraInfo {
+        name: header.text("INSTRUME").unwrap_or("CCDI Playback").to_owned(),
+        pixel_width,
+        pixel_height: header.number("YPIXSZ").map(|size| size as f32).unwrap_or(pixel_width),
+        bayer_pattern: bayer_pattern(header),
+    }
+}
+
+/// Pattern of the frame origin, the chip pattern is shifted by the bayer offsets
+fn bayer_pattern(header: &FitsHeader) -> Option<BayerPattern> {
+    let offset = |name| header.number(name).unwrap_or(0.0).max(0.0) as usize;
+    let pattern = header.text("BAYERPAT").and_then(BayerPattern::from_name)?;
+    Some(pattern.shifted(offset("XBAYROFF"), offset("YBAYROFF")))
+}
+
+fn frame_temperature(header: &FitsHeader) -> Option<f32> {
+    header.number("CCD-TEMP").map(|temperature| temperature as f32)
+}
+
+/// Cut the requested area out of the frame, binned pixels are sums of chip pixels
+/// saturated at the 16 bit range like binned pixels of a camera
+fn extract_area(
+    data: &[u16],
+    width: usize,
+    area: &ExposureArea,
+    binning: Binning,
+) -> Result<Vec<u16>, String> {
+    let (bin_x, bin_y) = (binning.x.max(1), binning.y.max(1));
+    let height = data.len()/width.max(1);
+
+    if (area.x + area.width)*bin_x > width || (area.y + area.height)*bin_y > height {
+        return Err(format!("Requested area {:?} exceeds the frame {}x{}", area, width, height))
+    }
+
+    let pixel = |x: usize, y: usize| -> u16 {
+        let sum = (0..bin_y)
+            .flat_map(|dy| (0..bin_x).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| data[(y*bin_y + dy)*width + x*bin_x + dx] as u32)
+            .sum::<u32>();
+
+        sum.min(u16::MAX as u32) as u16
+    };
+
+    Ok(
+        (area.y..area.y + area.height)
+            .flat_map(|y| (area.x..area.x + area.width).map(move |x| (x, y)))
+            .map(|(x, y)| pixel(x, y))
+            .collect()
+    )
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ccdi_common::test_exposure_params;
+
+    use crate::fits::tests::fits;
+
+    use super::*;
+
+    /// 4x2 frame with pixel values `first`, `first + 1`, ...
+    fn frame(first: u16, cards: &[&str]) -> Vec<u8> {
+        let image = [
+            "SIMPLE  =                    T", "BITPIX  =                   16",
+            "NAXIS   =                    2", "NAXIS1  =                    4",
+            "NAXIS2  =                    2", "BZERO   =                32768",
+        ];
+
+        let data = (first..first + 8)
+            .flat_map(|value| ((value as i32 - 32768) as i16).to_be_bytes())
+            .collect::<Vec<u8>>();
+
+        fits(&[&image[..], cards].concat(), &data)
+    }
+
+    fn expose(device: &mut dyn ImagerDevice, params: &ExposureParams) -> Vec<u16> {
+        device.start_exposure(params).unwrap();
+        assert!(device.image_ready().unwrap());
+        device.download_image(params).unwrap()
+    }
+
+    #[test]
+    fn area_is_cut_and_binned() {
+        let data = (0..24).collect::<Vec<u16>>();
+        let area = ExposureArea { x: 1, y: 1, width: 2, height: 2 };
+        assert_eq!(extract_area(&data, 6, &area, Binning::new(1, 1)).unwrap(), vec![7, 8, 13, 14]);
+
+        let area = ExposureArea { x: 1, y: 0, width: 2, height: 2 };
+        let binned = extract_area(&data, 6, &area, Binning::new(2, 2)).unwrap();
+        assert_eq!(binned, vec![
+            2 + 3 + 8 + 9, 4 + 5 + 10 + 11, 14 + 15 + 20 + 21, 16 + 17 + 22 + 23
+        ]);
+
+        let area = ExposureArea { x: 2, y: 0, width: 2, height: 2 };
+        assert!(extract_area(&data, 6, &area, Binning::new(2, 2)).is_err());
+    }
+
+    #[test]
+    fn binned_pixels_saturate() {
+        let data = vec![40000, 30000, 100, 200];
+        let area = ExposureArea { x: 0, y: 0, width: 1, height: 1 };
+        assert_eq!(extract_area(&data, 2, &area, Binning::new(2, 2)).unwrap(), vec![u16::MAX]);
+    }
+
+    #[test]
+    fn frames_of_directory_are_played_in_order() {
+        let directory = std::env::temp_dir()
+            .join(format!("ccdi-playback-test-{}", std::process::id()));
+
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("b.fits"), frame(100, &["EXPTIME =                  0.0"]))
+            .unwrap();
+        fs::write(directory.join("a.fit"), frame(0, &[
+            "EXPTIME =                  0.0", "CCD-TEMP=                -10.0",
+            "BAYERPAT= 'RGGB'", "XBAYROFF=                    1",
+        ])).unwrap();
+        fs::write(directory.join("notes.txt"), "not a frame").unwrap();
+
+        let mut driver = PlaybackImagerDriver::new(directory.clone());
+        let devices = driver.list_devices().unwrap();
+        assert_eq!(devices.len(), 1);
+
+        let mut device = driver.connect_device(&devices[0]).unwrap();
+        let properties = device.read_properties().unwrap();
+        assert_eq!((properties.basic.width, properties.basic.height), (4, 2));
+        assert_eq!(properties.basic.temperature, -10.0);
+        assert_eq!(properties.info.bayer_pattern, Some(BayerPattern::Grbg));
+
+        let full = ExposureParams { time: 0.0, ..test_exposure_params(4, 2) };
+        assert_eq!(expose(device.as_mut(), &full), (0..8).collect::<Vec<u16>>());
+        assert_eq!(expose(device.as_mut(), &full), (100..108).collect::<Vec<u16>>());
+
+        let binned = ExposureParams {
+            area: ExposureArea { x: 1, y: 0, width: 1, height: 1 },
+            binning: Binning::new(2, 2),
+            ..full
+        };
+
+        assert_eq!(expose(device.as_mut(), &binned), vec![2 + 3 + 6 + 7]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,12 +1,15 @@
-use std::sync::{Arc, mpsc::Sender};
+use std::{sync::{Arc, mpsc::Sender}, time::Instant};
 
 use ccdi_common::{
-    ExposureCommand, ClientMessage, ConnectionState, ProcessMessage, CameraParams, StorageMessage
+    ExposureCommand, ClientMessage, ConnectionState, ProcessMessage, CameraParams, StorageMessage,
+    RawImage, TelemetrySample, log_err
 };
-use ccdi_imager_interface::{ImagerDevice, ImagerProperties, TemperatureRequest};
-use nanocv::ImgSize;
+use ccdi_imager_interface::{ImagerDevice, ImagerProperties, TemperatureRequest, ImagerError};
+use crate::ServiceConfig;
 
-use super::{properties::PropertiesController, exposure::ExposureController};
+use super::{
+    properties::PropertiesController, exposure::ExposureController, cooling::CoolingMonitor
+};
 
 // ============================================ PUBLIC =============================================
 
@@ -15,58 +18,85 @@ pub struct ConnectedCameraController {
     properties: PropertiesController,
     exposure: ExposureController,
     messages: Vec<ClientMessage>,
-    last_temperature_set: Option<f64>,
+    last_temperature_set: Option<TemperatureRequest>,
+    cooling: CoolingMonitor,
 }
 
 impl ConnectedCameraController {
     pub fn new(
         mut device: Box<dyn ImagerDevice>,
-        render_size: ImgSize,
+        config: Arc<ServiceConfig>,
         process_tx: Sender<ProcessMessage>,
         storage_tx: Sender<StorageMessage>,
-    ) -> Result<Self, String> {
-        let properties = PropertiesController::new(device.as_mut())?;
+        now: Instant,
+    ) -> Result<Self, ImagerError> {
+        let properties = PropertiesController::new(device.as_mut(), now)?;
+        let read_modes = device.list_read_modes()?;
+        let filters = device.list_filters()?;
+
+        let cooling = CoolingMonitor::new(config.cooling.clone());
 
         let exposure = ExposureController::new(
-            render_size, properties.get_properties().basic, process_tx, storage_tx
+            config, &properties.get_properties(), read_modes, filters, process_tx, storage_tx, now
         );
 
-        let last_temperature_set = None;
-        Ok(Self {properties, exposure, device, messages: vec![], last_temperature_set})
+        Ok(Self {
+            properties,
+            exposure,
+            device,
+            messages: vec![],
+            last_temperature_set: None,
+            cooling,
+        })
     }
 
     pub fn close(mut self) {
         self.device.close()
     }
 
-    pub fn periodic(&mut self, temperature: f64) -> Result<(), String> {
-        self.messages.append(&mut self.exposure.periodic(self.device.as_mut())?);
+    pub fn periodic(
+        &mut self,
+        temperature: TemperatureRequest,
+        now: Instant,
+    ) -> Result<(), ImagerError> {
+        let properties = self.properties.get_properties();
+        self.exposure.update_chip_temperature(properties.basic.temperature);
+        self.cooling.update(temperature.temperature as f64, &properties.telemetry, now);
+        self.exposure.update_cooling_ready(self.cooling.is_ready());
+        self.messages.append(&mut self.exposure.periodic(self.device.as_mut(), now)?);
 
         if self.last_temperature_set != Some(temperature) {
-            self.device.set_temperature(
-                TemperatureRequest { temperature: temperature as f32, speed: 3.0 }
-            )?;
+            self.device.set_temperature(temperature)?;
             self.last_temperature_set = Some(temperature);
         }
 
-        self.properties
-            .read_properties(self.device.as_mut())
-            .map_err(|_| format!("Periodic read properties failed"))
-
+        self.properties.read_properties(self.device.as_mut(), now)
     }
 
     pub fn get_properties(&self) -> Arc<ImagerProperties> {
         self.properties.get_properties()
     }
 
+    pub fn take_telemetry(&mut self) -> Vec<TelemetrySample> {
+        self.properties.take_samples()
+    }
+
     pub fn update_camera_params(&mut self, params: CameraParams) {
         self.exposure.update_camera_params(params);
     }
 
-    pub fn exposure_command(&mut self, command: ExposureCommand) -> Result<(), String> {
+    pub fn select_filter(&mut self) -> Result<(), ImagerError> {
+        self.exposure.select_filter(self.device.as_mut())
+    }
+
+    pub fn exposure_command(&mut self, command: ExposureCommand) -> Result<(), ImagerError> {
         self.exposure.exposure_command(self.device.as_mut(), command)
     }
 
+    pub fn focus_exposure(&mut self) -> Result<(), ImagerError> {
+        self.exposure.focus_exposure(self.device.as_mut())
+    }
+
     pub fn flush_messages(&mut self) -> Vec<ClientMessage> {
         let mut result = Vec::new();
         result.append(&mut self.messages);
@@ -81,11 +111,31 @@ impl ConnectedCameraController {
         }
     }
 
+    /// Chip temperature stayed at the target for the configured time
+    pub fn cooling_status(&self) -> ConnectionState {
+        self.cooling.status()
+    }
+
+    /// Loop and sequence exposures may be started at the current temperature
+    pub fn cooling_ready(&self) -> bool {
+        self.cooling.is_ready()
+    }
+
+    pub fn image_available(&self) -> bool {
+        self.exposure.image_available()
+    }
+
+    pub fn take_image(&mut self) -> Option<Arc<RawImage>> {
+        self.exposure.take_image()
+    }
+
     pub fn update_trigger_status(&mut self, value: bool) {
         self.exposure.update_trigger_status(value);
     }
 
-    pub fn turn_off(&mut self) {
+    /// Switch the cooler off and close the camera
+    pub fn turn_off(mut self, warm_up: TemperatureRequest) {
+        log_err("Turn cooler off", self.device.cooler_off(warm_up));
         self.device.close();
     }
-}
\ No newline at end of file
+}
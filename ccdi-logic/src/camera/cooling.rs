@@ -0,0 +1,178 @@
+use std::time::Instant;
+
+use ccdi_common::ConnectionState;
+use ccdi_imager_interface::Telemetry;
+use log::warn;
+
+use crate::CoolingConfig;
+
+// ============================================ PUBLIC =============================================
+
+/// Chip temperature is stable when it stays within the tolerance of the target for
+/// the configured time, a new target starts the wait again
+pub struct CoolingMonitor {
+    config: CoolingConfig,
+    target: Option<f64>,
+    /// Time the current target was set
+    target_since: Option<Instant>,
+    /// Time the chip temperature got within the tolerance
+    within_since: Option<Instant>,
+    stable: bool,
+}
+
+impl CoolingMonitor {
+    pub fn new(config: CoolingConfig) -> Self {
+        Self { config, target: None, target_since: None, within_since: None, stable: false }
+    }
+
+    pub fn update(&mut self, target: f64, telemetry: &Telemetry, now: Instant) {
+        if self.target != Some(target) {
+            self.target = Some(target);
+            self.target_since = Some(now);
+            self.within_since = None;
+        }
+
+        let chip = telemetry.chip_temperature as f64;
+        let within = (chip - target).abs() <= self.config.tolerance;
+        let idle = chip < target && match telemetry.cooler_power {
+            // Idle cooler below the target means the target is above ambient, the chip
+            // can not get any closer to it
+            Some(power) => power <= 0.0,
+            None => self.below_target_timed_out(within, now),
+        };
+
+        self.within_since = match within || idle {
+            false => None,
+            true => self.within_since.or(Some(now)),
+        };
+
+        self.stable = self.within_since.is_some_and(
+            |since| now.duration_since(since).as_secs_f64() >= self.config.stable_time
+        );
+    }
+
+    /// Loop and sequence exposures wait for stable cooling only when configured to
+    pub fn is_ready(&self) -> bool {
+        !self.config.wait_for_stable || self.stable
+    }
+
+    pub fn status(&self) -> ConnectionState {
+        match self.stable {
+            false => ConnectionState::Connecting,
+            true => ConnectionState::Established,
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+impl CoolingMonitor {
+    /// Without the cooler power the target may be above ambient, the chip below it
+    /// counts as stable after the configured timeout
+    fn below_target_timed_out(&self, within: bool, now: Instant) -> bool {
+        let timed_out = self.target_since.is_some_and(
+            |since| now.duration_since(since).as_secs_f64() >= self.config.timeout
+        );
+
+        if timed_out && !within && self.within_since.is_none() {
+            warn!("Cooler power unknown, chip below the target taken as stable after timeout");
+        }
+
+        timed_out
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn monitor() -> CoolingMonitor {
+        let config = CoolingConfig { tolerance: 0.5, stable_time: 60.0, ..Default::default() };
+        CoolingMonitor::new(config)
+    }
+
+    fn stable(monitor: &CoolingMonitor) -> bool {
+        monitor.status() == ConnectionState::Established
+    }
+
+    fn unknown_power(chip_temperature: f32) -> Telemetry {
+        Telemetry { chip_temperature, cooler_power: None, ..Default::default() }
+    }
+
+    fn telemetry(chip_temperature: f32, power: f32) -> Telemetry {
+        Telemetry { chip_temperature, cooler_power: Some(power), ..Default::default() }
+    }
+
+    fn at(start: Instant, seconds: u64) -> Instant {
+        start + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn stable_after_staying_within_tolerance() {
+        let start = Instant::now();
+        let mut monitor = monitor();
+
+        monitor.update(-10.0, &telemetry(-9.7, 0.5), start);
+        monitor.update(-10.0, &telemetry(-10.2, 0.5), at(start, 59));
+        assert!(!stable(&monitor));
+
+        monitor.update(-10.0, &telemetry(-10.1, 0.5), at(start, 60));
+        assert!(stable(&monitor));
+
+        monitor.update(-10.0, &telemetry(-9.0, 0.5), at(start, 61));
+        monitor.update(-10.0, &telemetry(-10.0, 0.5), at(start, 62));
+        assert!(!stable(&monitor));
+        assert_eq!(monitor.status(), ConnectionState::Connecting);
+    }
+
+    #[test]
+    fn new_target_restarts_wait() {
+        let start = Instant::now();
+        let mut monitor = monitor();
+
+        monitor.update(-10.0, &telemetry(-10.0, 0.5), start);
+        monitor.update(-10.0, &telemetry(-10.0, 0.5), at(start, 60));
+        assert!(stable(&monitor));
+
+        monitor.update(-10.2, &telemetry(-10.0, 0.5), at(start, 61));
+        assert!(!stable(&monitor));
+        monitor.update(-10.2, &telemetry(-10.0, 0.5), at(start, 121));
+        assert!(stable(&monitor));
+    }
+
+    #[test]
+    fn idle_cooler_below_target_is_stable() {
+        let start = Instant::now();
+        let mut monitor = monitor();
+
+        monitor.update(25.0, &telemetry(20.0, 0.0), start);
+        monitor.update(25.0, &telemetry(20.0, 0.0), at(start, 60));
+        assert!(stable(&monitor));
+
+        monitor.update(25.0, &telemetry(30.0, 0.0), at(start, 61));
+        assert!(!stable(&monitor));
+    }
+
+    #[test]
+    fn unknown_cooler_power_below_target_times_out() {
+        let start = Instant::now();
+        let config = CoolingConfig { timeout: 300.0, ..Default::default() };
+        let mut monitor = CoolingMonitor::new(config);
+
+        monitor.update(25.0, &unknown_power(20.0), start);
+        monitor.update(25.0, &unknown_power(20.0), at(start, 299));
+        assert!(!stable(&monitor));
+        assert!(monitor.is_ready());
+
+        monitor.update(25.0, &unknown_power(20.0), at(start, 300));
+        monitor.update(25.0, &unknown_power(20.0), at(start, 360));
+        assert!(stable(&monitor));
+
+        monitor.update(25.0, &unknown_power(30.0), at(start, 361));
+        assert!(!stable(&monitor));
+    }
+}
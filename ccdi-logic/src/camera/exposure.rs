@@ -1,62 +1,110 @@
 
-use std::{mem::swap, sync::{mpsc::Sender, Arc}, time::SystemTime};
+use std::{mem::swap, sync::{mpsc::Sender, Arc}, time::{Duration, Instant, SystemTime}};
 
 use ccdi_common::{
     ExposureCommand, ClientMessage, RawImage, ProcessMessage, ConvertRawImage, log_err,
-    CameraParams, StorageMessage
+    CameraParams, StorageMessage, ImageMetadata
+};
+use ccdi_imager_interface::{
+    BasicProperties, ImagerDevice, ExposureParams, ExposureArea, Binning, ReadMode, CameraInfo,
+    ImagerProperties, ImagerError, Filter
 };
-use ccdi_imager_interface::{BasicProperties, ImagerDevice, ExposureParams, ExposureArea};
 use log::debug;
-use nanocv::ImgSize;
+
+use crate::ServiceConfig;
 
 // ============================================ PUBLIC =============================================
 
 pub struct ExposureController {
+    config: Arc<ServiceConfig>,
     properties: BasicProperties,
+    info: CameraInfo,
+    read_modes: Vec<String>,
+    filters: Vec<Filter>,
     camera_params: CameraParams,
     current_exposure: Option<ExposureParams>,
+    current_metadata: Option<ImageMetadata>,
+    /// Time after which the image of the current exposure is considered lost
+    download_deadline: Option<Instant>,
     process_tx: Sender<ProcessMessage>,
     storage_tx: Sender<StorageMessage>,
     trigger_active: bool,
+    chip_temperature: Option<f32>,
+    /// Loop exposures may be started at the current temperature
+    cooling_ready: bool,
+    downloaded: Option<Arc<RawImage>>,
+    /// Image of the current exposure is passed to the storage
+    current_saved: bool,
+    /// Time of the last periodic call, download deadlines are counted from it
+    now: Instant,
 }
 
 impl ExposureController {
     pub fn new(
-        render_size: ImgSize,
-        properties: BasicProperties,
+        config: Arc<ServiceConfig>,
+        properties: &ImagerProperties,
+        read_modes: Vec<String>,
+        filters: Vec<Filter>,
         process_tx: Sender<ProcessMessage>,
         storage_tx: Sender<StorageMessage>,
+        now: Instant,
     ) -> Self {
         Self {
-            properties,
-            camera_params: CameraParams::new(render_size),
+            camera_params: CameraParams::new(config.render_size),
+            config,
+            properties: properties.basic,
+            info: properties.info.clone(),
+            read_modes,
+            filters,
             current_exposure: None,
+            current_metadata: None,
+            download_deadline: None,
             process_tx,
             storage_tx,
             trigger_active: false,
+            chip_temperature: None,
+            cooling_ready: false,
+            downloaded: None,
+            current_saved: false,
+            now,
         }
     }
 
     pub fn periodic(
         &mut self,
-        device: &mut dyn ImagerDevice
-    ) -> Result<Vec<ClientMessage>, String> {
+        device: &mut dyn ImagerDevice,
+        now: Instant,
+    ) -> Result<Vec<ClientMessage>, ImagerError> {
+        self.now = now;
+
         if self.current_exposure.is_some() && device.image_ready()? {
             debug!("Image ready to download");
             let mut exposure = None;
             swap(&mut exposure, &mut self.current_exposure);
+            self.download_deadline = None;
 
             if let Some(params) = exposure {
                 let data = device.download_image(&params)?;
-                let raw_image = RawImage { params, data };
+                check_image_size(&params, &data)?;
+                let metadata = self.current_metadata.take().unwrap_or_default();
+                let raw_image = RawImage { params, metadata, data };
                 debug!("Image downloaded");
-                self.call_process_message(Arc::new(raw_image));
+                let image = Arc::new(raw_image);
+                self.call_process_message(image.clone(), self.current_saved);
+                self.downloaded = Some(image);
             }
         }
 
-        if !self.exposure_active() && self.camera_params.loop_enabled {
+        if self.download_deadline.is_some_and(|deadline| now > deadline) {
+            self.current_exposure = None;
+            self.current_metadata = None;
+            self.download_deadline = None;
+            return Err(ImagerError::timeout("Image not ready before the download timeout"))
+        }
+
+        if !self.exposure_active() && self.camera_params.loop_enabled && self.cooling_ready {
             if self.trigger_active || !self.camera_params.trigger_required  {
-                self.start_exposure(device)?;
+                self.start_exposure(device, true)?;
             }
         }
 
@@ -67,65 +115,278 @@ impl ExposureController {
         self.camera_params = params;
     }
 
+    /// Move the filter wheel to the selected filter, cameras without a wheel are skipped
+    pub fn select_filter(&mut self, device: &mut dyn ImagerDevice) -> Result<(), ImagerError> {
+        match self.filter_name().is_some() {
+            false => Ok(()),
+            true => device.set_filter(self.camera_params.filter),
+        }
+    }
+
     pub fn exposure_command(
         &mut self,
         device: &mut dyn ImagerDevice,
         command: ExposureCommand
-    ) -> Result<(), String> {
+    ) -> Result<(), ImagerError> {
         Ok(match command {
-            ExposureCommand::Start => self.start_exposure(device)?,
+            ExposureCommand::Start => self.start_exposure(device, true)?,
+            ExposureCommand::Stop => self.abort_exposure(device)?,
         })
     }
 
+    /// Exposure measured by the autofocus, the image is not stored
+    pub fn focus_exposure(&mut self, device: &mut dyn ImagerDevice) -> Result<(), ImagerError> {
+        self.start_exposure(device, false)
+    }
+
     pub fn exposure_active(&self) -> bool {
         self.current_exposure.is_some()
     }
 
+    /// Image was downloaded and not taken yet
+    pub fn image_available(&self) -> bool {
+        self.downloaded.is_some()
+    }
+
+    /// Take the last downloaded image, if not taken yet
+    pub fn take_image(&mut self) -> Option<Arc<RawImage>> {
+        self.downloaded.take()
+    }
+
+    pub fn update_chip_temperature(&mut self, temperature: f32) {
+        self.chip_temperature = Some(temperature);
+    }
+
     pub fn update_trigger_status(&mut self, value: bool) {
         self.trigger_active = value;
     }
+
+    pub fn update_cooling_ready(&mut self, value: bool) {
+        self.cooling_ready = value;
+    }
 }
 
 // =========================================== PRIVATE =============================================
 
 impl ExposureController {
-    fn call_process_message(&self, image: Arc<RawImage>) {
+    fn call_process_message(&self, image: Arc<RawImage>, saved: bool) {
         let rendering = self.camera_params.rendering;
         let size = self.camera_params.render_size;
-        let message = StorageMessage::ProcessImage(image.clone());
-        log_err("Self process message", self.storage_tx.send(message));
-        let message = ProcessMessage::ConvertRawImage(ConvertRawImage{image, size, rendering});
+        let quality = self.camera_params.demosaic;
+
+        if saved {
+            let message = StorageMessage::ProcessImage(image.clone());
+            log_err("Self process message", self.storage_tx.send(message));
+        }
+
+        let message = ProcessMessage::ConvertRawImage(ConvertRawImage{image, size, rendering, quality});
         log_err("Self process message", self.process_tx.send(message));
     }
 
-    fn start_exposure(&mut self, device: &mut dyn ImagerDevice) -> Result<(), String> {
+    fn start_exposure(
+        &mut self,
+        device: &mut dyn ImagerDevice,
+        saved: bool,
+    ) -> Result<(), ImagerError> {
         debug!("Starting exposure");
         if self.current_exposure.is_some() {
-            return Err(format!("Exposure already in progress."))
+            return Err(ImagerError::busy("Exposure already in progress."))
+        }
+
+        let params = self.make_exposure_description()?;
+
+        if let Some(mode) = params.read_mode.as_ref() {
+            device.set_read_mode(mode.index)?;
         }
 
-        let params = self.make_exposure_description();
         let result = device.start_exposure(&params);
 
         if result.is_ok() {
-            self.current_exposure = Some(params)
+            let wait = Duration::try_from_secs_f64(params.time + self.config.download_timeout)
+                .unwrap_or(Duration::MAX);
+
+            self.download_deadline = self.now.checked_add(wait);
+            self.current_metadata = Some(self.make_metadata(&params));
+            self.current_exposure = Some(params);
+            self.current_saved = saved;
         }
 
         debug!("Exposure started");
         result
     }
 
-    fn make_exposure_description(&self) -> ExposureParams {
-        ExposureParams {
+    fn abort_exposure(&mut self, device: &mut dyn ImagerDevice) -> Result<(), ImagerError> {
+        self.camera_params.loop_enabled = false;
+        self.current_metadata = None;
+        self.download_deadline = None;
+
+        if self.current_exposure.take().is_some() {
+            debug!("Aborting exposure");
+            device.abort_exposure()?;
+            debug!("Exposure aborted");
+        }
+
+        Ok(())
+    }
+
+    fn make_exposure_description(&self) -> Result<ExposureParams, ImagerError> {
+        let binning = self.camera_params.binning;
+
+        Ok(ExposureParams {
             gain: self.camera_params.gain,
             time: self.camera_params.time,
-            area: ExposureArea {
-                x: 0,
-                y: 0,
-                width: self.properties.width,
-                height: self.properties.height
-            },
+            area: binned_area(&self.chip_area()?, &self.full_area(), binning)?,
+            binning,
+            read_mode: self.read_mode(),
+            frame_type: self.camera_params.frame_type,
             start_time: SystemTime::now(),
+        })
+    }
+
+    fn make_metadata(&self, params: &ExposureParams) -> ImageMetadata {
+        ImageMetadata {
+            camera_name: self.info.name.clone(),
+            pixel_width: self.info.pixel_width,
+            pixel_height: self.info.pixel_height,
+            temperature: self.chip_temperature,
+            target_temperature: self.camera_params.temperature,
+            bayer_pattern: match params.binning.is_binned() {
+                false => self.config.bayer_pattern.or(self.info.bayer_pattern),
+                true => None,
+            },
+            filter: self.filter_name().cloned(),
+            object: self.camera_params.object.clone(),
+            coordinates: self.camera_params.coordinates,
+            optics: self.config.optics.clone(),
+            site: self.config.site.clone(),
         }
     }
+
+    fn read_mode(&self) -> Option<ReadMode> {
+        let index = self.camera_params.read_mode;
+        self.read_modes.get(index).map(|name| ReadMode { index, name: name.clone() })
+    }
+
+    /// Name of the selected filter, `None` without a filter wheel
+    fn filter_name(&self) -> Option<&String> {
+        self.filters.get(self.camera_params.filter).map(|filter| &filter.name)
+    }
+
+    fn full_area(&self) -> ExposureArea {
+        ExposureArea {
+            x: 0,
+            y: 0,
+            width: self.properties.width,
+            height: self.properties.height
+        }
+    }
+
+    /// Selected ROI clipped to the chip, an ROI outside of the chip is rejected
+    fn chip_area(&self) -> Result<ExposureArea, ImagerError> {
+        let full = self.full_area();
+
+        match self.camera_params.roi.as_ref() {
+            None => Ok(full),
+            Some(roi) => {
+                let x = roi.x.min(full.width);
+                let y = roi.y.min(full.height);
+                let width = roi.width.min(full.width - x);
+                let height = roi.height.min(full.height - y);
+
+                match width == 0 || height == 0 {
+                    true => Err(ImagerError::invalid_parameter("ROI is outside of the chip")),
+                    false => Ok(ExposureArea { x, y, width, height }),
+                }
+            }
+        }
+    }
+}
+
+/// Image with missing pixels would break processing and storage
+fn check_image_size(params: &ExposureParams, data: &[u16]) -> Result<(), String> {
+    let expected = params.area.pixel_count();
+
+    match data.len() == expected {
+        true => Ok(()),
+        false => Err(format!("Downloaded {} pixels, expected {}", data.len(), expected)),
+    }
+}
+
+/// Convert chip area to binned pixels, the area is aligned to whole binned pixels
+/// and never exceeds the chip. Unbinned color images keep the ROI origin, the bayer
+/// pattern offset is given by it.
+fn binned_area(
+    area: &ExposureArea,
+    chip: &ExposureArea,
+    binning: Binning
+) -> Result<ExposureArea, ImagerError> {
+    let step_x = binning.x.max(1);
+    let step_y = binning.y.max(1);
+    // Pixels at the chip edge are moved inside when the chip is not a multiple of the binning
+    let x = (area.x/step_x*step_x).min((chip.width/step_x*step_x).saturating_sub(step_x));
+    let y = (area.y/step_y*step_y).min((chip.height/step_y*step_y).saturating_sub(step_y));
+    let max_width = chip.width.saturating_sub(x)/step_x*step_x;
+    let max_height = chip.height.saturating_sub(y)/step_y*step_y;
+    let width = ((area.x + area.width - x)/step_x*step_x).max(step_x).min(max_width);
+    let height = ((area.y + area.height - y)/step_y*step_y).max(step_y).min(max_height);
+
+    if width == 0 || height == 0 {
+        return Err(ImagerError::invalid_parameter("ROI is smaller than one binned pixel"))
+    }
+
+    Ok(ExposureArea {
+        x: x/binning.x.max(1),
+        y: y/binning.y.max(1),
+        width: width/binning.x.max(1),
+        height: height/binning.y.max(1),
+    })
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use ccdi_imager_interface::ImagerErrorKind;
+
+    use super::*;
+
+    fn area(x: usize, y: usize, width: usize, height: usize) -> ExposureArea {
+        ExposureArea { x, y, width, height }
+    }
+
+    const CHIP: ExposureArea = ExposureArea { x: 0, y: 0, width: 100, height: 60 };
+
+    #[test]
+    fn full_chip_is_binned() {
+        let binned = binned_area(&CHIP, &CHIP, Binning { x: 2, y: 2 });
+        assert_eq!(binned, Ok(area(0, 0, 50, 30)));
+    }
+
+    #[test]
+    fn roi_is_aligned_to_binned_pixels() {
+        let binned = binned_area(&area(5, 3, 10, 10), &CHIP, Binning { x: 2, y: 2 });
+        assert_eq!(binned, Ok(area(2, 1, 5, 5)));
+    }
+
+    #[test]
+    fn unbinned_roi_keeps_its_origin() {
+        let binned = binned_area(&area(5, 3, 10, 10), &CHIP, Binning::default());
+        assert_eq!(binned, Ok(area(5, 3, 10, 10)));
+    }
+
+    #[test]
+    fn roi_at_chip_edge_stays_on_chip() {
+        let binned = binned_area(&area(97, 58, 3, 2), &CHIP, Binning { x: 3, y: 1 });
+        assert_eq!(binned, Ok(area(32, 58, 1, 2)));
+
+        let binned = binned_area(&area(99, 0, 1, 60), &CHIP, Binning { x: 3, y: 1 }).unwrap();
+        assert!((binned.x + binned.width)*3 <= CHIP.width);
+    }
+
+    #[test]
+    fn roi_smaller_than_binned_pixel_is_rejected() {
+        let chip = area(0, 0, 2, 2);
+        let binned = binned_area(&area(0, 0, 2, 2), &chip, Binning { x: 3, y: 3 });
+        assert_eq!(binned.map_err(|error| error.kind), Err(ImagerErrorKind::InvalidParameter));
+    }
 }
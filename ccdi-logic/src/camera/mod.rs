@@ -2,22 +2,41 @@ mod properties;
 mod exposure;
 mod connected;
 mod command;
+mod selection;
+mod cooling;
+mod shutdown;
 
-use std::sync::{mpsc::Sender, Arc};
+use std::{
+    collections::BTreeMap, path::PathBuf, sync::{mpsc::Sender, Arc},
+    time::{Instant, SystemTime}
+};
 
 use ccdi_common::{
     ConnectionState, ViewState, LogicStatus, ExposureCommand, ClientMessage, ProcessMessage,
-    CameraParams, CameraParamMessage, StorageState, StorageMessage, IoMessage, StorageDetail
+    CameraParams, CameraParamMessage, StorageState, StorageMessage, IoMessage, StorageDetail,
+    SequenceMessage, SpaceLevel, FocuserMessage, FocuserView, SequenceState, CameraListView,
+    TelemetryHistory, TelemetrySample, ShutdownStage, FocusMetrics, log_err
+};
+use ccdi_focuser_interface::FocuserDriver;
+use ccdi_imager_interface::{
+    ImagerDriver, DeviceDescriptor, ImagerError, ImagerErrorKind, TemperatureRequest, Filter
 };
-use ccdi_imager_interface::{ImagerDriver, DeviceDescriptor};
 use log::info;
 
-use crate::ServiceConfig;
+use crate::{
+    ServiceConfig, sequencer::{Sequencer, FrameRequest},
+    focuser::{FocuserController, Autofocus, AutofocusAction},
+};
 
-use self::{connected::ConnectedCameraController, command::execute_command};
+use self::{
+    connected::ConnectedCameraController, selection::CameraSelection,
+    shutdown::{Shutdown, ShutdownAction},
+};
 
 // ============================================ PUBLIC =============================================
 
+pub use self::command::execute_command;
+
 pub struct CameraController {
     driver: Box<dyn ImagerDriver>,
     state: State,
@@ -31,16 +50,35 @@ pub struct CameraController {
     config: Arc<ServiceConfig>,
     trigger_active: bool,
     storage_detail: StorageDetail,
-    turnning_off: bool,
+    /// Power off sequence, present once power off was requested
+    shutdown: Option<Shutdown>,
+    sequencer: Sequencer,
+    /// Storage was enabled by the sequencer and is disabled once the sequence ends
+    sequence_storage: bool,
+    focuser: FocuserController,
+    autofocus: Autofocus,
+    selection: CameraSelection,
+    /// Cameras found by the last listing
+    devices: Vec<DeviceDescriptor>,
+    connected_id: Option<i32>,
+    /// Timeouts and busy errors since the last downloaded image or idle period
+    transient_failures: usize,
+    /// Kept across reconnections so that the charts cover the whole night
+    telemetry: TelemetryHistory,
 }
 
 impl CameraController {
     pub fn new(
         driver: Box<dyn ImagerDriver>,
+        focuser_driver: Option<Box<dyn FocuserDriver>>,
         process_tx: Sender<ProcessMessage>,
         storage_tx: Sender<StorageMessage>,
         config: Arc<ServiceConfig>,
+        sequence_file: Option<PathBuf>,
+        camera_file: Option<PathBuf>,
     ) -> Self {
+        let autofocus = Autofocus::new(config.autofocus.clone());
+
         Self {
             driver,
             state: State::Error,
@@ -54,26 +92,39 @@ impl CameraController {
             config,
             trigger_active: false,
             storage_detail: Default::default(),
-            turnning_off: false,
+            shutdown: None,
+            sequencer: Sequencer::new(sequence_file),
+            sequence_storage: false,
+            focuser: FocuserController::new(focuser_driver),
+            autofocus,
+            selection: CameraSelection::new(camera_file),
+            devices: vec![],
+            connected_id: None,
+            transient_failures: 0,
+            telemetry: Default::default(),
         }
     }
 
-    pub fn periodic(&mut self) -> (Vec<ClientMessage>, Vec<IoMessage>) {
-        if self.turnning_off {
+    pub fn periodic(&mut self, now: Instant) -> (Vec<ClientMessage>, Vec<IoMessage>) {
+        if self.shutdown_finished() {
             return (vec![], vec![])
         }
 
         let old_state = self.state;
 
         self.state = match self.state {
-            State::Error => self.handle_error_state(),
-            State::Connected => self.handle_connected_state(),
+            State::Error => self.handle_error_state(now),
+            State::Connected => self.handle_connected_state(now),
         };
 
         if self.state != old_state {
             info!("Camera state {:?} -> {:?}", old_state, self.state);
         }
 
+        self.run_sequencer();
+        self.run_focuser();
+        self.run_shutdown(now);
+
         let new_view = self.get_view();
 
         let mut messages = vec![];
@@ -85,6 +136,13 @@ impl CameraController {
 
         if let Some(ref mut camera) = self.connected {
             messages.append(&mut camera.flush_messages());
+
+            let samples = camera.take_telemetry();
+
+            if !samples.is_empty() {
+                self.telemetry.extend(samples.iter().copied());
+                messages.push(ClientMessage::Telemetry(samples));
+            }
         }
 
         let states = vec![
@@ -111,18 +169,37 @@ impl CameraController {
                 required: into_state(self.camera_params.trigger_required),
                 save: into_state(self.storage_detail.storage_enabled),
                 loop_enabled: into_state(self.camera_params.loop_enabled),
-
+                cooling: self.connected.as_ref().map(|cam| cam.cooling_status())
+                    .unwrap_or(ConnectionState::Disconnected),
             },
             camera_properties: self.connected.as_ref().map(|cam| cam.get_properties()),
             camera_params: self.camera_params.clone(),
             config: self.config.gui.clone(),
             storage_detail: self.storage_detail.clone(),
+            sequence: self.sequencer.view(),
+            focuser: FocuserView {
+                properties: self.focuser.properties().cloned(),
+                status: self.focuser.status(),
+                autofocus: self.autofocus.view(),
+            },
+            cameras: CameraListView {
+                devices: self.devices.clone(),
+                selected: self.selection.selected(),
+                connected: self.connected_id,
+            },
+            shutdown: self.shutdown.as_ref().map(|shutdown| shutdown.progress()),
         }
     }
 
+    pub fn telemetry(&self) -> Vec<TelemetrySample> {
+        self.telemetry.to_vec()
+    }
+
     pub fn update_camera_params(&mut self, message: CameraParamMessage) {
         use CameraParamMessage::*;
 
+        let old_filter = self.camera_params.filter;
+
         match message {
             EnableLoop(value) => self.camera_params.loop_enabled = value,
             SetGain(gain) => self.camera_params.gain = gain,
@@ -131,18 +208,44 @@ impl CameraController {
             SetTime(time) => self.camera_params.time = time,
             SetRenderingType(rendering) => self.camera_params.rendering = rendering,
             SetTriggerRequired(value) => self.camera_params.trigger_required = value,
+            SetBinning(binning) => self.camera_params.binning = binning,
+            SetRoi(roi) => self.camera_params.roi = roi,
+            SetReadMode(mode) => self.camera_params.read_mode = mode,
+            SetFilter(filter) => self.camera_params.filter = filter,
+            SetFrameType(frame_type) => self.camera_params.frame_type = frame_type,
+            SetCoordinates(coordinates) => self.camera_params.coordinates = coordinates,
+            SetObject(object) => self.camera_params.object = object,
+            SetDemosaicQuality(quality) => self.camera_params.demosaic = quality,
         }
 
         if let Some(camera) =  self.connected.as_mut() {
             camera.update_camera_params(self.camera_params.clone());
         }
+
+        if self.camera_params.filter != old_filter {
+            self.select_filter(old_filter);
+        }
     }
 
     pub fn exposure_command(&mut self, command: ExposureCommand) {
+        let exposure_active = self.exposure_active();
+
+        if command == ExposureCommand::Stop {
+            // Stopped exposure must not be restarted by the loop
+            self.camera_params.loop_enabled = false;
+            self.sequencer.exposure_interrupted();
+            self.autofocus.stop();
+        }
+
         match self.connected.as_mut() {
             None => self.set_detail("Not connected - cannot handle exposure command"),
-            Some(connected) => match connected.exposure_command(command) {
-                Ok(_) => {},
+            Some(connected) => match connected.exposure_command(command.clone()) {
+                Ok(_) => if command == ExposureCommand::Stop {
+                    self.set_detail(match exposure_active {
+                        true => "Exposure aborted",
+                        false => "No exposure running",
+                    })
+                },
                 Err(message) => self.set_detail(
                     &format!("Exposure command failed: {}", message)
                 ),
@@ -150,8 +253,83 @@ impl CameraController {
         }
     }
 
+    /// Focus metrics measured by the process thread on a downloaded frame
+    pub fn focus_measured(&mut self, frame: SystemTime, metrics: &FocusMetrics) {
+        self.autofocus.focus_measured(frame, metrics);
+    }
+
+    pub fn sequence_message(&mut self, message: SequenceMessage) {
+        if message == SequenceMessage::Start {
+            // Sequencer takes over starting of exposures
+            self.camera_params.loop_enabled = false;
+        }
+
+        match self.sequencer.process(message) {
+            Err(message) => self.set_detail(&format!("Sequence command failed: {}", message)),
+            Ok(false) => {},
+            Ok(true) => if let Some(connected) = self.connected.as_mut() {
+                if let Err(message) = connected.exposure_command(ExposureCommand::Stop) {
+                    self.set_detail(&format!("Sequence exposure abort failed: {}", message));
+                }
+            },
+        }
+    }
+
+    pub fn focuser_message(&mut self, message: FocuserMessage) {
+        let result = match message {
+            FocuserMessage::MoveTo(position) => self.move_focuser(position),
+            FocuserMessage::MoveBy(steps) => match self.focuser.status() {
+                Some(status) => self.move_focuser(status.position + steps),
+                None => Err(String::from("Focuser not connected")),
+            },
+            FocuserMessage::Halt => {
+                self.stop_autofocus();
+                self.focuser.halt()
+            },
+            FocuserMessage::StartAutofocus => self.start_autofocus(),
+            FocuserMessage::StopAutofocus => {
+                self.stop_autofocus();
+                Ok(())
+            },
+        };
+
+        if let Err(message) = result {
+            self.set_detail(&format!("Focuser command failed: {}", message));
+        }
+    }
+
+    /// List cameras offered for selection again, while a camera is connected the list
+    /// is refreshed only on request
+    pub fn refresh_cameras(&mut self) {
+        match self.driver.list_devices() {
+            Ok(devices) => self.devices = devices,
+            Err(message) => self.set_detail(&format!("Could not list devices: {}", message)),
+        }
+    }
+
+    /// Selected camera is connected instead of the current one, if different
+    pub fn select_camera(&mut self, id: i32) {
+        self.selection.select(id);
+
+        if self.connected_id.is_some_and(|connected| connected != id) {
+            if let Some(old_device) = self.connected.take() {
+                old_device.close();
+            }
+
+            self.connected_id = None;
+            self.state = State::Error;
+            self.set_detail(&format!("Switching to camera {}", id));
+        }
+    }
+
     pub fn update_storage_status(&mut self, message: StorageState) {
+        let was_critical = space_critical(&self.storage_status);
         self.storage_status = message;
+
+        // Stop only once when the space runs out, so that user can start capture again
+        if !was_critical && space_critical(&self.storage_status) {
+            self.stop_capture_low_space();
+        }
     }
 
     pub fn update_storage_detail(&mut self, detail: StorageDetail) {
@@ -166,15 +344,30 @@ impl CameraController {
         }
     }
 
+    /// Start the power off sequence, capture is stopped and storage disabled, queued
+    /// images are written and the camera is warmed up before it is closed
     pub fn turn_off(&mut self) {
-        if let Some(ref mut camera) = self.connected {
-            camera.turn_off();
+        if self.shutdown.is_some() {
+            return
         }
 
-        self.turnning_off = true;
-        info!("Abort requested, executing abort.");
-        execute_command(&self.config.turn_off_command);
-        std::process::abort();
+        info!("Power off requested, stopping capture");
+        self.exposure_command(ExposureCommand::Stop);
+        log_err("Pause sequence", self.sequencer.process(SequenceMessage::Pause));
+        log_err("Disable storage", self.storage_tx.send(StorageMessage::DisableStore));
+        self.shutdown = Some(Shutdown::new(self.config.shutdown.clone(), Instant::now()));
+        self.set_detail("Shutting down, writing queued images");
+    }
+
+    /// Power off was requested, user commands are not accepted anymore
+    pub fn shutting_down(&self) -> bool {
+        self.shutdown.is_some()
+    }
+
+    /// Camera was closed by the power off sequence
+    pub fn shutdown_finished(&self) -> bool {
+        self.shutdown.as_ref()
+            .is_some_and(|shutdown| shutdown.stage() == ShutdownStage::PoweringOff)
     }
 }
 
@@ -192,6 +385,221 @@ impl CameraController {
         }
     }
 
+    fn run_sequencer(&mut self) {
+        if self.sequence_storage && !self.sequencer.is_busy() {
+            self.sequence_storage = false;
+            log_err("Disable sequence storage", self.storage_tx.send(StorageMessage::DisableStore));
+        }
+
+        if self.state != State::Connected {
+            self.sequencer.exposure_interrupted();
+            return;
+        }
+
+        let cooling_ready = self.connected.as_ref().is_some_and(|cam| cam.cooling_ready());
+        let request = self.sequencer.periodic(self.exposure_active(), cooling_ready);
+
+        if let Some(request) = request {
+            self.start_sequence_frame(request);
+        }
+    }
+
+    fn start_sequence_frame(&mut self, request: FrameRequest) {
+        let step = request.step;
+
+        if request.change_directory {
+            let directory = StorageMessage::SetDirectory(step.directory.clone());
+            log_err("Set sequence directory", self.storage_tx.send(directory));
+            log_err("Enable sequence storage", self.storage_tx.send(StorageMessage::EnableStore));
+            self.sequence_storage = true;
+        }
+
+        self.camera_params.time = step.time;
+        self.camera_params.gain = step.gain;
+        self.camera_params.binning = step.binning;
+        self.camera_params.frame_type = step.frame_type;
+        self.camera_params.loop_enabled = false;
+
+        if let Some(connected) = self.connected.as_mut() {
+            connected.update_camera_params(self.camera_params.clone());
+
+            if let Err(message) = connected.exposure_command(ExposureCommand::Start) {
+                self.sequencer.exposure_interrupted();
+                self.set_detail(&format!("Sequence exposure failed: {}", message));
+            }
+        }
+    }
+
+    fn run_shutdown(&mut self, now: Instant) {
+        let telemetry = self.connected.as_ref().map(|camera| camera.get_properties().telemetry);
+
+        let action = match self.shutdown.as_mut() {
+            None => return,
+            Some(shutdown) => {
+                shutdown.periodic(&self.storage_detail, telemetry.as_ref(), now)
+            },
+        };
+
+        match action {
+            None => {},
+            Some(ShutdownAction::WarmUp(target)) => {
+                self.camera_params.temperature = target;
+
+                if let Some(connected) = self.connected.as_mut() {
+                    connected.update_camera_params(self.camera_params.clone());
+                }
+
+                self.set_detail(&format!("Shutting down, warming up to {:.1} °C", target));
+            },
+            Some(ShutdownAction::PowerOff) => {
+                // Cooler is left at the warm-up target and speed
+                let warm_up = self.temperature_request();
+
+                if let Some(camera) = self.connected.take() {
+                    camera.turn_off(warm_up);
+                }
+
+                self.connected_id = None;
+                self.set_detail("Shutting down, camera closed");
+            },
+        }
+    }
+
+    /// Cooler is ramped at the warm-up speed during the shutdown
+    fn temperature_request(&self) -> TemperatureRequest {
+        let speed = match self.shutdown.is_some() {
+            false => self.config.cooling.ramp_speed,
+            true => self.config.shutdown.warm_up_speed,
+        };
+
+        TemperatureRequest { temperature: self.camera_params.temperature as f32, speed }
+    }
+
+    fn run_focuser(&mut self) {
+        if let Err(message) = self.focuser.periodic() {
+            self.set_detail(&format!("Focuser failed: {}", message));
+        }
+
+        // Image is taken every time, so that a stale image is never measured later
+        let image = self.connected.as_mut().and_then(|camera| camera.take_image());
+
+        if let Some(image) = image {
+            self.autofocus.image_ready(&image);
+        }
+
+        if self.state != State::Connected {
+            self.autofocus.fail("Camera disconnected");
+        }
+
+        let action = self.autofocus.periodic(self.focuser.status(), self.exposure_active());
+
+        match action {
+            None => {},
+            Some(AutofocusAction::MoveFocuser(position)) => {
+                if let Err(message) = self.focuser.move_to(position) {
+                    self.autofocus.fail(&format!("Focuser move failed: {}", message));
+                }
+            },
+            Some(AutofocusAction::StartExposure) => {
+                let result = match self.connected.as_mut() {
+                    Some(connected) => connected.focus_exposure(),
+                    None => Err(ImagerError::disconnected("Camera not connected")),
+                };
+
+                if let Err(message) = result {
+                    self.autofocus.fail(&format!("Autofocus exposure failed: {}", message));
+                }
+            },
+        }
+    }
+
+    fn move_focuser(&mut self, position: i32) -> Result<(), String> {
+        match self.autofocus.is_running() {
+            true => Err(String::from("Autofocus is running")),
+            false => self.focuser.move_to(position),
+        }
+    }
+
+    /// Move the filter wheel and keep the focus when switching filters of different
+    /// optical thickness
+    fn select_filter(&mut self, old_filter: usize) {
+        let connected = match self.connected.as_mut() {
+            Some(connected) => connected,
+            None => return,
+        };
+
+        if let Err(error) = connected.select_filter() {
+            self.set_detail(&format!("Filter not selected: {}", error));
+            return
+        }
+
+        let properties = connected.get_properties();
+        let new_filter = self.camera_params.filter;
+        let shift =
+            filter_shift(&properties.filters, &self.config.filter_offsets, old_filter, new_filter);
+
+        let result = match (shift, self.focuser.status()) {
+            (0, _) | (_, None) => Ok(()),
+            (shift, Some(status)) => self.move_focuser(status.position + shift),
+        };
+
+        if let Err(message) = result {
+            self.set_detail(&format!("Filter focus offset not applied: {}", message));
+        }
+    }
+
+    fn start_autofocus(&mut self) -> Result<(), String> {
+        let sequence = self.sequencer.view().progress.state;
+
+        if matches!(sequence, SequenceState::Running | SequenceState::Paused) {
+            return Err(String::from("Sequence is in progress"))
+        }
+
+        if self.connected.is_none() {
+            return Err(String::from("Camera not connected"))
+        }
+
+        let (status, max_position) = match (self.focuser.status(), self.focuser.properties()) {
+            (Some(status), Some(properties)) => (status, properties.max_position),
+            _ => return Err(String::from("Focuser not connected")),
+        };
+
+        // Autofocus takes over starting of exposures, running exposure is finished first
+        self.camera_params.loop_enabled = false;
+
+        if let Some(connected) = self.connected.as_mut() {
+            connected.update_camera_params(self.camera_params.clone());
+        }
+
+        self.autofocus.start(status.position, max_position)
+    }
+
+    fn stop_autofocus(&mut self) {
+        if self.autofocus.stop() {
+            if let Some(connected) = self.connected.as_mut() {
+                if let Err(message) = connected.exposure_command(ExposureCommand::Stop) {
+                    self.set_detail(&format!("Autofocus exposure abort failed: {}", message));
+                }
+            }
+        }
+    }
+
+    /// Running exposure is finished, no new exposures are started
+    fn stop_capture_low_space(&mut self) {
+        if !self.storage_detail.storage_enabled {
+            return
+        }
+
+        self.camera_params.loop_enabled = false;
+        log_err("Pause sequence", self.sequencer.process(SequenceMessage::Pause));
+
+        if let Some(connected) = self.connected.as_mut() {
+            connected.update_camera_params(self.camera_params.clone());
+        }
+
+        self.set_detail("Capture stopped, storage is running out of space");
+    }
+
     fn connection_state(&self) -> ConnectionState {
         match self.state {
             State::Error => ConnectionState::Connecting,
@@ -207,28 +615,38 @@ impl CameraController {
         self.detail = detail.to_owned();
     }
 
-    fn handle_error_state(&mut self) -> State {
+    fn handle_error_state(&mut self, now: Instant) -> State {
         if let Some(old_device) = self.connected.take() {
             old_device.close();
             self.set_detail("Closing old device");
         }
 
+        self.connected_id = None;
+
         match self.driver.list_devices() {
             Err(_) => {
                 self.set_detail("Could not list devices");
                 State::Error
             }
-            Ok(devices) => match devices.as_slice() {
-                [] => {
-                    self.set_detail("No devices present in list");
-                    State::Error
+            Ok(devices) => {
+                self.devices = devices;
+
+                match (self.selection.choose(&self.devices).cloned(), self.selection.selected()) {
+                    (Some(device), _) => self.connect_and_init(&device, now),
+                    (None, None) => {
+                        self.set_detail("No devices present in list");
+                        State::Error
+                    },
+                    (None, Some(id)) => {
+                        self.set_detail(&format!("Selected camera {} not present", id));
+                        State::Error
+                    },
                 }
-                [device_id, ..] => self.connect_and_init(device_id)
             }
         }
     }
 
-    fn connect_and_init(&mut self, id: &DeviceDescriptor) -> State {
+    fn connect_and_init(&mut self, id: &DeviceDescriptor, now: Instant) -> State {
         match self.driver.connect_device(id) {
             Err(_) => {
                 self.set_detail("Connect device failed");
@@ -238,11 +656,24 @@ impl CameraController {
                 self.set_detail("Device connected, reading basic info");
 
                 match ConnectedCameraController::new(
-                    device, self.config.render_size, self.process_tx.clone(), self.storage_tx.clone()
+                    device,
+                    self.config.clone(),
+                    self.process_tx.clone(),
+                    self.storage_tx.clone(),
+                    now,
                 ) {
-                    Ok(connected) => {
+                    Ok(mut connected) => {
                         self.set_detail("Camera initialized");
+                        // Parameters survive reconnection so that the loop continues
+                        connected.update_camera_params(self.camera_params.clone());
+
+                        if let Err(error) = connected.select_filter() {
+                            self.set_detail(&format!("Filter not selected: {}", error));
+                        }
+
                         self.connected = Some(connected);
+                        self.connected_id = Some(id.id);
+                        self.transient_failures = 0;
                         State::Connected
                     },
                     Err(message) => {
@@ -255,26 +686,502 @@ impl CameraController {
         }
     }
 
-    fn handle_connected_state(&mut self) -> State {
+    fn handle_connected_state(&mut self, now: Instant) -> State {
+        let temperature = self.temperature_request();
+
         if let Some(ref mut controller) = self.connected {
-            match controller.periodic(self.camera_params.temperature) {
+            match controller.periodic(temperature, now) {
                 Ok(_) => {
+                    // Camera made progress, earlier transient failures are forgotten
+                    let idle = controller.exposure_status() == ConnectionState::Disconnected;
+
+                    if controller.image_available() || idle {
+                        self.transient_failures = 0;
+                    }
+
                     State::Connected
                 },
-                Err(message) => {
-                    self.set_detail(&format!("Periodic task failed: {}", message));
-                    self.connected = None;
-                    State::Error
-                }
+                Err(error) => self.handle_camera_error(error),
             }
         } else {
             State::Error
         }
     }
+
+    /// Rejected requests stop the loop, transient failures are tolerated a few times,
+    /// a full reconnect is done for everything else
+    fn handle_camera_error(&mut self, error: ImagerError) -> State {
+        match error.kind {
+            ImagerErrorKind::InvalidParameter => {
+                // Neither the loop nor the sequence may repeat the rejected request
+                self.camera_params.loop_enabled = false;
+                self.sequencer.exposure_interrupted();
+                log_err("Pause sequence", self.sequencer.process(SequenceMessage::Pause));
+
+                if let Some(connected) = self.connected.as_mut() {
+                    connected.update_camera_params(self.camera_params.clone());
+                }
+
+                self.set_detail(&format!("Camera rejected the request: {}", error));
+                State::Connected
+            },
+            ImagerErrorKind::Timeout | ImagerErrorKind::Busy
+                if self.transient_failures < MAX_TRANSIENT_FAILURES =>
+            {
+                // Image of a timed out sequence frame was not taken, it must not be counted
+                self.transient_failures += 1;
+                self.sequencer.exposure_interrupted();
+
+                self.set_detail(&format!(
+                    "Camera call failed: {}, reconnecting after {} failures ({}/{})",
+                    error, MAX_TRANSIENT_FAILURES, self.transient_failures, MAX_TRANSIENT_FAILURES
+                ));
+
+                State::Connected
+            },
+            _ => {
+                if let Some(old_device) = self.connected.take() {
+                    old_device.close();
+                }
+
+                self.set_detail(&format!("Periodic task failed: {}", error));
+                self.connected_id = None;
+                State::Error
+            },
+        }
+    }
+}
+
+/// Consecutive timeouts or busy errors tolerated before the camera is reconnected
+const MAX_TRANSIENT_FAILURES: usize = 3;
+
+fn space_critical(state: &StorageState) -> bool {
+    matches!(state, StorageState::Available(capacity) if capacity.level == SpaceLevel::Critical)
+}
+
+/// Focuser steps between two filters, configured offsets override the filter wheel ones
+fn filter_shift(
+    filters: &[Filter],
+    offsets: &BTreeMap<String, i32>,
+    old_filter: usize,
+    new_filter: usize,
+) -> i32 {
+    let offset = |filter: usize| filters.get(filter)
+        .map(|filter| offsets.get(&filter.name).copied().unwrap_or(filter.offset))
+        .unwrap_or(0);
+
+    offset(new_filter) - offset(old_filter)
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 enum State {
     Error,
     Connected
-}
\ No newline at end of file
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc::{channel, Receiver}, time::Duration};
+
+    use ccdi_imager_demo::{DemoImagerDriver, FaultScript};
+    use ccdi_imager_interface::ExposureArea;
+
+    use ccdi_common::{ExposurePlan, PlanStep};
+
+    use crate::CoolingConfig;
+
+    use super::*;
+
+    struct Harness {
+        controller: CameraController,
+        storage_rx: Receiver<StorageMessage>,
+        _process_rx: Receiver<ProcessMessage>,
+        /// Time passed to the controller, advanced only by `wait`
+        now: Instant,
+    }
+
+    impl Harness {
+        fn new(script: FaultScript, download_timeout: f64) -> Self {
+            Self::with_config(script, ServiceConfig { download_timeout, ..Default::default() })
+        }
+
+        fn with_config(script: FaultScript, config: ServiceConfig) -> Self {
+            let (process_tx, _process_rx) = channel();
+            let (storage_tx, storage_rx) = channel();
+            let config = Arc::new(config);
+            let driver = Box::new(DemoImagerDriver::new().with_faults(script));
+            // Files of the user configuration must not affect the tests
+            let mut controller =
+                CameraController::new(driver, None, process_tx, storage_tx, config, None, None);
+
+            let roi = ExposureArea { x: 0, y: 0, width: 64, height: 64 };
+            controller.update_camera_params(CameraParamMessage::SetRoi(Some(roi)));
+            controller.update_camera_params(CameraParamMessage::SetTime(0.0));
+            controller.update_camera_params(CameraParamMessage::EnableLoop(true));
+
+            Self { controller, storage_rx, _process_rx, now: Instant::now() }
+        }
+
+        fn run(&mut self, count: usize) {
+            (0..count).for_each(|_| { self.controller.periodic(self.now); });
+        }
+
+        fn wait(&mut self, seconds: f64) {
+            self.now += Duration::from_secs_f64(seconds);
+        }
+
+        /// Run until the camera state changes, returns the number of periodic calls
+        fn run_until(&mut self, state: State) -> usize {
+            (1..=20)
+                .find(|_| {
+                    self.controller.periodic(self.now);
+                    self.controller.state == state
+                })
+                .expect("State not reached")
+        }
+
+        fn images(&self) -> usize {
+            self.storage_rx.try_iter()
+                .filter(|message| matches!(message, StorageMessage::ProcessImage(_)))
+                .count()
+        }
+
+        fn detail(&self) -> &str {
+            &self.controller.detail
+        }
+    }
+
+    fn script() -> FaultScript {
+        Default::default()
+    }
+
+    #[test]
+    fn connects_after_list_and_connect_failures() {
+        let mut harness = Harness::new(
+            FaultScript { list_devices_failures: 2, connect_failures: 1, ..script() }, 60.0
+        );
+
+        harness.run(2);
+        assert_eq!(harness.controller.state, State::Error);
+        assert_eq!(harness.detail(), "Could not list devices");
+        harness.run(1);
+        assert_eq!(harness.controller.state, State::Error);
+        assert_eq!(harness.detail(), "Connect device failed");
+        harness.run(1);
+        assert_eq!(harness.controller.state, State::Connected);
+        harness.run(4);
+        assert!(harness.images() > 0);
+    }
+
+    #[test]
+    fn loop_continues_after_disconnect_during_download() {
+        let faults = FaultScript { disconnect_downloads: vec![2], ..script() };
+        let mut harness = Harness::new(faults, 60.0);
+
+        harness.run_until(State::Connected);
+        harness.run_until(State::Error);
+        assert!(harness.detail().contains("Camera disconnected during download"));
+        assert_eq!(harness.images(), 1);
+
+        harness.run_until(State::Connected);
+        harness.run(4);
+        assert_eq!(harness.controller.state, State::Connected);
+        assert!(harness.images() > 0);
+    }
+
+    #[test]
+    fn truncated_image_is_not_processed() {
+        let faults = FaultScript { truncated_downloads: vec![1], ..script() };
+        let mut harness = Harness::new(faults, 60.0);
+
+        harness.run_until(State::Connected);
+        harness.run_until(State::Error);
+        assert!(harness.detail().contains("Downloaded 2048 pixels, expected 4096"));
+        assert_eq!(harness.images(), 0);
+
+        harness.run_until(State::Connected);
+        harness.run(4);
+        assert!(harness.images() > 0);
+    }
+
+    #[test]
+    fn stalled_exposure_is_retried() {
+        let faults = FaultScript { stalled_exposures: vec![1], ..script() };
+        let mut harness = Harness::new(faults, 0.05);
+
+        harness.run_until(State::Connected);
+        harness.run(3);
+        assert!(harness.controller.exposure_active());
+
+        harness.wait(0.1);
+        harness.run(1);
+        assert_eq!(harness.controller.state, State::Connected);
+        assert!(harness.detail().contains("download timeout, reconnecting after 3 failures (1/3)"));
+        assert_eq!(harness.images(), 0);
+
+        harness.run(4);
+        assert!(harness.images() > 0);
+        assert_eq!(harness.controller.transient_failures, 0);
+    }
+
+    #[test]
+    fn stop_reports_whether_exposure_was_aborted() {
+        let mut harness = Harness::new(script(), 60.0);
+        harness.controller.update_camera_params(CameraParamMessage::SetTime(10.0));
+        harness.run_until(State::Connected);
+        harness.run(1);
+        assert!(harness.controller.exposure_active());
+
+        harness.controller.exposure_command(ExposureCommand::Stop);
+        assert_eq!(harness.detail(), "Exposure aborted");
+        assert!(!harness.controller.exposure_active());
+
+        harness.controller.exposure_command(ExposureCommand::Stop);
+        assert_eq!(harness.detail(), "No exposure running");
+    }
+
+    #[test]
+    fn timed_out_sequence_frame_is_not_counted() {
+        let faults = FaultScript { stalled_exposures: vec![1], ..script() };
+        let mut harness = Harness::new(faults, 0.05);
+        harness.controller.update_camera_params(CameraParamMessage::EnableLoop(false));
+        harness.run_until(State::Connected);
+
+        let step = PlanStep { count: 2, time: 0.0, ..Default::default() };
+        let plan = ExposurePlan { steps: vec![step] };
+        harness.controller.sequence_message(SequenceMessage::SetPlan(plan));
+        harness.controller.sequence_message(SequenceMessage::Start);
+        harness.run(2);
+        assert!(harness.controller.exposure_active());
+
+        harness.wait(0.1);
+        harness.run(3);
+        let progress = harness.controller.sequencer.view().progress;
+        assert_eq!(progress.state, SequenceState::Paused);
+        assert_eq!(progress.frames_done, 0);
+        assert_eq!(harness.images(), 0);
+    }
+
+    #[test]
+    fn repeated_timeouts_reconnect() {
+        let faults = FaultScript { stalled_exposures: vec![1, 2, 3, 4], ..script() };
+        let mut harness = Harness::new(faults, 0.05);
+
+        harness.run_until(State::Connected);
+        harness.run(1);
+
+        for _ in 0..MAX_TRANSIENT_FAILURES {
+            harness.wait(0.1);
+            harness.run(2);
+            assert_eq!(harness.controller.state, State::Connected);
+        }
+
+        harness.wait(0.1);
+        harness.run(1);
+        assert_eq!(harness.controller.state, State::Error);
+        assert!(harness.detail().contains("download timeout"));
+        assert_eq!(harness.images(), 0);
+
+        harness.run_until(State::Connected);
+        harness.run(4);
+        assert!(harness.images() > 0);
+    }
+
+    #[test]
+    fn rejected_request_stops_loop() {
+        let mut harness = Harness::new(script(), 60.0);
+        harness.run_until(State::Connected);
+
+        let step = PlanStep { count: 5, time: 0.0, ..Default::default() };
+        let plan = ExposurePlan { steps: vec![step] };
+        harness.controller.sequence_message(SequenceMessage::SetPlan(plan));
+        harness.controller.sequence_message(SequenceMessage::Start);
+        harness.run(1);
+
+        let state = harness.controller
+            .handle_camera_error(ImagerError::invalid_parameter("Invalid binning"));
+
+        assert_eq!(state, State::Connected);
+        assert!(!harness.controller.camera_params.loop_enabled);
+        // Exposure running at the failure finishes, no other is started
+        harness.run(3);
+        assert_eq!(harness.controller.sequencer.view().progress.state, SequenceState::Paused);
+        assert!(!harness.controller.exposure_active());
+        assert!(harness.controller.connected.is_some());
+
+        let state = harness.controller
+            .handle_camera_error(ImagerError::disconnected("Camera was unplugged"));
+
+        assert_eq!(state, State::Error);
+        assert!(harness.controller.connected.is_none());
+    }
+
+    #[test]
+    fn selected_filter_is_written_to_metadata() {
+        let mut harness = Harness::new(script(), 60.0);
+        harness.run_until(State::Connected);
+        harness.controller.update_camera_params(CameraParamMessage::SetFilter(4));
+        harness.images();
+        harness.run(3);
+
+        let filters = harness.storage_rx.try_iter()
+            .filter_map(|message| match message {
+                StorageMessage::ProcessImage(image) => Some(image.metadata.filter.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert!(!filters.is_empty());
+        assert!(filters.iter().all(|filter| filter.as_deref() == Some("Ha")));
+    }
+
+    #[test]
+    fn configured_offsets_override_filter_wheel() {
+        let filter = |name: &str, offset| Filter { name: name.to_string(), offset };
+        let filters = [filter("L", 0), filter("R", 10), filter("Ha", 40)];
+        let offsets = BTreeMap::from([(String::from("Ha"), 120)]);
+
+        assert_eq!(filter_shift(&filters, &Default::default(), 0, 2), 40);
+        assert_eq!(filter_shift(&filters, &offsets, 0, 2), 120);
+        assert_eq!(filter_shift(&filters, &offsets, 2, 1), -110);
+        assert_eq!(filter_shift(&filters, &offsets, 1, 5), -10);
+    }
+
+    #[test]
+    fn telemetry_survives_reconnection() {
+        let faults = FaultScript { disconnect_downloads: vec![1], ..script() };
+        let mut harness = Harness::new(faults, 60.0);
+
+        harness.run_until(State::Connected);
+        let samples = harness.controller.telemetry();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].telemetry.environment_temperature, Some(20.0));
+
+        harness.run_until(State::Error);
+        harness.run_until(State::Connected);
+        harness.run(2);
+        assert_eq!(harness.controller.telemetry().len(), 2);
+    }
+
+    #[test]
+    fn storage_is_disabled_when_sequence_ends() {
+        let mut harness = Harness::new(script(), 60.0);
+        harness.run_until(State::Connected);
+        harness.controller.update_camera_params(CameraParamMessage::EnableLoop(false));
+        harness.run(2);
+        harness.storage_rx.try_iter().for_each(drop);
+
+        let step = PlanStep {
+            count: 2, time: 0.0, directory: String::from("a"), ..Default::default()
+        };
+        let plan = ExposurePlan { steps: vec![step] };
+        harness.controller.sequence_message(SequenceMessage::SetPlan(plan));
+        harness.controller.sequence_message(SequenceMessage::Start);
+        harness.run(10);
+
+        let messages: Vec<StorageMessage> = harness.storage_rx.try_iter().collect();
+        let images = messages.iter()
+            .filter(|message| matches!(message, StorageMessage::ProcessImage(_)))
+            .count();
+
+        assert_eq!(harness.controller.sequencer.view().progress.state, SequenceState::Finished);
+        assert_eq!(images, 2);
+        assert!(matches!(messages.last(), Some(StorageMessage::DisableStore)));
+    }
+
+    #[test]
+    fn loop_waits_for_stable_cooling() {
+        let cooling = CoolingConfig {
+            stable_time: 0.2, wait_for_stable: true, ..Default::default()
+        };
+        let config = ServiceConfig { cooling, ..Default::default() };
+        let mut harness = Harness::with_config(script(), config);
+
+        // Demo chip stays at ambient below the default target with the cooler idle
+        harness.run_until(State::Connected);
+        harness.run(3);
+        assert_eq!(harness.controller.get_view().status.cooling, ConnectionState::Connecting);
+        assert_eq!(harness.images(), 0);
+
+        harness.wait(0.3);
+        harness.run(4);
+        assert_eq!(harness.controller.get_view().status.cooling, ConnectionState::Established);
+        assert!(harness.images() > 0);
+
+        harness.controller.update_camera_params(CameraParamMessage::SetTemp(-10.0));
+        harness.run(2);
+        assert_eq!(harness.controller.get_view().status.cooling, ConnectionState::Connecting);
+    }
+
+    #[test]
+    fn power_off_writes_queue_and_warms_up() {
+        let mut harness = Harness::new(script(), 60.0);
+        let storage = |storage_enabled, queue_depth| StorageDetail {
+            storage_enabled, queue_depth, ..Default::default()
+        };
+        let stage = |harness: &Harness| harness.controller.get_view().shutdown
+            .map(|progress| progress.stage);
+
+        harness.run_until(State::Connected);
+        harness.run(2);
+        harness.controller.update_storage_detail(storage(true, 2));
+        harness.controller.turn_off();
+
+        assert!(!harness.controller.camera_params.loop_enabled);
+        assert!(harness.storage_rx.try_iter()
+            .any(|message| matches!(message, StorageMessage::DisableStore)));
+
+        harness.run(3);
+        assert!(!harness.controller.exposure_active());
+        assert_eq!(stage(&harness), Some(ShutdownStage::Flushing));
+
+        harness.controller.update_storage_detail(storage(false, 0));
+        harness.run(1);
+        assert_eq!(stage(&harness), Some(ShutdownStage::WarmingUp));
+        assert_eq!(harness.controller.camera_params.temperature, 20.0);
+
+        // Demo chip is at ambient already
+        harness.run(1);
+        assert_eq!(stage(&harness), Some(ShutdownStage::PoweringOff));
+        assert!(harness.controller.shutdown_finished());
+        assert!(harness.controller.connected.is_none());
+
+        harness.run(3);
+        assert!(harness.controller.connected.is_none());
+    }
+
+    #[test]
+    fn selected_camera_is_connected() {
+        let mut harness = Harness::new(script(), 60.0);
+
+        harness.run_until(State::Connected);
+        assert_eq!(harness.controller.connected_id, Some(0));
+        assert_eq!(harness.controller.get_view().cameras.devices.len(), 2);
+
+        harness.controller.select_camera(1);
+        assert_eq!(harness.controller.state, State::Error);
+        harness.run_until(State::Connected);
+        assert_eq!(harness.controller.connected_id, Some(1));
+
+        harness.controller.select_camera(5);
+        harness.run(3);
+        assert_eq!(harness.controller.state, State::Error);
+        assert_eq!(harness.detail(), "Selected camera 5 not present");
+    }
+
+    #[test]
+    fn cameras_are_listed_on_request_while_connected() {
+        let mut harness = Harness::new(script(), 60.0);
+        harness.run_until(State::Connected);
+        harness.controller.devices.clear();
+
+        harness.wait(60.0);
+        harness.run(3);
+        assert!(harness.controller.get_view().cameras.devices.is_empty());
+
+        harness.controller.refresh_cameras();
+        assert_eq!(harness.controller.get_view().cameras.devices.len(), 2);
+    }
+}
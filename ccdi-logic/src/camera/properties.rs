@@ -1,42 +1,55 @@
-use std::{sync::Arc, time::Instant};
+use std::{sync::Arc, time::{Instant, SystemTime}};
 
-use ccdi_imager_interface::{ImagerDevice, ImagerProperties};
+use ccdi_common::{TelemetrySample, TELEMETRY_PERIOD};
+use ccdi_imager_interface::{ImagerDevice, ImagerProperties, ImagerError};
 
 // ============================================ PUBLIC =============================================
 
 pub struct PropertiesController {
     properties: Arc<ImagerProperties>,
-    last_properties_read: Instant
+    last_properties_read: Instant,
+    last_sample: Option<Instant>,
+    /// Telemetry samples not taken yet
+    samples: Vec<TelemetrySample>,
 }
 
 impl PropertiesController {
-    pub fn new(device: &mut dyn ImagerDevice) -> Result<Self, String> {
+    pub fn new(device: &mut dyn ImagerDevice, now: Instant) -> Result<Self, ImagerError> {
         match device.read_properties() {
-            Err(_) => Err(String::from("Reading camera properties failed")),
-            Ok(properties) => Ok(
-                Self {
+            Err(error) => Err(ImagerError::new(
+                error.kind, format!("Reading camera properties failed: {}", error)
+            )),
+            Ok(properties) => {
+                let mut controller = Self {
                     properties: Arc::new(properties),
-                    last_properties_read: Instant::now(),
-                }
-            )
+                    last_properties_read: now,
+                    last_sample: None,
+                    samples: vec![],
+                };
+
+                controller.sample_telemetry(now);
+                Ok(controller)
+            }
         }
     }
 
     pub fn read_properties(
         &mut self,
-        device: &mut dyn ImagerDevice
-    ) -> Result<(), ()> {
-        match self.should_read_properties() {
+        device: &mut dyn ImagerDevice,
+        now: Instant,
+    ) -> Result<(), ImagerError> {
+        match self.should_read_properties(now) {
             false => Ok(()),
             true => match device.read_properties() {
                 Ok(properties) => {
                     self.properties = Arc::new(properties);
-                    self.last_properties_read = Instant::now();
+                    self.last_properties_read = now;
+                    self.sample_telemetry(now);
                     Ok(())
                 },
-                Err(_) => {
-                    Err(())
-                }
+                Err(error) => Err(ImagerError::new(
+                    error.kind, format!("Periodic read properties failed: {}", error)
+                )),
             }
         }
     }
@@ -44,6 +57,11 @@ impl PropertiesController {
     pub fn get_properties(&self) -> Arc<ImagerProperties> {
         self.properties.clone()
     }
+
+    /// Telemetry sampled since the last call, oldest first
+    pub fn take_samples(&mut self) -> Vec<TelemetrySample> {
+        std::mem::take(&mut self.samples)
+    }
 }
 
 // =========================================== PRIVATE =============================================
@@ -51,7 +69,20 @@ impl PropertiesController {
 const PROPERTIES_READ_INTERVAL: f64 = 2.0; // Seconds
 
 impl PropertiesController {
-    fn should_read_properties(&self) -> bool {
-        self.last_properties_read.elapsed().as_secs_f64() >= PROPERTIES_READ_INTERVAL
+    fn should_read_properties(&self, now: Instant) -> bool {
+        now.duration_since(self.last_properties_read).as_secs_f64() >= PROPERTIES_READ_INTERVAL
+    }
+
+    /// Properties are read more often than the charts need, only every
+    /// `TELEMETRY_PERIOD` seconds a sample is taken
+    fn sample_telemetry(&mut self, now: Instant) {
+        let due = self.last_sample
+            .is_none_or(|sampled| now.duration_since(sampled).as_secs_f64() >= TELEMETRY_PERIOD);
+
+        if due {
+            self.last_sample = Some(now);
+            let telemetry = self.properties.telemetry;
+            self.samples.push(TelemetrySample { time: SystemTime::now(), telemetry });
+        }
     }
 }
\ No newline at end of file
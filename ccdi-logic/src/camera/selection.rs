@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+
+use ccdi_common::{save_text_file, read_text_file, to_string, log_err};
+use ccdi_imager_interface::DeviceDescriptor;
+use log::info;
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+/// Camera chosen by the user, stored in a file so that the same camera is connected
+/// after a restart or a USB drop even when other cameras are present
+pub struct CameraSelection {
+    path: Option<PathBuf>,
+    selected: Option<i32>,
+}
+
+impl CameraSelection {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let selected = path.as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| log_err("Load camera selection", load_selection(path)))
+            .map(|stored| stored.id);
+
+        Self { path, selected }
+    }
+
+    pub fn selected(&self) -> Option<i32> {
+        self.selected
+    }
+
+    pub fn select(&mut self, id: i32) {
+        info!("Camera {} selected", id);
+        self.selected = Some(id);
+
+        if let Some(path) = self.path.as_ref() {
+            log_err("Save camera selection", save_selection(&StoredSelection { id }, path));
+        }
+    }
+
+    /// Device to be connected, the first one without a selection, selected camera
+    /// is waited for when missing so that another camera is never used instead
+    pub fn choose<'a>(&self, devices: &'a [DeviceDescriptor]) -> Option<&'a DeviceDescriptor> {
+        match self.selected {
+            None => devices.first(),
+            Some(id) => devices.iter().find(|device| device.id == id),
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct StoredSelection {
+    id: i32,
+}
+
+fn load_selection(path: &Path) -> Result<StoredSelection, String> {
+    serde_yaml::from_str(&read_text_file(path)?).map_err(to_string)
+}
+
+fn save_selection(selection: &StoredSelection, path: &Path) -> Result<(), String> {
+    save_text_file(&serde_yaml::to_string(selection).map_err(to_string)?, path)
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::TestDir;
+
+    use super::*;
+
+    fn devices(ids: &[i32]) -> Vec<DeviceDescriptor> {
+        ids.iter()
+            .map(|&id| DeviceDescriptor { id, name: format!("Camera #{}", id) })
+            .collect()
+    }
+
+    #[test]
+    fn selected_camera_is_never_replaced() {
+        let mut selection = CameraSelection::new(None);
+        assert_eq!(selection.choose(&devices(&[7, 3])).map(|device| device.id), Some(7));
+        assert_eq!(selection.choose(&[]), None);
+
+        selection.select(3);
+        assert_eq!(selection.choose(&devices(&[7, 3])).map(|device| device.id), Some(3));
+        assert_eq!(selection.choose(&devices(&[7])), None);
+    }
+
+    #[test]
+    fn selection_is_persisted() {
+        let dir = TestDir::new("camera-selection");
+        let path = dir.join("camera.yaml");
+
+        CameraSelection::new(Some(path.clone())).select(1234);
+        assert_eq!(CameraSelection::new(Some(path)).selected(), Some(1234));
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::Instant;
+
+use ccdi_common::{ShutdownStage, ShutdownProgress, StorageDetail};
+use ccdi_imager_interface::Telemetry;
+use log::{info, warn};
+
+use crate::ShutdownConfig;
+
+// ============================================ PUBLIC =============================================
+
+/// Power off sequence, queued images are written and the chip is warmed up towards
+/// ambient before the camera is closed
+pub struct Shutdown {
+    config: ShutdownConfig,
+    stage: ShutdownStage,
+    stage_started: Instant,
+    queue_depth: usize,
+    chip_temperature: Option<f32>,
+    target_temperature: Option<f64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShutdownAction {
+    /// Ramp the cooler to the temperature
+    WarmUp(f64),
+    /// Turn the cooler off and close the camera
+    PowerOff,
+}
+
+impl Shutdown {
+    pub fn new(config: ShutdownConfig, now: Instant) -> Self {
+        Self {
+            config,
+            stage: ShutdownStage::Flushing,
+            stage_started: now,
+            queue_depth: 0,
+            chip_temperature: None,
+            target_temperature: None,
+        }
+    }
+
+    /// Advance the sequence, telemetry is missing when the camera is not connected
+    pub fn periodic(
+        &mut self,
+        storage: &StorageDetail,
+        telemetry: Option<&Telemetry>,
+        now: Instant,
+    ) -> Option<ShutdownAction> {
+        self.queue_depth = storage.queue_depth;
+        self.chip_temperature = telemetry.map(|telemetry| telemetry.chip_temperature);
+        let elapsed = now.duration_since(self.stage_started).as_secs_f64();
+
+        match self.stage {
+            ShutdownStage::Flushing => {
+                let flushed = !storage.storage_enabled && storage.queue_depth == 0;
+
+                if !flushed && elapsed < self.config.flush_timeout {
+                    return None
+                }
+
+                if !flushed {
+                    warn!("{} images not written before the power off", storage.queue_depth);
+                }
+
+                match telemetry {
+                    None => self.power_off(),
+                    Some(telemetry) => {
+                        let ambient = telemetry.environment_temperature
+                            .map(f64::from)
+                            .unwrap_or(self.config.default_ambient);
+
+                        info!("Warming up the camera to {:.1} °C", ambient);
+                        self.stage = ShutdownStage::WarmingUp;
+                        self.stage_started = now;
+                        self.target_temperature = Some(ambient);
+                        Some(ShutdownAction::WarmUp(ambient))
+                    }
+                }
+            },
+            ShutdownStage::WarmingUp => {
+                let target = self.target_temperature.unwrap_or(self.config.default_ambient);
+                let warm = self.chip_temperature
+                    .is_none_or(|chip| chip as f64 >= target - self.config.ambient_margin);
+
+                if !warm && elapsed < self.config.warm_up_timeout {
+                    return None
+                }
+
+                if !warm {
+                    warn!("Camera not warmed up before the warm-up timeout");
+                }
+
+                self.power_off()
+            },
+            ShutdownStage::PoweringOff => None,
+        }
+    }
+
+    pub fn stage(&self) -> ShutdownStage {
+        self.stage
+    }
+
+    pub fn progress(&self) -> ShutdownProgress {
+        ShutdownProgress {
+            stage: self.stage,
+            queue_depth: self.queue_depth,
+            chip_temperature: self.chip_temperature,
+            target_temperature: self.target_temperature,
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+impl Shutdown {
+    fn power_off(&mut self) -> Option<ShutdownAction> {
+        self.stage = ShutdownStage::PoweringOff;
+        Some(ShutdownAction::PowerOff)
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn storage(storage_enabled: bool, queue_depth: usize) -> StorageDetail {
+        StorageDetail { storage_enabled, queue_depth, ..Default::default() }
+    }
+
+    fn telemetry(chip_temperature: f32) -> Telemetry {
+        Telemetry {
+            chip_temperature,
+            environment_temperature: Some(18.0),
+            ..Default::default()
+        }
+    }
+
+    fn at(start: Instant, seconds: u64) -> Instant {
+        start + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn queue_is_written_before_warm_up() {
+        let start = Instant::now();
+        let mut shutdown = Shutdown::new(Default::default(), start);
+        let cold = telemetry(-20.0);
+
+        assert_eq!(shutdown.periodic(&storage(true, 2), Some(&cold), start), None);
+        assert_eq!(shutdown.periodic(&storage(false, 1), Some(&cold), at(start, 5)), None);
+        assert_eq!(shutdown.progress().queue_depth, 1);
+
+        let action = shutdown.periodic(&storage(false, 0), Some(&cold), at(start, 6));
+        assert_eq!(action, Some(ShutdownAction::WarmUp(18.0)));
+        assert_eq!(shutdown.stage(), ShutdownStage::WarmingUp);
+
+        let warm = telemetry(15.5);
+        assert_eq!(shutdown.periodic(&storage(false, 0), Some(&cold), at(start, 60)), None);
+        let action = shutdown.periodic(&storage(false, 0), Some(&warm), at(start, 900));
+        assert_eq!(action, Some(ShutdownAction::PowerOff));
+        assert_eq!(shutdown.stage(), ShutdownStage::PoweringOff);
+        assert_eq!(shutdown.periodic(&storage(false, 0), Some(&warm), at(start, 901)), None);
+    }
+
+    #[test]
+    fn timeouts_do_not_block_power_off() {
+        let start = Instant::now();
+        let config = ShutdownConfig {
+            flush_timeout: 10.0, warm_up_timeout: 60.0, ..Default::default()
+        };
+        let mut shutdown = Shutdown::new(config, start);
+        let cold = telemetry(-20.0);
+
+        assert_eq!(shutdown.periodic(&storage(true, 3), Some(&cold), at(start, 9)), None);
+        let action = shutdown.periodic(&storage(true, 3), Some(&cold), at(start, 10));
+        assert_eq!(action, Some(ShutdownAction::WarmUp(18.0)));
+
+        assert_eq!(shutdown.periodic(&storage(true, 3), Some(&cold), at(start, 69)), None);
+        let action = shutdown.periodic(&storage(true, 3), Some(&cold), at(start, 70));
+        assert_eq!(action, Some(ShutdownAction::PowerOff));
+    }
+
+    #[test]
+    fn disconnected_camera_is_not_warmed_up() {
+        let start = Instant::now();
+        let mut shutdown = Shutdown::new(Default::default(), start);
+
+        let action = shutdown.periodic(&storage(false, 0), None, start);
+        assert_eq!(action, Some(ShutdownAction::PowerOff));
+        assert_eq!(shutdown.progress().target_temperature, None);
+    }
+}
@@ -1,10 +1,17 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, collections::BTreeMap};
 use nanocv::ImgSize;
 use serde_derive::{Serialize, Deserialize};
 
-use ccdi_common::{to_string, GuiConfig, save_text_file, read_text_file};
+use ccdi_common::{
+    to_string, GuiConfig, save_text_file, read_text_file, OpticsConfig, SiteConfig,
+    XisfCompression
+};
+use ccdi_imager_demo::FaultScript;
+use ccdi_imager_interface::BayerPattern;
 use directories::ProjectDirs;
 
+use crate::storage::DEFAULT_TEMPLATE;
+
 // ============================================ PUBLIC =============================================
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -14,6 +21,52 @@ pub struct ServiceConfig {
     pub render_size: ImgSize,
     pub gui: GuiConfig,
     pub io: IoConfig,
+    /// Template of saved file names relative to the storage directory
+    #[serde(default = "default_file_name_template")]
+    pub file_name_template: String,
+    #[serde(default)]
+    pub optics: OpticsConfig,
+    /// Observing site, not written to images when missing
+    #[serde(default)]
+    pub site: Option<SiteConfig>,
+    /// Format of stored image files
+    #[serde(default)]
+    pub file_format: FileFormat,
+    /// Default compression of the image data block, used by XISF files only,
+    /// the compression is changed for the running session from the GUI
+    #[serde(default)]
+    pub xisf_compression: XisfCompression,
+    /// Maximum number of images waiting to be written to the storage
+    #[serde(default = "default_write_queue_size")]
+    pub write_queue_size: usize,
+    /// Directory images are written to when writing to the storage fails
+    #[serde(default)]
+    pub fallback_storage: Option<String>,
+    #[serde(default)]
+    pub low_space: LowSpaceConfig,
+    /// Overrides the color filter pattern reported by the camera
+    #[serde(default)]
+    pub bayer_pattern: Option<BayerPattern>,
+    #[serde(default)]
+    pub autofocus: AutofocusConfig,
+    /// Focuser offsets in steps by filter name, the focuser is moved by the difference
+    /// when the filter changes, filters not listed use the offset of the filter wheel
+    #[serde(default)]
+    pub filter_offsets: BTreeMap<String, i32>,
+    /// Seconds to wait for the image after the exposure end, the camera is reconnected
+    /// when the image is not ready in time
+    #[serde(default = "default_download_timeout")]
+    pub download_timeout: f64,
+    #[serde(default)]
+    pub cooling: CoolingConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    /// Failures injected into the demo camera, ignored with a real camera
+    #[serde(default)]
+    pub demo_faults: Option<FaultScript>,
+    /// Seed of the demo camera noise, equal seeds give equal images
+    #[serde(default)]
+    pub demo_seed: Option<u64>,
 }
 
 impl Default for ServiceConfig {
@@ -24,10 +77,129 @@ impl Default for ServiceConfig {
             gui: Default::default(),
             io: Default::default(),
             turn_off_command: String::new(),
+            file_name_template: default_file_name_template(),
+            optics: Default::default(),
+            site: None,
+            file_format: Default::default(),
+            xisf_compression: Default::default(),
+            write_queue_size: default_write_queue_size(),
+            fallback_storage: None,
+            low_space: Default::default(),
+            bayer_pattern: None,
+            autofocus: Default::default(),
+            filter_offsets: Default::default(),
+            download_timeout: default_download_timeout(),
+            cooling: Default::default(),
+            shutdown: Default::default(),
+            demo_faults: None,
+            demo_seed: None,
+        }
+    }
+}
+
+/// Free space thresholds of the storage in gigabytes
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LowSpaceConfig {
+    /// User is warned in the GUI below this value
+    pub warning_gigabytes: f64,
+    /// Capture is switched to the secondary storage or stopped below this value
+    pub critical_gigabytes: f64,
+    /// Storage used when the primary one runs out of space, e.g. a USB stick
+    pub secondary_storage: Option<String>,
+}
+
+impl Default for LowSpaceConfig {
+    fn default() -> Self {
+        Self {
+            warning_gigabytes: 5.0,
+            critical_gigabytes: 1.0,
+            secondary_storage: None,
         }
     }
 }
 
+/// Focuser positions measured by the autofocus, centered at the starting position
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AutofocusConfig {
+    /// Distance of measured positions in focuser steps
+    pub step_size: i32,
+    /// Number of measured positions
+    pub points: usize,
+}
+
+impl Default for AutofocusConfig {
+    fn default() -> Self {
+        Self {
+            step_size: 300,
+            points: 9,
+        }
+    }
+}
+
+/// Cooler ramp and the condition the chip temperature is considered stable at
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CoolingConfig {
+    /// Speed the cooler moves towards the target in degrees celsius per minute
+    pub ramp_speed: f32,
+    /// Largest difference of the chip temperature from the target in degrees celsius
+    pub tolerance: f64,
+    /// Seconds the chip temperature has to stay within the tolerance
+    pub stable_time: f64,
+    /// Loop and sequence exposures are not started until the temperature is stable
+    pub wait_for_stable: bool,
+    /// Seconds after which a chip below the target counts as stable when the camera
+    /// does not report the cooler power, a target above ambient is never reached
+    #[serde(default = "default_cooling_timeout")]
+    pub timeout: f64,
+}
+
+impl Default for CoolingConfig {
+    fn default() -> Self {
+        Self {
+            ramp_speed: 3.0,
+            tolerance: 0.5,
+            stable_time: 60.0,
+            wait_for_stable: false,
+            timeout: default_cooling_timeout(),
+        }
+    }
+}
+
+/// Power off sequence run before `turn_off_command`, the camera is warmed up slowly
+/// so that the chip is not exposed to a thermal shock
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ShutdownConfig {
+    /// Warm-up speed in degrees celsius per minute
+    pub warm_up_speed: f32,
+    /// Warm-up is finished once the chip is less than this many degrees below ambient
+    pub ambient_margin: f64,
+    /// Ambient temperature used when the camera does not measure it
+    pub default_ambient: f64,
+    /// Seconds to wait for queued images to be written
+    pub flush_timeout: f64,
+    /// Seconds to wait for the warm-up, the power is turned off anyway after that
+    pub warm_up_timeout: f64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            warm_up_speed: 2.0,
+            ambient_margin: 3.0,
+            default_ambient: 15.0,
+            flush_timeout: 120.0,
+            warm_up_timeout: 1800.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum FileFormat {
+    #[default]
+    Fits,
+    Xisf,
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct IoConfig {
     pub trigger_input: String,
@@ -67,8 +239,39 @@ pub fn create_default_config_file() -> Result<String, String> {
     }
 }
 
+/// File the sequencer keeps its plan and progress in
+pub fn sequence_file_path() -> Result<PathBuf, String> {
+    create_file_path("sequence.yaml")
+}
+
+/// Camera selected by the user when several cameras are present
+pub fn camera_file_path() -> Result<PathBuf, String> {
+    create_file_path("camera.yaml")
+}
+
+/// Append-only record of saved and failed images
+pub fn journal_file_path() -> Result<PathBuf, String> {
+    create_file_path("journal.log")
+}
+
 // =========================================== PRIVATE =============================================
 
+fn default_write_queue_size() -> usize {
+    4
+}
+
+fn default_download_timeout() -> f64 {
+    60.0
+}
+
+fn default_cooling_timeout() -> f64 {
+    600.0
+}
+
+fn default_file_name_template() -> String {
+    String::from(DEFAULT_TEMPLATE)
+}
+
 fn path_as_string(path: &PathBuf) -> String {
     path.to_string_lossy().to_string()
 }
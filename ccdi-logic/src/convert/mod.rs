@@ -1,14 +1,20 @@
 use std::sync::Arc;
 
-use ccdi_common::{ClientMessage, ProcessMessage, ConvertRawImage, debayer_scale_fast};
+use ccdi_common::{
+    ClientMessage, ProcessMessage, ConvertRawImage, DemosaicQuality, DemosaicMethod, PreviewImage,
+    debayer_scale_fast, demosaic_scale, mono_scale
+};
 use log::debug;
 
+use crate::stars::measure_focus;
+
 // ============================================ PUBLIC =============================================
 
 pub fn handle_process_message(message: ProcessMessage) -> Vec<ClientMessage> {
     match message {
         ProcessMessage::ConvertRawImage(message) => vec![
-            convert_raw_image(message)
+            ClientMessage::FocusMetrics(measure_focus(&message.image)),
+            convert_raw_image(message),
         ],
     }
 }
@@ -21,6 +27,26 @@ fn convert_raw_image(message: ConvertRawImage) -> ClientMessage {
         message.image.params.area.width, message.image.params.area.height,
         message.size.x, message.size.y
     );
-    let rgb_image = Arc::new(debayer_scale_fast(&message.image, message.size, message.rendering));
-    ClientMessage::RgbImage(rgb_image)
+    ClientMessage::Image(Arc::new(render_image(&message)))
+}
+
+/// Images without a color pattern (mono chips or binned images) are rendered as luminance
+fn render_image(message: &ConvertRawImage) -> PreviewImage {
+    let image = &message.image;
+    let (size, rendering) = (message.size, message.rendering);
+
+    let pattern = match image.bayer_pattern() {
+        None => return PreviewImage::Gray(mono_scale(image, size, rendering)),
+        Some(pattern) => pattern,
+    };
+
+    PreviewImage::Rgb(match message.quality {
+        DemosaicQuality::Fast => debayer_scale_fast(image, pattern, size, rendering),
+        DemosaicQuality::Bilinear => demosaic_scale(
+            image, pattern, size, rendering, DemosaicMethod::Bilinear
+        ),
+        DemosaicQuality::EdgeAware => demosaic_scale(
+            image, pattern, size, rendering, DemosaicMethod::EdgeAware
+        ),
+    })
 }
\ No newline at end of file
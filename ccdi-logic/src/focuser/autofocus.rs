@@ -0,0 +1,349 @@
+use std::time::SystemTime;
+
+use ccdi_common::{AutofocusState, AutofocusView, FocusPoint, RawImage, FocusMetrics};
+use ccdi_focuser_interface::FocuserStatus;
+use log::info;
+
+use crate::config::AutofocusConfig;
+
+use super::fit::{best_position, fit_focus_curve, MIN_POINTS};
+
+// ============================================ PUBLIC =============================================
+
+/// Action requested by the autofocus routine from the camera controller
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AutofocusAction {
+    MoveFocuser(i32),
+    StartExposure,
+}
+
+/// Autofocus routine, moves the focuser through a range of positions, measures star HFR
+/// on a frame at each of them, fits a hyperbola and moves the focuser to its center
+pub struct Autofocus {
+    config: AutofocusConfig,
+    positions: Vec<i32>,
+    next: usize,
+    phase: Phase,
+    view: AutofocusView,
+}
+
+impl Autofocus {
+    pub fn new(config: AutofocusConfig) -> Self {
+        Self {
+            config,
+            positions: vec![],
+            next: 0,
+            phase: Phase::Idle,
+            view: Default::default(),
+        }
+    }
+
+    pub fn start(&mut self, position: i32, max_position: i32) -> Result<(), String> {
+        if self.is_running() {
+            return Err(String::from("Autofocus already running"))
+        }
+
+        let positions = measured_positions(&self.config, position, max_position);
+
+        if positions.len() < MIN_POINTS {
+            return Err(format!("Autofocus needs at least {} distinct positions", MIN_POINTS))
+        }
+
+        info!("Starting autofocus at positions {:?}", positions);
+
+        self.view = AutofocusView {
+            state: AutofocusState::Running,
+            detail: String::from("Moving to the first position"),
+            total_points: positions.len(),
+            ..Default::default()
+        };
+
+        self.phase = Phase::Move { target: positions[0], last: false };
+        self.positions = positions;
+        self.next = 1;
+        Ok(())
+    }
+
+    /// Stop the routine, returns true when an autofocus exposure is in progress
+    /// and should be aborted
+    pub fn stop(&mut self) -> bool {
+        if !self.is_running() {
+            return false
+        }
+
+        let exposing = self.phase == Phase::Exposing;
+        self.phase = Phase::Idle;
+        self.view.state = AutofocusState::Idle;
+        self.view.detail = String::from("Autofocus stopped");
+        info!("Autofocus stopped");
+        exposing
+    }
+
+    pub fn fail(&mut self, message: &str) {
+        if self.is_running() {
+            info!("Autofocus failed: {}", message);
+            self.phase = Phase::Idle;
+            self.view.state = AutofocusState::Failed;
+            self.view.detail = message.to_owned();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.phase != Phase::Idle
+    }
+
+    /// Called periodically, returns the next action to be executed, if any
+    pub fn periodic(
+        &mut self,
+        focuser: Option<FocuserStatus>,
+        exposure_active: bool,
+    ) -> Option<AutofocusAction> {
+        if !self.is_running() {
+            return None
+        }
+
+        let status = match focuser {
+            Some(status) => status,
+            None => {
+                self.fail("Focuser disconnected");
+                return None
+            }
+        };
+
+        match self.phase {
+            Phase::Idle => None,
+            Phase::Move { target, last } => {
+                self.phase = Phase::Moving { target, last };
+                Some(AutofocusAction::MoveFocuser(target))
+            },
+            Phase::Moving { target, last } => {
+                if status.moving || status.position != target || exposure_active {
+                    return None
+                }
+
+                match last {
+                    true => {
+                        self.finish(target);
+                        None
+                    },
+                    false => {
+                        self.view.detail = format!(
+                            "Measuring position {} ({}/{})",
+                            target, self.view.points.len() + 1, self.view.total_points
+                        );
+
+                        self.phase = Phase::Exposing;
+                        Some(AutofocusAction::StartExposure)
+                    }
+                }
+            },
+            Phase::Exposing => {
+                // Image of a finished exposure is handed over before the periodic call
+                if !exposure_active {
+                    self.fail("Autofocus exposure interrupted");
+                }
+
+                None
+            },
+            Phase::Measuring { .. } => None,
+        }
+    }
+
+    /// Image downloaded from the camera, its metrics are awaited if it belongs to the routine
+    pub fn image_ready(&mut self, image: &RawImage) {
+        if self.phase == Phase::Exposing {
+            self.phase = Phase::Measuring { frame: image.params.start_time };
+        }
+    }
+
+    /// Focus metrics of a frame measured by the process thread
+    pub fn focus_measured(&mut self, frame: SystemTime, metrics: &FocusMetrics) {
+        if self.phase != (Phase::Measuring { frame }) {
+            return
+        }
+
+        let position = self.positions[self.next - 1];
+        info!(
+            "Autofocus position {}: {} stars, HFR {:.2}", position, metrics.star_count, metrics.hfr
+        );
+
+        self.view.points.push(FocusPoint {
+            position,
+            hfr: metrics.hfr,
+            star_count: metrics.star_count,
+        });
+
+        match self.positions.get(self.next) {
+            Some(&target) => {
+                self.next += 1;
+                self.phase = Phase::Move { target, last: false };
+            },
+            None => self.evaluate(),
+        }
+    }
+
+    pub fn view(&self) -> AutofocusView {
+        self.view.clone()
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Phase {
+    Idle,
+    /// Focuser is to be commanded to the target position
+    Move { target: i32, last: bool },
+    /// Waiting for the focuser to settle at the target position,
+    /// last move goes to the best position after all frames were measured
+    Moving { target: i32, last: bool },
+    /// Waiting for the frame taken at the current position
+    Exposing,
+    /// Waiting for the metrics of the frame started at the given time
+    Measuring { frame: SystemTime },
+}
+
+impl Autofocus {
+    fn evaluate(&mut self) {
+        let measured = self.view.points.iter()
+            .filter(|point| point.star_count > 0)
+            .copied()
+            .collect::<Vec<_>>();
+
+        if measured.len() < MIN_POINTS {
+            return self.fail(&format!("Stars detected at only {} positions", measured.len()))
+        }
+
+        let curve = fit_focus_curve(&measured);
+
+        match best_position(&measured, curve.as_ref()) {
+            None => self.fail("Best focus position not found"),
+            Some(best) => {
+                self.view.curve = curve;
+                self.view.best_position = Some(best);
+                self.view.detail = format!("Moving to the best position {}", best);
+                self.phase = Phase::Move { target: best, last: true };
+            }
+        }
+    }
+
+    fn finish(&mut self, position: i32) {
+        let fitted = match self.view.curve {
+            Some(_) => "fitted curve",
+            None => "lowest measured HFR",
+        };
+
+        info!("Autofocus finished at position {}", position);
+        self.phase = Phase::Idle;
+        self.view.state = AutofocusState::Finished;
+        self.view.detail = format!("Focused at position {} ({})", position, fitted);
+    }
+}
+
+/// Positions centered at the current one, shifted to fit the focuser range
+fn measured_positions(config: &AutofocusConfig, position: i32, max_position: i32) -> Vec<i32> {
+    let step = config.step_size.max(1);
+    let span = step*(config.points.saturating_sub(1) as i32);
+    let first = (position - span/2).clamp(0, (max_position - span).max(0));
+
+    let mut positions = (0..config.points as i32)
+        .map(|index| (first + index*step).min(max_position))
+        .collect::<Vec<_>>();
+
+    positions.dedup();
+    positions
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ccdi_common::test_image;
+
+    use super::*;
+
+    fn config(step_size: i32, points: usize) -> AutofocusConfig {
+        AutofocusConfig { step_size, points }
+    }
+
+    fn status(position: i32, moving: bool) -> Option<FocuserStatus> {
+        Some(FocuserStatus { position, moving, temperature: None })
+    }
+
+    #[test]
+    fn positions_are_centered_and_kept_in_range() {
+        let positions = measured_positions(&config(100, 5), 1000, 5000);
+        assert_eq!(positions, vec![800, 900, 1000, 1100, 1200]);
+        assert_eq!(measured_positions(&config(100, 3), 20, 5000), vec![0, 100, 200]);
+        assert_eq!(measured_positions(&config(100, 3), 4990, 5000), vec![4800, 4900, 5000]);
+        assert_eq!(measured_positions(&config(100, 5), 100, 150), vec![0, 100, 150]);
+    }
+
+    #[test]
+    fn focuser_moves_before_each_exposure() {
+        let mut autofocus = Autofocus::new(config(100, 3));
+        autofocus.start(1000, 5000).unwrap();
+
+        let action = autofocus.periodic(status(1000, false), false);
+        assert_eq!(action, Some(AutofocusAction::MoveFocuser(900)));
+        assert_eq!(autofocus.periodic(status(950, true), false), None);
+        assert_eq!(autofocus.periodic(status(900, false), true), None);
+        let action = autofocus.periodic(status(900, false), false);
+        assert_eq!(action, Some(AutofocusAction::StartExposure));
+        assert_eq!(autofocus.periodic(status(900, false), true), None);
+        assert!(autofocus.stop());
+        assert_eq!(autofocus.view().state, AutofocusState::Idle);
+        assert_eq!(autofocus.periodic(status(900, false), false), None);
+    }
+
+    #[test]
+    fn interrupted_exposure_and_lost_focuser_fail() {
+        let mut autofocus = Autofocus::new(config(100, 3));
+        autofocus.start(1000, 5000).unwrap();
+        autofocus.periodic(status(1000, false), false);
+        autofocus.periodic(status(900, false), false);
+        autofocus.periodic(status(900, false), false);
+        assert_eq!(autofocus.view().state, AutofocusState::Failed);
+
+        autofocus.start(1000, 5000).unwrap();
+        autofocus.periodic(None, false);
+        assert_eq!(autofocus.view().state, AutofocusState::Failed);
+        assert!(!autofocus.is_running());
+    }
+
+    #[test]
+    fn measured_frames_move_focuser_to_best_position() {
+        let mut autofocus = Autofocus::new(config(100, 5));
+        autofocus.start(1000, 5000).unwrap();
+        let mut image = test_image(2, 2, vec![0; 4]);
+
+        for (index, position) in [800, 900, 1000, 1100, 1200].into_iter().enumerate() {
+            let action = autofocus.periodic(status(position - 100, false), false);
+            assert_eq!(action, Some(AutofocusAction::MoveFocuser(position)));
+            let action = autofocus.periodic(status(position, false), false);
+            assert_eq!(action, Some(AutofocusAction::StartExposure));
+
+            image.params.start_time = SystemTime::UNIX_EPOCH + Duration::from_secs(index as u64);
+            autofocus.image_ready(&image);
+            assert_eq!(autofocus.periodic(status(position, false), false), None);
+
+            // Metrics of another frame are ignored
+            let hfr = 1.0 + ((position - 1050) as f64/100.0).powi(2);
+            let metrics = FocusMetrics { star_count: 10, hfr, ..Default::default() };
+            autofocus.focus_measured(SystemTime::UNIX_EPOCH, &FocusMetrics::default());
+            autofocus.focus_measured(image.params.start_time, &metrics);
+        }
+
+        assert_eq!(autofocus.view().points.len(), 5);
+        let best = autofocus.view().best_position.expect("Best position found");
+        assert!((1040..=1060).contains(&best), "Best position {}", best);
+
+        let action = autofocus.periodic(status(1200, false), false);
+        assert_eq!(action, Some(AutofocusAction::MoveFocuser(best)));
+        assert_eq!(autofocus.periodic(status(best, false), false), None);
+        assert_eq!(autofocus.view().state, AutofocusState::Finished);
+        assert!(!autofocus.is_running());
+    }
+}
@@ -0,0 +1,164 @@
+use ccdi_common::{FocusCurve, FocusPoint};
+
+// ============================================ PUBLIC =============================================
+
+/// Fewer points can not define a V curve
+pub const MIN_POINTS: usize = 3;
+
+/// Fit hyperbola to the measured points, HFR² of a hyperbola is a parabola in the position,
+/// so the fit is a linear least squares fit of a parabola to squared HFR values.
+/// Returns `None` when the points do not form a valid V curve.
+pub fn fit_focus_curve(points: &[FocusPoint]) -> Option<FocusCurve> {
+    if points.len() < MIN_POINTS {
+        return None
+    }
+
+    // Positions are normalized to keep the normal equations well conditioned
+    let count = points.len() as f64;
+    let mean = points.iter().map(|point| point.position as f64).sum::<f64>()/count;
+    let scale = points.iter()
+        .map(|point| (point.position as f64 - mean).abs())
+        .fold(0.0, f64::max);
+
+    if scale <= 0.0 {
+        return None
+    }
+
+    let samples = points.iter()
+        .map(|point| ((point.position as f64 - mean)/scale, point.hfr*point.hfr))
+        .collect::<Vec<_>>();
+
+    let [p0, p1, p2] = fit_parabola(&samples)?;
+
+    if p2 <= 0.0 {
+        return None
+    }
+
+    let vertex = -p1/(2.0*p2);
+    let minimum = p0 - p1*p1/(4.0*p2);
+
+    if minimum <= 0.0 {
+        return None
+    }
+
+    let a = minimum.sqrt();
+
+    Some(FocusCurve {
+        a,
+        b: a*scale/p2.sqrt(),
+        center: mean + vertex*scale,
+    })
+}
+
+/// Best focuser position, center of the fitted curve when it lies within the measured
+/// range, position of the smallest measured HFR otherwise
+pub fn best_position(points: &[FocusPoint], curve: Option<&FocusCurve>) -> Option<i32> {
+    let lowest = points.iter().map(|point| point.position).min()?;
+    let highest = points.iter().map(|point| point.position).max()?;
+
+    let fitted = curve
+        .map(|curve| curve.center.round())
+        .filter(|center| *center >= lowest as f64 && *center <= highest as f64)
+        .map(|center| center as i32);
+
+    fitted.or_else(|| points.iter()
+        .min_by(|first, second| first.hfr.total_cmp(&second.hfr))
+        .map(|point| point.position)
+    )
+}
+
+// =========================================== PRIVATE =============================================
+
+/// Least squares coefficients of `y = p0 + p1*x + p2*x²`
+fn fit_parabola(samples: &[(f64, f64)]) -> Option<[f64; 3]> {
+    let mut sums = [0.0; 5];
+    let mut rhs = [0.0; 3];
+
+    for &(x, y) in samples {
+        let mut power = 1.0;
+
+        for (index, sum) in sums.iter_mut().enumerate() {
+            if index < 3 {
+                rhs[index] += power*y;
+            }
+
+            *sum += power;
+            power *= x;
+        }
+    }
+
+    let matrix = [
+        [sums[0], sums[1], sums[2]],
+        [sums[1], sums[2], sums[3]],
+        [sums[2], sums[3], sums[4]],
+    ];
+
+    let det = determinant(&matrix);
+
+    if det.abs() < 1e-12 {
+        return None
+    }
+
+    // Cramer's rule, each coefficient replaces one column by the right hand side
+    let mut result = [0.0; 3];
+
+    for (column, coefficient) in result.iter_mut().enumerate() {
+        let mut replaced = matrix;
+
+        for (row, value) in replaced.iter_mut().zip(rhs) {
+            row[column] = value;
+        }
+
+        *coefficient = determinant(&replaced)/det;
+    }
+
+    Some(result)
+}
+
+fn determinant(m: &[[f64; 3]; 3]) -> f64 {
+    m[0][0]*(m[1][1]*m[2][2] - m[1][2]*m[2][1])
+        - m[0][1]*(m[1][0]*m[2][2] - m[1][2]*m[2][0])
+        + m[0][2]*(m[1][0]*m[2][1] - m[1][1]*m[2][0])
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(position: i32, hfr: f64) -> FocusPoint {
+        FocusPoint { position, hfr, star_count: 10 }
+    }
+
+    #[test]
+    fn exact_hyperbola_is_recovered() {
+        let truth = FocusCurve { a: 2.0, b: 400.0, center: 31370.0 };
+
+        let points = (0..9)
+            .map(|index| 30000 + index*300)
+            .map(|position| point(position, truth.hfr_at(position as f64)))
+            .collect::<Vec<_>>();
+
+        let curve = fit_focus_curve(&points).unwrap();
+        assert!((curve.a - truth.a).abs() < 1e-6);
+        assert!((curve.b - truth.b).abs() < 1e-3);
+        assert!((curve.center - truth.center).abs() < 1e-3);
+        assert_eq!(best_position(&points, Some(&curve)), Some(31370));
+    }
+
+    #[test]
+    fn monotonic_points_fall_back_to_lowest_hfr() {
+        let points = vec![point(100, 2.0), point(200, 4.0), point(300, 6.0)];
+        let curve = fit_focus_curve(&points);
+
+        assert!(curve.is_none_or(|curve| curve.center < 100.0));
+        assert_eq!(best_position(&points, curve.as_ref()), Some(100));
+    }
+
+    #[test]
+    fn too_few_points_are_rejected() {
+        assert_eq!(fit_focus_curve(&[point(100, 2.0), point(200, 3.0)]), None);
+        assert_eq!(best_position(&[], None), None);
+    }
+}
@@ -0,0 +1,122 @@
+mod autofocus;
+mod fit;
+
+use ccdi_focuser_interface::{FocuserDriver, FocuserDevice, FocuserProperties, FocuserStatus};
+use log::info;
+
+pub use autofocus::{Autofocus, AutofocusAction};
+
+// ============================================ PUBLIC =============================================
+
+/// Keeps the first focuser provided by the driver connected and tracks its status
+pub struct FocuserController {
+    driver: Option<Box<dyn FocuserDriver>>,
+    device: Option<Box<dyn FocuserDevice>>,
+    properties: Option<FocuserProperties>,
+    status: Option<FocuserStatus>,
+}
+
+impl FocuserController {
+    /// Controller without a driver never connects, focuser support is optional
+    pub fn new(driver: Option<Box<dyn FocuserDriver>>) -> Self {
+        Self {
+            driver,
+            device: None,
+            properties: None,
+            status: None,
+        }
+    }
+
+    /// Connect the focuser if needed and read its status, device is dropped on failure
+    /// and connected again in the next call
+    pub fn periodic(&mut self) -> Result<(), String> {
+        if self.driver.is_none() {
+            return Ok(())
+        }
+
+        if self.device.is_none() {
+            self.connect()?;
+        }
+
+        let result = match self.device.as_mut() {
+            None => return Ok(()),
+            Some(device) => device.read_status(),
+        };
+
+        match result {
+            Ok(status) => {
+                self.status = Some(status);
+                Ok(())
+            },
+            Err(message) => {
+                self.disconnect();
+                Err(message)
+            }
+        }
+    }
+
+    pub fn move_to(&mut self, position: i32) -> Result<(), String> {
+        let max_position = self.properties.as_ref().map(|properties| properties.max_position);
+
+        match (self.device.as_mut(), max_position) {
+            (Some(device), Some(max_position)) => device.move_to(position.clamp(0, max_position)),
+            _ => Err(String::from("Focuser not connected")),
+        }
+    }
+
+    pub fn halt(&mut self) -> Result<(), String> {
+        match self.device.as_mut() {
+            Some(device) => device.halt(),
+            None => Err(String::from("Focuser not connected")),
+        }
+    }
+
+    pub fn properties(&self) -> Option<&FocuserProperties> {
+        self.properties.as_ref()
+    }
+
+    pub fn status(&self) -> Option<FocuserStatus> {
+        self.status
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+impl FocuserController {
+    fn connect(&mut self) -> Result<(), String> {
+        let driver = match self.driver.as_mut() {
+            Some(driver) => driver,
+            None => return Ok(()),
+        };
+
+        let descriptor = match driver.list_devices()?.into_iter().next() {
+            Some(descriptor) => descriptor,
+            None => return Ok(()),
+        };
+
+        let mut device = driver.connect_device(&descriptor)?;
+
+        match device.read_properties() {
+            Ok(properties) => {
+                info!("Focuser {} connected", properties.name);
+                self.properties = Some(properties);
+                self.device = Some(device);
+                Ok(())
+            },
+            Err(message) => {
+                device.close();
+                Err(message)
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(mut device) = self.device.take() {
+            info!("Focuser disconnected");
+            device.close();
+        }
+
+        self.properties = None;
+        self.status = None;
+    }
+}
@@ -5,6 +5,12 @@ mod convert;
 mod config;
 mod storage;
 mod io;
+mod sequencer;
+mod stars;
+mod focuser;
+
+#[cfg(test)]
+mod test_util;
 
 pub use thread::*;
 pub use config::*;
\ No newline at end of file
@@ -0,0 +1,352 @@
+use std::{path::{PathBuf, Path}, time::Instant};
+
+use ccdi_common::{
+    ExposurePlan, PlanStep, SequenceMessage, SequenceProgress, SequenceState, SequenceView,
+    save_text_file, read_text_file, to_string, log_err
+};
+use log::info;
+use serde_derive::{Serialize, Deserialize};
+
+// ============================================ PUBLIC =============================================
+
+/// Executes exposure plan steps one frame at a time, the plan and progress are persisted
+/// so that an interrupted sequence can be resumed after a service restart
+pub struct Sequencer {
+    stored: StoredSequence,
+    path: Option<PathBuf>,
+    /// Start time of the exposure requested by the sequencer
+    exposure: Option<Instant>,
+    /// Step for which the storage directory was already set
+    directory_step: Option<usize>,
+    /// Measured time between exposure end and image download in seconds
+    overhead: f64,
+}
+
+/// Exposure the sequencer asks to start
+pub struct FrameRequest {
+    pub step: PlanStep,
+    /// Storage directory must be switched before the frame is saved
+    pub change_directory: bool,
+}
+
+impl Sequencer {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let mut stored = path.as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| log_err("Load sequence", load_sequence(path)))
+            .unwrap_or_default();
+
+        // Sequence interrupted by a restart is never resumed without user action
+        if stored.state == SequenceState::Running {
+            stored.state = SequenceState::Paused;
+        }
+
+        Self { stored, path, exposure: None, directory_step: None, overhead: 0.0 }
+    }
+
+    /// Process a sequencer command, returns true if the current exposure must be aborted
+    pub fn process(&mut self, message: SequenceMessage) -> Result<bool, String> {
+        let abort = match message {
+            SequenceMessage::SetPlan(plan) => {
+                if self.is_busy() {
+                    return Err(String::from("Stop the sequence before changing the plan"))
+                }
+
+                self.stored = StoredSequence { plan, ..Default::default() };
+                false
+            },
+            SequenceMessage::Start => {
+                if self.is_busy() {
+                    return Err(String::from("Sequence is already running"))
+                }
+
+                if self.stored.plan.total_frames() == 0 {
+                    return Err(String::from("Plan does not contain any frames"))
+                }
+
+                self.stored.state = SequenceState::Running;
+                self.stored.step = 0;
+                self.stored.frame = 0;
+                self.stored.skipped = 0;
+                self.directory_step = None;
+                false
+            },
+            SequenceMessage::Pause => {
+                if self.stored.state == SequenceState::Running {
+                    self.stored.state = SequenceState::Paused;
+                }
+                false
+            },
+            SequenceMessage::Resume => {
+                if self.stored.state == SequenceState::Paused {
+                    self.stored.state = SequenceState::Running;
+                }
+                false
+            },
+            SequenceMessage::Skip => match self.is_busy() {
+                false => false,
+                true => {
+                    let remaining = self.current_step()
+                        .map(|step| step.count.saturating_sub(self.stored.frame))
+                        .unwrap_or(0);
+
+                    self.stored.skipped += remaining;
+                    self.next_step();
+                    self.exposure.take().is_some()
+                }
+            },
+            SequenceMessage::Stop => {
+                self.stored.state = SequenceState::Idle;
+                self.exposure.take().is_some()
+            },
+        };
+
+        self.save();
+        Ok(abort)
+    }
+
+    /// Called periodically, returns the next frame to be exposed, if any, no frame
+    /// is returned until the start is allowed
+    pub fn periodic(&mut self, exposure_active: bool, start_allowed: bool) -> Option<FrameRequest> {
+        if let Some(started) = self.exposure {
+            if exposure_active {
+                return None
+            }
+
+            self.exposure = None;
+            self.frame_finished(started.elapsed().as_secs_f64());
+        }
+
+        match self.stored.state {
+            SequenceState::Running if !exposure_active && start_allowed => self.next_frame(),
+            _ => None
+        }
+    }
+
+    /// Exposure requested by the sequencer was aborted or failed outside of the
+    /// sequencer, the frame is not counted and the sequence is paused
+    pub fn exposure_interrupted(&mut self) {
+        if self.exposure.take().is_some() && self.stored.state == SequenceState::Running {
+            info!("Sequence exposure interrupted, pausing sequence");
+            self.stored.state = SequenceState::Paused;
+            self.save();
+        }
+    }
+
+    pub fn view(&self) -> SequenceView {
+        SequenceView {
+            plan: self.stored.plan.clone(),
+            progress: SequenceProgress {
+                state: self.stored.state,
+                step: self.stored.step,
+                frame: self.stored.frame,
+                frames_done: self.frames_done(),
+                frames_total: self.stored.plan.total_frames(),
+                eta: self.eta(),
+            }
+        }
+    }
+
+    /// Sequence is running or paused
+    pub fn is_busy(&self) -> bool {
+        match self.stored.state {
+            SequenceState::Running | SequenceState::Paused => true,
+            SequenceState::Idle | SequenceState::Finished => false,
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+struct StoredSequence {
+    plan: ExposurePlan,
+    state: SequenceState,
+    step: usize,
+    frame: usize,
+    /// Frames of skipped steps, they are not counted as done
+    #[serde(default)]
+    skipped: usize,
+}
+
+impl Sequencer {
+    fn current_step(&self) -> Option<&PlanStep> {
+        self.stored.plan.steps.get(self.stored.step)
+    }
+
+    fn frame_finished(&mut self, duration: f64) {
+        let (time, step_count) = match self.current_step() {
+            None => return,
+            Some(step) => (step.time, step.count),
+        };
+
+        self.overhead = (duration - time).max(0.0);
+
+        self.stored.frame += 1;
+
+        if self.stored.frame >= step_count {
+            self.next_step();
+        }
+
+        self.save();
+    }
+
+    fn next_step(&mut self) {
+        self.stored.step += 1;
+        self.stored.frame = 0;
+    }
+
+    fn next_frame(&mut self) -> Option<FrameRequest> {
+        while self.current_step().map(|step| step.count == 0).unwrap_or(false) {
+            self.next_step();
+        }
+
+        let index = self.stored.step;
+
+        match self.current_step().cloned() {
+            None => {
+                info!("Sequence finished");
+                self.stored.state = SequenceState::Finished;
+                self.save();
+                None
+            },
+            Some(step) => {
+                let change_directory = self.directory_step != Some(index);
+                self.directory_step = Some(index);
+                self.exposure = Some(Instant::now());
+                Some(FrameRequest { step, change_directory })
+            }
+        }
+    }
+
+    fn frames_done(&self) -> usize {
+        let previous: usize = self.stored.plan.steps.iter()
+            .take(self.stored.step)
+            .map(|step| step.count)
+            .sum();
+
+        (previous + self.stored.frame).saturating_sub(self.stored.skipped)
+    }
+
+    fn eta(&self) -> Option<f64> {
+        if !self.is_busy() {
+            return None
+        }
+
+        let frame_time = |step: &PlanStep| step.time + self.overhead;
+
+        let remaining: f64 = self.stored.plan.steps.iter()
+            .enumerate()
+            .skip(self.stored.step)
+            .map(|(index, step)| match index == self.stored.step {
+                true => step.count.saturating_sub(self.stored.frame) as f64*frame_time(step),
+                false => step.count as f64*frame_time(step),
+            })
+            .sum();
+
+        let elapsed = self.exposure
+            .map(|started| started.elapsed().as_secs_f64())
+            .unwrap_or(0.0);
+
+        Some((remaining - elapsed).max(0.0))
+    }
+
+    fn save(&self) {
+        if let Some(path) = self.path.as_ref() {
+            log_err("Save sequence", save_sequence(&self.stored, path));
+        }
+    }
+}
+
+fn load_sequence(path: &Path) -> Result<StoredSequence, String> {
+    serde_yaml::from_str(&read_text_file(path)?).map_err(to_string)
+}
+
+fn save_sequence(sequence: &StoredSequence, path: &Path) -> Result<(), String> {
+    save_text_file(&serde_yaml::to_string(sequence).map_err(to_string)?, path)
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(count: usize, directory: &str) -> PlanStep {
+        PlanStep { count, time: 1.0, directory: directory.to_owned(), ..Default::default() }
+    }
+
+    fn running_sequencer(steps: Vec<PlanStep>) -> Sequencer {
+        let mut sequencer = Sequencer::new(None);
+        sequencer.process(SequenceMessage::SetPlan(ExposurePlan { steps })).unwrap();
+        sequencer.process(SequenceMessage::Start).unwrap();
+        sequencer
+    }
+
+    #[test]
+    fn sequence_executes_all_frames() {
+        let mut sequencer = running_sequencer(vec![step(2, "a"), step(0, "b"), step(1, "c")]);
+        let mut directories = vec![];
+
+        while let Some(request) = sequencer.periodic(false, true) {
+            if request.change_directory {
+                directories.push(request.step.directory);
+            }
+        }
+
+        assert_eq!(directories, vec!["a", "c"]);
+        assert_eq!(sequencer.view().progress.state, SequenceState::Finished);
+        assert_eq!(sequencer.view().progress.frames_done, 3);
+    }
+
+    #[test]
+    fn active_exposure_is_not_counted() {
+        let mut sequencer = running_sequencer(vec![step(2, "a")]);
+        assert!(sequencer.periodic(false, true).is_some());
+        assert!(sequencer.periodic(true, true).is_none());
+        assert_eq!(sequencer.view().progress.frames_done, 0);
+    }
+
+    #[test]
+    fn frame_is_counted_while_start_is_not_allowed() {
+        let mut sequencer = running_sequencer(vec![step(2, "a")]);
+        assert!(sequencer.periodic(false, true).is_some());
+        assert!(sequencer.periodic(false, false).is_none());
+        assert_eq!(sequencer.view().progress.frames_done, 1);
+        assert!(sequencer.periodic(false, true).is_some());
+    }
+
+    #[test]
+    fn paused_sequence_finishes_current_frame_only() {
+        let mut sequencer = running_sequencer(vec![step(3, "a")]);
+        assert!(sequencer.periodic(false, true).is_some());
+        assert_eq!(sequencer.process(SequenceMessage::Pause), Ok(false));
+        assert!(sequencer.periodic(false, true).is_none());
+        assert_eq!(sequencer.view().progress.frames_done, 1);
+        sequencer.process(SequenceMessage::Resume).unwrap();
+        assert!(sequencer.periodic(false, true).is_some());
+    }
+
+    #[test]
+    fn skip_aborts_exposure_and_moves_to_next_step() {
+        let mut sequencer = running_sequencer(vec![step(3, "a"), step(1, "b")]);
+        assert!(sequencer.periodic(false, true).is_some());
+        assert_eq!(sequencer.process(SequenceMessage::Skip), Ok(true));
+
+        let request = sequencer.periodic(false, true).expect("Next frame expected");
+        assert_eq!(request.step.directory, "b");
+        assert!(request.change_directory);
+        assert_eq!(sequencer.view().progress.frames_done, 0);
+
+        assert!(sequencer.periodic(false, true).is_none());
+        assert_eq!(sequencer.view().progress.frames_done, 1);
+        assert_eq!(sequencer.view().progress.frames_total, 4);
+    }
+
+    #[test]
+    fn plan_cannot_change_while_running() {
+        let mut sequencer = running_sequencer(vec![step(1, "a")]);
+        let plan = ExposurePlan { steps: vec![] };
+        assert!(sequencer.process(SequenceMessage::SetPlan(plan)).is_err());
+    }
+}
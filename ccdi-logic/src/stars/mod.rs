@@ -0,0 +1,397 @@
+use std::{borrow::Cow, cmp::Reverse, f64::consts::PI};
+
+use ccdi_common::{RawImage, FocusMetrics};
+
+// ============================================ PUBLIC =============================================
+
+/// Detect stars and compute median focus metrics of the frame, color images are measured
+/// on whole bayer cells so that the color pattern does not distort the star profiles
+pub fn measure_focus(image: &RawImage) -> FocusMetrics {
+    let (plane, scale) = match image.metadata.bayer_pattern {
+        Some(_) => (Plane::bayer_cells(image), 2.0),
+        None => (Plane::chip_pixels(image), 1.0),
+    };
+
+    let (detected, stars) = detect_stars(&plane);
+    summarize(detected, &stars, scale)
+}
+
+// =========================================== PRIVATE =============================================
+
+/// Detection threshold above the background in noise standard deviations
+const DETECTION_SIGMA: f64 = 5.0;
+/// Components smaller than this are hot pixels or noise
+const MIN_STAR_PIXELS: usize = 3;
+/// Components larger than this are nebulae, satellites or saturated blobs
+const MAX_STAR_PIXELS: usize = 2500;
+/// Only the brightest stars are measured to bound the processing time in dense fields
+const MAX_MEASURED_STARS: usize = 200;
+const MAX_APERTURE_RADIUS: f64 = 40.0;
+const BACKGROUND_SAMPLES: usize = 100_000;
+/// Ratio of FWHM and standard deviation of a gaussian profile
+const GAUSSIAN_FWHM: f64 = 2.354_820_045;
+/// Ratio of standard deviation and median absolute deviation of normal distribution
+const MAD_TO_SIGMA: f64 = 1.4826;
+
+/// Single channel image the stars are detected in
+struct Plane<'a> {
+    data: Cow<'a, [u16]>,
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Plane<'a> {
+    fn chip_pixels(image: &'a RawImage) -> Self {
+        Self {
+            data: Cow::Borrowed(&image.data),
+            width: image.params.area.width,
+            height: image.params.area.height,
+        }
+    }
+
+    /// Average of each 2x2 bayer cell
+    fn bayer_cells(image: &RawImage) -> Self {
+        let (input_width, input_height) = (image.params.area.width, image.params.area.height);
+        let (width, height) = (input_width/2, input_height/2);
+        let mut data = Vec::with_capacity(width*height);
+
+        for y in 0..height {
+            let top = &image.data[2*y*input_width..(2*y + 1)*input_width];
+            let bottom = &image.data[(2*y + 1)*input_width..(2*y + 2)*input_width];
+
+            for x in 0..width {
+                let sum = top[2*x] as u32 + top[2*x + 1] as u32
+                    + bottom[2*x] as u32 + bottom[2*x + 1] as u32;
+
+                data.push((sum/4) as u16);
+            }
+        }
+
+        Self { data: Cow::Owned(data), width, height }
+    }
+
+    fn value(&self, x: usize, y: usize) -> u16 {
+        self.data[y*self.width + x]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Background {
+    level: f64,
+    sigma: f64,
+}
+
+/// Pixels of a connected area above the detection threshold
+struct Component {
+    pixels: Vec<usize>,
+    peak: u16,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Star {
+    hfr: f64,
+    fwhm: f64,
+    eccentricity: f64,
+}
+
+/// Returns the number of detected stars and measurements of the brightest ones
+fn detect_stars(plane: &Plane) -> (usize, Vec<Star>) {
+    if plane.data.is_empty() {
+        return (0, Vec::new())
+    }
+
+    let background = estimate_background(plane);
+    let mut components = find_components(plane, &background);
+    components.sort_by_key(|component| Reverse(component.peak));
+
+    let stars = components.iter()
+        .take(MAX_MEASURED_STARS)
+        .filter_map(|component| measure_star(plane, &background, component))
+        .collect::<Vec<_>>();
+
+    let rejected = components.len().min(MAX_MEASURED_STARS) - stars.len();
+    (components.len() - rejected, stars)
+}
+
+/// Median and robust noise estimate from a regular sample of pixels
+fn estimate_background(plane: &Plane) -> Background {
+    let step = (plane.data.len()/BACKGROUND_SAMPLES).max(1);
+    let mut samples = plane.data.iter().step_by(step).copied().collect::<Vec<u16>>();
+    let level = median_u16(&mut samples) as f64;
+
+    let mut deviations = samples.iter()
+        .map(|sample| (*sample as f64 - level).abs() as u16)
+        .collect::<Vec<u16>>();
+
+    let sigma = (median_u16(&mut deviations) as f64*MAD_TO_SIGMA).max(1.0);
+    Background { level, sigma }
+}
+
+fn find_components(plane: &Plane, background: &Background) -> Vec<Component> {
+    let threshold = background.level + DETECTION_SIGMA*background.sigma;
+    let above = |index: usize| plane.data[index] as f64 > threshold;
+    let mut visited = vec![false; plane.data.len()];
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..plane.data.len() {
+        if visited[start] || !above(start) {
+            continue
+        }
+
+        visited[start] = true;
+        stack.push(start);
+        let mut pixels = Vec::new();
+        let mut peak = 0;
+        let mut touches_edge = false;
+
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index % plane.width, index/plane.width);
+            touches_edge |= x == 0 || y == 0 || x + 1 == plane.width || y + 1 == plane.height;
+            peak = peak.max(plane.data[index]);
+
+            if pixels.len() <= MAX_STAR_PIXELS {
+                pixels.push(index);
+            }
+
+            for (nx, ny) in neighbors(x, y, plane.width, plane.height) {
+                let neighbor = ny*plane.width + nx;
+
+                if !visited[neighbor] && above(neighbor) {
+                    visited[neighbor] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        let size_ok = (MIN_STAR_PIXELS..=MAX_STAR_PIXELS).contains(&pixels.len());
+
+        if size_ok && !touches_edge && peak < u16::MAX {
+            components.push(Component { pixels, peak });
+        }
+    }
+
+    components
+}
+
+fn neighbors(
+    x: usize, y: usize, width: usize, height: usize
+) -> impl Iterator<Item = (usize, usize)> {
+    (-1isize..=1)
+        .flat_map(|dy| (-1isize..=1).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| dx != 0 || dy != 0)
+        .map(move |(dx, dy)| (x as isize + dx, y as isize + dy))
+        .filter(move |&(x, y)| x >= 0 && y >= 0 && x < width as isize && y < height as isize)
+        .map(|(x, y)| (x as usize, y as usize))
+}
+
+/// Star profile is measured in a circular aperture around the component centroid,
+/// size is computed from flux weighted radii and second moments
+fn measure_star(plane: &Plane, background: &Background, component: &Component) -> Option<Star> {
+    let (mut sum, mut sum_x, mut sum_y) = (0.0, 0.0, 0.0);
+
+    for &index in component.pixels.iter() {
+        let value = plane.data[index] as f64 - background.level;
+        sum += value;
+        sum_x += value*(index % plane.width) as f64;
+        sum_y += value*(index/plane.width) as f64;
+    }
+
+    if sum <= 0.0 {
+        return None
+    }
+
+    let (cx, cy) = (sum_x/sum, sum_y/sum);
+    let radius = (2.0*(component.pixels.len() as f64/PI).sqrt() + 3.0).min(MAX_APERTURE_RADIUS);
+
+    let x_range = cx - radius..=cx + radius;
+    let y_range = cy - radius..=cy + radius;
+    let clamp_x = |value: f64| value.clamp(0.0, (plane.width - 1) as f64) as usize;
+    let clamp_y = |value: f64| value.clamp(0.0, (plane.height - 1) as f64) as usize;
+
+    let (mut flux, mut radius_sum) = (0.0, 0.0);
+    let (mut xx, mut yy, mut xy) = (0.0, 0.0, 0.0);
+
+    for y in clamp_y(*y_range.start())..=clamp_y(*y_range.end()) {
+        for x in clamp_x(*x_range.start())..=clamp_x(*x_range.end()) {
+            let (dx, dy) = (x as f64 - cx, y as f64 - cy);
+            let distance = (dx*dx + dy*dy).sqrt();
+
+            if distance > radius {
+                continue
+            }
+
+            // Background is not clipped, so that noise averages out in the sums
+            let value = plane.value(x, y) as f64 - background.level;
+            flux += value;
+            radius_sum += value*distance;
+            xx += value*dx*dx;
+            yy += value*dy*dy;
+            xy += value*dx*dy;
+        }
+    }
+
+    if flux <= 0.0 {
+        return None
+    }
+
+    let (xx, yy, xy) = (xx/flux, yy/flux, xy/flux);
+    let spread = (((xx - yy)/2.0).powi(2) + xy*xy).sqrt();
+    let major = ((xx + yy)/2.0 + spread).max(0.0);
+    let minor = ((xx + yy)/2.0 - spread).max(0.0);
+
+    let star = Star {
+        hfr: radius_sum/flux,
+        fwhm: GAUSSIAN_FWHM*((major + minor)/2.0).sqrt(),
+        eccentricity: match major > 0.0 {
+            true => (1.0 - minor/major).sqrt(),
+            false => 0.0,
+        },
+    };
+
+    let valid = star.hfr.is_finite() && star.fwhm.is_finite() && star.hfr > 0.0;
+    valid.then_some(star)
+}
+
+fn summarize(detected: usize, stars: &[Star], scale: f64) -> FocusMetrics {
+    let median_of = |value: fn(&Star) -> f64| median(stars.iter().map(value).collect());
+
+    FocusMetrics {
+        star_count: detected,
+        hfr: median_of(|star| star.hfr)*scale,
+        fwhm: median_of(|star| star.fwhm)*scale,
+        eccentricity: median_of(|star| star.eccentricity),
+    }
+}
+
+fn median_u16(values: &mut [u16]) -> u16 {
+    match values.is_empty() {
+        true => 0,
+        false => *values.select_nth_unstable(values.len()/2).1,
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(|first, second| first.total_cmp(second));
+
+    match values.len() {
+        0 => 0.0,
+        length if length % 2 == 0 => (values[length/2 - 1] + values[length/2])/2.0,
+        length => values[length/2],
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use ccdi_common::test_image;
+    use ccdi_imager_interface::BayerPattern;
+
+    use super::*;
+
+    const BACKGROUND: f64 = 1000.0;
+
+    /// Gaussian star with sigma along x and y axes
+    struct TestStar {
+        x: f64,
+        y: f64,
+        sigma_x: f64,
+        sigma_y: f64,
+        peak: f64,
+    }
+
+    fn star(x: f64, y: f64, sigma_x: f64, sigma_y: f64) -> TestStar {
+        TestStar { x, y, sigma_x, sigma_y, peak: 20000.0 }
+    }
+
+    fn render(width: usize, height: usize, stars: &[TestStar]) -> RawImage {
+        // Deterministic noise from a linear congruential generator
+        let mut seed = 12345u32;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % 41) as f64 - 20.0
+        };
+
+        let data = (0..width*height)
+            .map(|index| {
+                let (x, y) = ((index % width) as f64, (index/width) as f64);
+
+                let signal = stars.iter().map(|star| {
+                    let (dx, dy) = ((x - star.x)/star.sigma_x, (y - star.y)/star.sigma_y);
+                    star.peak*(-(dx*dx + dy*dy)/2.0).exp()
+                }).sum::<f64>();
+
+                (BACKGROUND + signal + noise()) as u16
+            })
+            .collect();
+
+        test_image(width, height, data)
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "Expected {} +- {}, got {}", expected, tolerance, actual
+        );
+    }
+
+    #[test]
+    fn round_stars_are_measured() {
+        let stars = [
+            star(30.0, 30.0, 2.0, 2.0),
+            star(90.0, 40.0, 2.0, 2.0),
+            star(60.0, 90.0, 2.0, 2.0),
+        ];
+
+        let metrics = measure_focus(&render(128, 128, &stars));
+
+        assert_eq!(metrics.star_count, 3);
+        assert_close(metrics.fwhm, 2.0*GAUSSIAN_FWHM, 0.4);
+        // Mean radius of a gaussian profile is sigma*sqrt(pi/2)
+        assert_close(metrics.hfr, 2.0*(PI/2.0).sqrt(), 0.4);
+        assert!(metrics.eccentricity < 0.35, "Eccentricity {}", metrics.eccentricity);
+    }
+
+    #[test]
+    fn elongated_stars_have_high_eccentricity() {
+        let stars = [star(40.0, 40.0, 4.0, 2.0), star(90.0, 80.0, 4.0, 2.0)];
+        let metrics = measure_focus(&render(128, 128, &stars));
+
+        assert_eq!(metrics.star_count, 2);
+        assert_close(metrics.eccentricity, (1.0 - 0.25f64).sqrt(), 0.1);
+    }
+
+    #[test]
+    fn stars_above_measured_limit_are_counted() {
+        let stars = (0..225)
+            .map(|index| star((16 + index % 15*16) as f64, (16 + index/15*16) as f64, 1.5, 1.5))
+            .collect::<Vec<_>>();
+
+        let metrics = measure_focus(&render(256, 256, &stars));
+
+        assert!(stars.len() > MAX_MEASURED_STARS);
+        assert_eq!(metrics.star_count, 225);
+    }
+
+    #[test]
+    fn noise_edges_and_saturation_are_ignored() {
+        let mut saturated = star(60.0, 60.0, 2.0, 2.0);
+        saturated.peak = 100000.0;
+
+        let stars = [star(1.0, 50.0, 2.0, 2.0), saturated];
+        let metrics = measure_focus(&render(128, 128, &stars));
+
+        assert_eq!(metrics, FocusMetrics::default());
+    }
+
+    #[test]
+    fn color_images_are_measured_in_chip_pixels() {
+        let mut image = render(128, 128, &[star(40.0, 40.0, 3.0, 3.0), star(80.0, 90.0, 3.0, 3.0)]);
+        image.metadata.bayer_pattern = Some(BayerPattern::Rggb);
+        let metrics = measure_focus(&image);
+
+        assert_eq!(metrics.star_count, 2);
+        assert_close(metrics.fwhm, 3.0*GAUSSIAN_FWHM, 1.0);
+    }
+}
@@ -1,33 +1,43 @@
-use std::sync::{Arc, mpsc::Sender};
+use std::{sync::{Arc, mpsc::Sender}, time::Instant};
 
-use ccdi_common::{ClientMessage, StateMessage, RgbImage, ProcessMessage, StorageMessage, IoMessage};
+use ccdi_common::{
+    ClientMessage, StateMessage, PreviewImage, ProcessMessage, StorageMessage, IoMessage, log_err
+};
+use ccdi_focuser_interface::FocuserDriver;
+use ccdi_imager_demo::{DemoFocus, DemoFocuserDriver, DemoImagerDriver};
+use ccdi_imager_interface::ImagerDriver;
+use ccdi_imager_playback::PlaybackImagerDriver;
 
-use crate::{camera::CameraController, ServiceConfig};
+use crate::{
+    camera::CameraController, ServiceConfig, ImagerSource, sequence_file_path, camera_file_path
+};
 
 // ============================================ PUBLIC =============================================
 
 pub struct BackendState {
     camera: CameraController,
     /// Last image sent to clients
-    image: Option<Arc<RgbImage<u16>>>,
+    image: Option<Arc<PreviewImage>>,
 }
 
 impl BackendState {
     pub fn new(
-        demo_mode: bool,
+        imager: ImagerSource,
         process_tx: Sender<ProcessMessage>,
         storage_tx: Sender<StorageMessage>,
         config: Arc<ServiceConfig>,
     ) -> Self {
+        let (driver, focuser_driver) = drivers(imager, &config);
+
         Self {
             camera: CameraController::new(
-                match demo_mode {
-                    false => Box::new(ccdi_imager_moravian::MoravianImagerDriver::new()),
-                    true => Box::new(ccdi_imager_demo::DemoImagerDriver::new()),
-                },
+                driver,
+                focuser_driver,
                 process_tx,
                 storage_tx,
-                config
+                config,
+                log_err("Sequence file path", sequence_file_path()),
+                log_err("Camera file path", camera_file_path()),
             ),
             image: None,
         }
@@ -37,11 +47,19 @@ impl BackendState {
     pub fn process(&mut self, message: StateMessage) -> Result<BackendResult, String> {
         use StateMessage::*;
 
+        if self.camera.shutting_down() && user_command(&message) {
+            return Ok(self.return_view())
+        }
+
         Ok(match message {
             ImageDisplayed(image) => {
                 self.image = Some(image);
                 BackendResult::empty()
             },
+            FocusMeasured(frame, metrics) => {
+                self.camera.focus_measured(frame, &metrics);
+                BackendResult::empty()
+            },
             CameraParam(message) => {
                 let heating = match message {
                     ccdi_common::CameraParamMessage::SetHeatingPwm(value) => Some(value),
@@ -64,14 +82,16 @@ impl BackendState {
                 self.return_view()
             },
             ClientConnected => {
-                let view_msg = ClientMessage::View(self.camera.get_view());
+                let mut messages = vec![
+                    ClientMessage::View(self.camera.get_view()),
+                    ClientMessage::Telemetry(self.camera.telemetry()),
+                ];
 
-                BackendResult::client(
-                    match self.image.as_ref() {
-                        None => vec![view_msg],
-                        Some(image) => vec![view_msg, ClientMessage::RgbImage(image.clone())],
-                    }
-                )
+                if let Some(image) = self.image.as_ref() {
+                    messages.push(ClientMessage::Image(image.clone()));
+                }
+
+                BackendResult::client(messages)
             }
             UpdateStorageState(storage_state) => {
                 self.camera.update_storage_status(storage_state);
@@ -80,7 +100,7 @@ impl BackendState {
             TriggerValueChanged(value) => {
                 self.camera.update_trigger_status(value);
                 // Trigger might be switched on, perform idle tasks immediately
-                let (client, io) = self.camera.periodic();
+                let (client, io) = self.camera.periodic(Instant::now());
                 BackendResult::client_io(client, io)
             },
             StorageMessage(message) => {
@@ -94,6 +114,22 @@ impl BackendState {
                 self.camera.update_storage_detail(detail);
                 self.return_view()
             },
+            SequenceMessage(message) => {
+                self.camera.sequence_message(message);
+                self.return_view()
+            },
+            FocuserMessage(message) => {
+                self.camera.focuser_message(message);
+                self.return_view()
+            },
+            SelectCamera(id) => {
+                self.camera.select_camera(id);
+                self.return_view()
+            },
+            RefreshCameras => {
+                self.camera.refresh_cameras();
+                self.return_view()
+            },
             PowerOff => {
                 self.camera.turn_off();
                 BackendResult::empty()
@@ -103,9 +139,14 @@ impl BackendState {
 
     /// Called periodically to perform any tasks needed and return messages for clients
     pub fn periodic(&mut self) -> Result<BackendResult, String> {
-        let (client, io) = self.camera.periodic();
+        let (client, io) = self.camera.periodic(Instant::now());
         Ok(BackendResult::client_io(client, io))
     }
+
+    /// Power off sequence finished, the camera is closed
+    pub fn shutdown_finished(&self) -> bool {
+        self.camera.shutdown_finished()
+    }
 }
 
 pub struct BackendResult {
@@ -153,4 +194,49 @@ impl BackendState {
             io_messages: Vec::new(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Commands of the user are ignored once the power off sequence is running
+fn user_command(message: &StateMessage) -> bool {
+    use StateMessage::*;
+
+    matches!(
+        message,
+        CameraParam(_) | ExposureMessage(_) | SequenceMessage(_) | FocuserMessage(_) |
+        StorageMessage(_) | SelectCamera(_) | RefreshCameras | PowerOff
+    )
+}
+
+/// Demo camera images are blurred according to the position of the demo focuser
+fn drivers(
+    imager: ImagerSource,
+    config: &ServiceConfig,
+) -> (Box<dyn ImagerDriver>, Option<Box<dyn FocuserDriver>>) {
+    match imager {
+        ImagerSource::Moravian => {
+            (Box::new(ccdi_imager_moravian::MoravianImagerDriver::new()), None)
+        },
+        ImagerSource::Playback(directory) => {
+            (Box::new(PlaybackImagerDriver::new(directory)), None)
+        },
+        ImagerSource::Demo => {
+            let focus = DemoFocus::new();
+            let driver = DemoImagerDriver::with_focus(focus.clone());
+
+            let driver = match config.demo_faults.clone() {
+                Some(script) => driver.with_faults(script),
+                None => driver,
+            };
+
+            let driver = match config.demo_seed {
+                Some(seed) => driver.with_seed(seed),
+                None => driver,
+            };
+
+            (
+                Box::new(driver),
+                Some(Box::new(DemoFocuserDriver::new(focus))),
+            )
+        }
+    }
+}
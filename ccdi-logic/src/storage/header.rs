@@ -0,0 +1,190 @@
+use std::time::{SystemTime, Duration};
+use chrono::{Utc, DateTime};
+
+use ccdi_common::RawImage;
+use ccdi_imager_interface::FrameType;
+
+// ============================================ PUBLIC =============================================
+
+/// Typed FITS keyword shared by all file formats
+#[derive(Clone, PartialEq, Debug)]
+pub struct HeaderKey {
+    pub name: &'static str,
+    pub value: KeyValue,
+    pub comment: &'static str,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum KeyValue {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+pub fn header_keys(image: &RawImage) -> Vec<HeaderKey> {
+    let mut keys = Vec::new();
+    exposure_keys(&mut keys, image);
+    camera_keys(&mut keys, image);
+    observatory_keys(&mut keys, image);
+    keys
+}
+
+/// Frame type names commonly recognized by calibration and stacking software
+pub fn image_type(frame_type: FrameType) -> &'static str {
+    match frame_type {
+        FrameType::Light => "Light Frame",
+        FrameType::Dark => "Dark Frame",
+        FrameType::Flat => "Flat Field",
+        FrameType::Bias => "Bias Frame",
+    }
+}
+
+/// Date format defined by the FITS standard, always UTC without time zone suffix
+pub fn format_iso8601(time: SystemTime) -> String {
+    let chrono_time: DateTime<Utc> = time.into();
+    format!("{}", chrono_time.format("%Y-%m-%dT%H:%M:%S%.3f"))
+}
+
+pub const SOFTWARE: &str = concat!("ccdi ", env!("CARGO_PKG_VERSION"));
+
+// =========================================== PRIVATE =============================================
+
+fn key(name: &'static str, value: KeyValue, comment: &'static str) -> HeaderKey {
+    HeaderKey { name, value, comment }
+}
+
+fn int(value: usize) -> KeyValue {
+    KeyValue::Int(value as i64)
+}
+
+fn text(value: &str) -> KeyValue {
+    KeyValue::Text(value.to_owned())
+}
+
+fn exposure_keys(keys: &mut Vec<HeaderKey>, image: &RawImage) {
+    let params = &image.params;
+    let end_time = params.start_time + Duration::from_secs_f64(params.time.max(0.0));
+    let start = format_iso8601(params.start_time);
+    let end = format_iso8601(end_time);
+
+    keys.push(key("DATE-OBS", KeyValue::Text(start), "UTC exposure start"));
+    keys.push(key("DATE-END", KeyValue::Text(end), "UTC exposure end"));
+    keys.push(key("EXPTIME", KeyValue::Float(params.time), "[s] Exposure time"));
+    keys.push(key("IMAGETYP", text(image_type(params.frame_type)), "Type of the frame"));
+
+    if let Some(object) = image.metadata.object.as_ref() {
+        keys.push(key("OBJECT", text(object), "Name of the imaged object"));
+    }
+
+    keys.push(key("GAIN", int(params.gain as usize), "Camera gain setting"));
+    keys.push(key("XBINNING", int(params.binning.x), "Horizontal binning factor"));
+    keys.push(key("YBINNING", int(params.binning.y), "Vertical binning factor"));
+    keys.push(key("XORGSUBF", int(params.area.x), "Subframe origin X in binned pixels"));
+    keys.push(key("YORGSUBF", int(params.area.y), "Subframe origin Y in binned pixels"));
+
+    if let Some(mode) = params.read_mode.as_ref() {
+        keys.push(key("READMODE", text(&mode.name), "Camera read mode"));
+    }
+
+    keys.push(key("SWCREATE", text(SOFTWARE), "Software that created the file"));
+}
+
+fn camera_keys(keys: &mut Vec<HeaderKey>, image: &RawImage) {
+    let metadata = &image.metadata;
+    let binning = image.params.binning;
+    let pixel_width = metadata.pixel_width as f64*binning.x as f64;
+    let pixel_height = metadata.pixel_height as f64*binning.y as f64;
+
+    keys.push(key("INSTRUME", text(&metadata.camera_name), "Camera name"));
+    keys.push(key("XPIXSZ", KeyValue::Float(pixel_width), "[um] Binned pixel width"));
+    keys.push(key("YPIXSZ", KeyValue::Float(pixel_height), "[um] Binned pixel height"));
+
+    if let Some(temperature) = metadata.temperature {
+        let value = KeyValue::Float(temperature as f64);
+        keys.push(key("CCD-TEMP", value, "[C] Chip temperature"));
+    }
+
+    let target = KeyValue::Float(metadata.target_temperature);
+    keys.push(key("SET-TEMP", target, "[C] Requested chip temperature"));
+
+    if let Some(pattern) = metadata.bayer_pattern.as_ref() {
+        keys.push(key("BAYERPAT", text(pattern.name()), "Color filter array pattern"));
+        keys.push(key("XBAYROFF", int(image.params.area.x % 2), "Bayer pattern X offset"));
+        keys.push(key("YBAYROFF", int(image.params.area.y % 2), "Bayer pattern Y offset"));
+    }
+
+    if let Some(filter) = metadata.filter.as_ref() {
+        keys.push(key("FILTER", text(filter), "Filter name"));
+    }
+}
+
+fn observatory_keys(keys: &mut Vec<HeaderKey>, image: &RawImage) {
+    let metadata = &image.metadata;
+    let optics = &metadata.optics;
+
+    if !optics.telescope.is_empty() {
+        keys.push(key("TELESCOP", text(&optics.telescope), "Telescope name"));
+    }
+
+    if let Some(focal_length) = optics.focal_length {
+        keys.push(key("FOCALLEN", KeyValue::Float(focal_length), "[mm] Focal length"));
+    }
+
+    if let Some(aperture) = optics.aperture {
+        keys.push(key("APTDIA", KeyValue::Float(aperture), "[mm] Aperture diameter"));
+    }
+
+    if let Some(site) = metadata.site.as_ref() {
+        let latitude = KeyValue::Float(site.latitude);
+        let longitude = KeyValue::Float(site.longitude);
+        keys.push(key("SITELAT", latitude, "[deg] Site latitude, north positive"));
+        keys.push(key("SITELONG", longitude, "[deg] Site longitude, east positive"));
+        keys.push(key("SITEELEV", KeyValue::Float(site.elevation), "[m] Site elevation"));
+    }
+
+    if let Some(coordinates) = metadata.coordinates {
+        let (ra, dec) = (KeyValue::Float(coordinates.ra), KeyValue::Float(coordinates.dec));
+        keys.push(key("RA", ra, "[deg] Right ascension of the center"));
+        keys.push(key("DEC", dec, "[deg] Declination of the center"));
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use ccdi_imager_interface::BayerPattern;
+
+    use super::*;
+
+    fn color_image(x: usize, y: usize, object: Option<&str>) -> RawImage {
+        let mut image = ccdi_common::test_image(2, 2, vec![0; 4]);
+        (image.params.area.x, image.params.area.y) = (x, y);
+        image.metadata.bayer_pattern = Some(BayerPattern::Rggb);
+        image.metadata.object = object.map(String::from);
+        image
+    }
+
+    fn value(keys: &[HeaderKey], name: &str) -> Option<KeyValue> {
+        keys.iter().find(|key| key.name == name).map(|key| key.value.clone())
+    }
+
+    #[test]
+    fn bayer_offset_follows_roi_origin() {
+        let image = color_image(5, 2, None);
+        let keys = header_keys(&image);
+
+        assert_eq!(value(&keys, "BAYERPAT"), Some(text("RGGB")));
+        assert_eq!(value(&keys, "XBAYROFF"), Some(KeyValue::Int(1)));
+        assert_eq!(value(&keys, "YBAYROFF"), Some(KeyValue::Int(0)));
+        assert_eq!(image.bayer_pattern(), Some(BayerPattern::Grbg));
+    }
+
+    #[test]
+    fn object_is_written_when_known() {
+        assert_eq!(value(&header_keys(&color_image(0, 0, None)), "OBJECT"), None);
+
+        let keys = header_keys(&color_image(0, 0, Some("M 31")));
+        assert_eq!(value(&keys, "OBJECT"), Some(text("M 31")));
+    }
+}
@@ -0,0 +1,79 @@
+use std::{fs::OpenOptions, io::Write, path::PathBuf, time::SystemTime};
+
+use ccdi_common::to_string;
+use log::error;
+
+use super::header::format_iso8601;
+
+// ============================================ PUBLIC =============================================
+
+/// Append-only log of every frame passed to the storage, one tab separated line per frame:
+/// time, status (`SAVED` or `FAILED`), file name and failure reason
+#[derive(Clone, Debug)]
+pub struct Journal {
+    path: Option<PathBuf>,
+}
+
+impl Journal {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    pub fn saved(&self, file_name: &str) {
+        self.append(&journal_line(SystemTime::now(), "SAVED", file_name, ""));
+    }
+
+    pub fn failed(&self, file_name: &str, reason: &str) {
+        self.append(&journal_line(SystemTime::now(), "FAILED", file_name, reason));
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+impl Journal {
+    fn append(&self, line: &str) {
+        if let Some(path) = self.path.as_ref() {
+            if let Err(message) = append_line(path, line) {
+                error!("Could not write storage journal {:?}: {}", path, message);
+            }
+        }
+    }
+}
+
+fn append_line(path: &PathBuf, line: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(to_string)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(to_string)?;
+
+    file.write_all(line.as_bytes()).map_err(to_string)?;
+    file.sync_data().map_err(to_string)
+}
+
+fn journal_line(time: SystemTime, status: &str, file_name: &str, reason: &str) -> String {
+    let clean = |text: &str| text.replace(['\t', '\n', '\r'], " ");
+
+    format!(
+        "{}\t{}\t{}\t{}\n", format_iso8601(time), status, clean(file_name), clean(reason)
+    )
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_line_format() {
+        assert_eq!(
+            journal_line(SystemTime::UNIX_EPOCH, "FAILED", "a/b.fits", "Disk\tfull\n"),
+            "1970-01-01T00:00:00.000\tFAILED\ta/b.fits\tDisk full \n"
+        );
+    }
+}
@@ -1,45 +1,93 @@
-use std::{sync::Arc, process::Command, path::PathBuf, collections::VecDeque};
+use std::{
+    sync::Arc, path::PathBuf, collections::VecDeque, time::{Instant, Duration}
+};
 
 use ccdi_common::{
-    StorageMessage, StateMessage, StorageState, StorageCapacity, to_string, StorageLogRecord,
-    RawImage, StorageLogStatus, StorageDetail
+    StorageMessage, StateMessage, StorageState, StorageLogRecord, RawImage, StorageLogStatus,
+    StorageDetail, SpaceLevel, XisfCompression
 };
-use log::debug;
+use log::{debug, error, warn};
 
-use crate::ServiceConfig;
+use crate::{ServiceConfig, config::journal_file_path};
 
-use self::save::save_fits_file;
+use self::{
+    save::file_extension,
+    naming::{NameTemplate, SequencePattern, SequenceCache},
+    journal::Journal,
+    writer::{WriteQueue, WriteJob},
+    space::check_storage,
+};
 
 mod save;
+mod naming;
+mod header;
+mod xisf;
+mod journal;
+mod writer;
+mod space;
+
+pub use self::naming::DEFAULT_TEMPLATE;
 
 // ============================================ PUBLIC =============================================
 
 pub struct Storage {
     config: Arc<ServiceConfig>,
+    /// Storage root images are currently written to
+    root: String,
     last_storage_state: StorageState,
+    template: NameTemplate,
+    /// Next sequence number for each rendered file name pattern
+    sequences: SequenceCache,
     counter: usize,
     storage_name: String,
+    /// Compression of XISF files, the configured one until changed by the user
+    compression: XisfCompression,
     storage_active: bool,
     details: VecDeque<StorageLogRecord>,
+    journal: Journal,
+    queue: WriteQueue,
+    last_depth: usize,
+    last_check: Option<Instant>,
 }
 
 impl Storage {
-    pub fn new(config: Arc<ServiceConfig>) -> Self {
-        Self {
+    pub fn new(config: Arc<ServiceConfig>) -> Result<Self, String> {
+        let template = NameTemplate::parse(&config.file_name_template).unwrap_or_else(|message| {
+            error!("Invalid file name template, using default: {}", message);
+            NameTemplate::parse(DEFAULT_TEMPLATE).expect("Default template must be valid")
+        });
+
+        let journal = Journal::new(journal_file_path().map_err(|message| {
+            error!("Storage journal not available: {}", message)
+        }).ok());
+
+        Ok(Self {
+            queue: WriteQueue::new(&config, journal.clone())?,
+            root: config.storage.clone(),
+            compression: config.xisf_compression,
             config,
             last_storage_state: StorageState::Unknown,
+            template,
+            sequences: SequenceCache::new(SEQUENCE_CACHE_SIZE),
             counter: 0,
             storage_name: String::from("default"),
             storage_active: false,
             details: VecDeque::new(),
-        }
+            journal,
+            last_depth: 0,
+            last_check: None,
+        })
     }
 
     pub fn process(&mut self, message: StorageMessage) -> Result<Vec<StateMessage>, String> {
         match message {
             StorageMessage::SetDirectory(name) => {
                 self.storage_name = name;
-                self.counter = 0;
+                self.sequences.clear();
+            },
+            StorageMessage::SetCompression(compression) => {
+                debug!("XISF compression set to {:?}", compression);
+                self.compression = compression;
             },
             StorageMessage::DisableStore => {
                 debug!("Storage disabled");
@@ -60,14 +108,34 @@ impl Storage {
     }
 
     pub fn periodic_tasks(&mut self) -> Result<Vec<StateMessage>, String> {
-        let storage_state = check_storage(&self.config.storage);
+        let mut messages = Vec::new();
+        let finished = self.queue.finished();
+        let depth = self.queue.depth();
+
+        if !finished.is_empty() || depth != self.last_depth {
+            finished.into_iter().for_each(|record| self.push_record(record));
+            self.last_depth = depth;
+            messages.push(StateMessage::UpdateStorageDetail(self.get_details()));
+        }
+
+        if self.last_check.map(|time| time.elapsed() >= CHECK_PERIOD).unwrap_or(true) {
+            self.last_check = Some(Instant::now());
+            let mut storage_state = check_storage(&self.root, &self.config.low_space);
+
+            if space_level(&storage_state) == Some(SpaceLevel::Critical) {
+                if let Some(secondary_state) = self.switch_to_secondary() {
+                    storage_state = secondary_state;
+                    messages.push(StateMessage::UpdateStorageDetail(self.get_details()));
+                }
+            }
 
-        if storage_state == self.last_storage_state {
-            Ok(vec![])
-        } else {
-            self.last_storage_state = storage_state.clone();
-            Ok(vec![StateMessage::UpdateStorageState(storage_state)])
+            if storage_state != self.last_storage_state {
+                self.last_storage_state = storage_state.clone();
+                messages.push(StateMessage::UpdateStorageState(storage_state));
+            }
         }
+
+        Ok(messages)
     }
 }
 
@@ -80,35 +148,96 @@ impl Storage {
             counter: self.counter,
             storage_log: self.details.iter().cloned().collect(),
             storage_enabled: self.storage_active,
+            storage_root: self.root.clone(),
             state: self.last_storage_state.clone(),
+            queue_depth: self.queue.depth(),
+            queue_capacity: self.queue.capacity(),
+            compression: self.compression,
         }
     }
 
-    fn current_dir(&self) -> Option<String> {
-        PathBuf::from(self.config.storage.clone())
-            .join(PathBuf::from(self.storage_name.clone()))
-            .to_str()
-            .map(|path| path.to_owned())
-    }
+    /// Assemble the name of the next file relative to the storage root,
+    /// files existing in the primary or fallback storage are never reused
+    fn next_file_name(&mut self, pattern: &SequencePattern) -> Result<String, String> {
+        let root = PathBuf::from(&self.root);
+        let fallback = self.config.fallback_storage.as_ref().map(PathBuf::from);
+        let key = format!("{}{{seq}}{}", pattern.prefix, pattern.suffix);
+        let extension = file_extension(self.config.file_format);
 
-    fn current_file_name(&self) -> Option<String> {
-        self.current_dir().map(|dir| format!("{}/{:05}.fits", dir, self.counter))
+        let mut sequence = match self.sequences.get(&key) {
+            Some(sequence) => sequence,
+            None => pattern.next_free_sequence(&root, extension)?,
+        };
+
+        // Files might have been created by someone else since the directory was scanned
+        let exists = |name: &str| Some(&root).into_iter()
+            .chain(fallback.as_ref())
+            .any(|root| root.join(name).exists());
+
+        while exists(&pattern.file_name(sequence, extension)) {
+            sequence += 1;
+        }
+
+        self.sequences.insert(key, sequence + 1);
+        self.counter = sequence + 1;
+        Ok(pattern.file_name(sequence, extension))
     }
 
     fn handle_image(&mut self, image: Arc<RawImage>) {
-        let result = match self.current_file_name() {
-            None => file_name_err(),
-            Some(file_name) => match save_fits_file(&image, &file_name) {
-                Ok(_) => ok_record(file_name),
-                Err(error) => StorageLogRecord {
-                    name: file_name,
-                    status: StorageLogStatus::Error(error)
-                }
-            }
+        let pattern = self.template.render(&image, &self.storage_name);
+
+        let file_name = match self.next_file_name(&pattern) {
+            Ok(file_name) => file_name,
+            Err(error) => {
+                self.journal.failed("", &error);
+                return self.push_record(file_name_err(error))
+            },
+        };
+
+        let job = WriteJob {
+            image,
+            root: PathBuf::from(&self.root),
+            file_name: file_name.clone(),
+            compression: self.compression,
         };
 
-        self.counter += 1;
-        self.details.push_back(result);
+        if let Err(error) = self.queue.push(job) {
+            self.journal.failed(&file_name, &error);
+            self.push_record(StorageLogRecord {
+                name: file_name,
+                status: StorageLogStatus::Error(error)
+            });
+        }
+    }
+
+    /// Continue on the secondary storage when it has enough free space,
+    /// returns its state when switched
+    fn switch_to_secondary(&mut self) -> Option<StorageState> {
+        let secondary = self.config.low_space.secondary_storage.as_ref()?;
+
+        if *secondary == self.root {
+            return None
+        }
+
+        let state = check_storage(secondary, &self.config.low_space);
+
+        match space_level(&state) {
+            Some(SpaceLevel::Sufficient) | Some(SpaceLevel::Low) => {
+                warn!("Storage {} is full, switching to {}", self.root, secondary);
+                self.root = secondary.clone();
+                self.sequences.clear();
+                self.push_record(StorageLogRecord {
+                    name: format!("Switched to storage {}", secondary),
+                    status: StorageLogStatus::Success,
+                });
+                Some(state)
+            },
+            Some(SpaceLevel::Critical) | None => None,
+        }
+    }
+
+    fn push_record(&mut self, record: StorageLogRecord) {
+        self.details.push_back(record);
 
         while self.details.len() > 20 {
             self.details.pop_front();
@@ -116,80 +245,20 @@ impl Storage {
     }
 }
 
-fn ok_record(name: String) -> StorageLogRecord {
-    StorageLogRecord { name, status: StorageLogStatus::Success }
-}
+const CHECK_PERIOD: Duration = Duration::from_secs(1);
+/// File name patterns whose next sequence number is remembered
+const SEQUENCE_CACHE_SIZE: usize = 64;
 
-fn file_name_err() -> StorageLogRecord {
+fn file_name_err(error: String) -> StorageLogRecord {
     StorageLogRecord {
         name: String::from("Could not assemble file name"),
-        status: StorageLogStatus::Error(String::new())
+        status: StorageLogStatus::Error(error)
     }
 }
 
-fn check_storage(path: &str) -> StorageState {
-    match Command::new("df").args([path]).output() {
-        Ok(output) => match output.status.code() {
-            Some(0) => match String::from_utf8(output.stdout) {
-                Ok(stdout) => match parse_free_space(&stdout) {
-                    Ok(details) => StorageState::Available(details),
-                    Err(error) => StorageState::Error(error),
-                },
-                Err(error) => StorageState::Error(
-                    format!("Could not parse stdout as utf8: {:?}", error)
-                ),
-            },
-            Some(code) => StorageState::Error(
-                format!(
-                    "Storage check returned error code: {:?} {:?}",
-                    code, String::from_utf8_lossy(&output.stderr)
-                )
-            ),
-            status => StorageState::Error(
-                format!("Storage check did not return successfully: {:?}", status)
-            )
-        },
-        Err(error) => StorageState::Error(
-            format!("Storage check call failed: {:?}", error)
-        ),
+fn space_level(state: &StorageState) -> Option<SpaceLevel> {
+    match state {
+        StorageState::Available(capacity) => Some(capacity.level),
+        StorageState::Unknown | StorageState::Error(_) => None,
     }
 }
-
-fn parse_free_space(stdout: &str) -> Result<StorageCapacity, String> {
-    let line = stdout.lines().nth(1).ok_or("df output second line missing")?;
-    let total_gigabytes = kb_to_gb(parse_nth_token(line, 1)?);
-    let free_gigabytes = kb_to_gb(parse_nth_token(line, 3)?);
-    Ok(StorageCapacity{total_gigabytes, free_gigabytes})
-}
-
-fn parse_nth_token(line: &str, index: usize) -> Result<f64, String> {
-    let token = line.split_whitespace()
-        .nth(index)
-        .ok_or(format!("{}th token not present in '{}'", index, line))?;
-
-    token.parse::<f64>().map_err(to_string)
-}
-
-fn kb_to_gb(kilobytes: f64) -> f64 {
-    kilobytes/1024.0/1024.0
-}
-
-// ============================================= TEST ==============================================
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use indoc::indoc;
-
-    const TEST_DF_OUTPUT: &str = indoc!{"
-        Filesystem           1K-blocks  Used      Available  Use% Mounted on
-        /dev/mapper/luks-a6e 1967861712 111750632 1756075448   6% /media/x/759
-    "};
-
-    #[test]
-    fn parse_df_output() {
-        let details = parse_free_space(TEST_DF_OUTPUT).expect("Parse details failed");
-        assert_eq!(details.total_gigabytes, 1967861712.0/1024.0/1024.0);
-        assert_eq!(details.free_gigabytes, 1756075448.0/1024.0/1024.0);
-    }
-}
\ No newline at end of file
@@ -0,0 +1,367 @@
+use std::{path::{Path, PathBuf}, collections::{HashMap, VecDeque}};
+
+use chrono::{DateTime, Utc};
+use ccdi_common::{RawImage, to_string};
+use ccdi_imager_interface::FrameType;
+
+// ============================================ PUBLIC =============================================
+
+/// File name template relative to the storage root, tokens in curly braces are replaced by
+/// image properties: `{date}`, `{time}`, `{target}`, `{type}`, `{exposure}`, `{gain}`,
+/// `{temperature}`, `{filter}` and `{seq}` or `{seq:N}` for a sequence number padded
+/// to N digits. Sequence number is appended when the template does not contain it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct NameTemplate {
+    parts: Vec<Part>,
+}
+
+impl NameTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_owned()));
+            }
+
+            let end = rest[start..].find('}')
+                .ok_or(format!("Unclosed token in file name template '{}'", template))?;
+
+            parts.push(Part::Token(parse_token(&rest[start + 1..start + end])?));
+            rest = &rest[start + end + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+
+        match parts.iter().filter(|part| part.is_sequence()).count() {
+            0 => parts.extend([Part::Text(String::from("_")), Part::Token(Token::Sequence(5))]),
+            1 => {},
+            _ => return Err(format!("Multiple sequence tokens in template '{}'", template)),
+        }
+
+        let template = Self { parts };
+
+        match template.sequence_in_directory() {
+            true => Err(String::from("Sequence number must be part of the file name")),
+            false => Ok(template),
+        }
+    }
+
+    /// Render the template for the given image, the sequence number is left out
+    pub fn render(&self, image: &RawImage, target: &str) -> SequencePattern {
+        let mut prefix = String::new();
+        let mut suffix = String::new();
+        let mut width = 0;
+
+        for part in self.parts.iter() {
+            let output = match width { 0 => &mut prefix, _ => &mut suffix };
+
+            match part {
+                Part::Text(text) => output.push_str(text),
+                Part::Token(Token::Sequence(digits)) => width = (*digits).max(1),
+                Part::Token(token) => output.push_str(
+                    &sanitize(&render_token(token, image, target))
+                ),
+            }
+        }
+
+        SequencePattern { prefix, suffix, width }
+    }
+}
+
+/// Rendered file name with a sequence number placeholder between prefix and suffix
+#[derive(Clone, PartialEq, Debug)]
+pub struct SequencePattern {
+    pub prefix: String,
+    pub suffix: String,
+    pub width: usize,
+}
+
+impl SequencePattern {
+    pub fn file_name(&self, sequence: usize, extension: &str) -> String {
+        format!(
+            "{}{:0width$}{}.{}", self.prefix, sequence, self.suffix, extension, width = self.width
+        )
+    }
+
+    /// Sequence number following the highest one already present in the directory
+    pub fn next_free_sequence(&self, root: &Path, extension: &str) -> Result<usize, String> {
+        let path = root.join(&self.prefix);
+
+        let (dir, name_prefix) = match self.prefix.ends_with('/') || self.prefix.is_empty() {
+            true => (path, String::new()),
+            false => (
+                path.parent().map(|dir| dir.to_path_buf()).unwrap_or(PathBuf::from(root)),
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            ),
+        };
+
+        if !dir.exists() {
+            return Ok(0)
+        }
+
+        let name_suffix = format!("{}.{}", self.suffix, extension);
+
+        let highest = std::fs::read_dir(&dir)
+            .map_err(to_string)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str().map(|name| name.to_owned()))
+            .filter_map(|name| parse_sequence(&name, &name_prefix, &name_suffix))
+            .max();
+
+        Ok(highest.map(|value| value + 1).unwrap_or(0))
+    }
+}
+
+/// Next sequence numbers of recently used file name patterns, the oldest pattern is
+/// evicted when the capacity is reached and found again by scanning its directory
+pub struct SequenceCache {
+    capacity: usize,
+    sequences: HashMap<String, usize>,
+    order: VecDeque<String>,
+}
+
+impl SequenceCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, sequences: HashMap::new(), order: VecDeque::new() }
+    }
+
+    pub fn get(&self, key: &str) -> Option<usize> {
+        self.sequences.get(key).copied()
+    }
+
+    pub fn insert(&mut self, key: String, sequence: usize) {
+        if !self.sequences.contains_key(&key) {
+            while self.order.len() >= self.capacity.max(1) {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.sequences.remove(&oldest);
+                }
+            }
+
+            self.order.push_back(key.clone());
+        }
+
+        self.sequences.insert(key, sequence);
+    }
+
+    pub fn clear(&mut self) {
+        self.sequences.clear();
+        self.order.clear();
+    }
+}
+
+/// Images are grouped by target and frame type, the sequence number is the file name
+pub const DEFAULT_TEMPLATE: &str = "{target}/{type}/{seq}";
+
+// =========================================== PRIVATE =============================================
+
+#[derive(Clone, PartialEq, Debug)]
+enum Part {
+    Text(String),
+    Token(Token),
+}
+
+impl Part {
+    fn is_sequence(&self) -> bool {
+        matches!(self, Part::Token(Token::Sequence(_)))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Token {
+    Date,
+    Time,
+    Target,
+    FrameType,
+    Exposure,
+    Gain,
+    Temperature,
+    Filter,
+    Sequence(usize),
+}
+
+impl NameTemplate {
+    fn sequence_in_directory(&self) -> bool {
+        self.parts.iter()
+            .skip_while(|part| !part.is_sequence())
+            .any(|part| matches!(part, Part::Text(text) if text.contains('/')))
+    }
+}
+
+fn parse_token(token: &str) -> Result<Token, String> {
+    Ok(match token {
+        "date" => Token::Date,
+        "time" => Token::Time,
+        "target" => Token::Target,
+        "type" => Token::FrameType,
+        "exposure" => Token::Exposure,
+        "gain" => Token::Gain,
+        "temperature" => Token::Temperature,
+        "filter" => Token::Filter,
+        "seq" => Token::Sequence(5),
+        other => match other.strip_prefix("seq:") {
+            Some(digits) => Token::Sequence(
+                digits.parse::<usize>()
+                    .map_err(|_| format!("Invalid sequence width in token '{}'", other))?
+            ),
+            None => return Err(format!("Unknown file name token '{}'", other)),
+        }
+    })
+}
+
+fn render_token(token: &Token, image: &RawImage, target: &str) -> String {
+    let start_time: DateTime<Utc> = image.params.start_time.into();
+
+    match token {
+        Token::Date => start_time.format("%Y-%m-%d").to_string(),
+        Token::Time => start_time.format("%H%M%S").to_string(),
+        Token::Target => target.to_owned(),
+        Token::FrameType => frame_type_name(image.params.frame_type).to_owned(),
+        Token::Exposure => format!("{}s", image.params.time),
+        Token::Gain => image.params.gain.to_string(),
+        Token::Temperature => image.metadata.temperature
+            .map(|temperature| format!("{:.1}C", temperature))
+            .unwrap_or(String::from("NA")),
+        Token::Filter => image.metadata.filter.clone().unwrap_or(String::from("NA")),
+        Token::Sequence(_) => String::new(),
+    }
+}
+
+fn frame_type_name(frame_type: FrameType) -> &'static str {
+    match frame_type {
+        FrameType::Light => "light",
+        FrameType::Dark => "dark",
+        FrameType::Flat => "flat",
+        FrameType::Bias => "bias",
+    }
+}
+
+/// Token values must not create new directory levels nor name the current or the parent
+/// directory, control characters are replaced too
+fn sanitize(value: &str) -> String {
+    let value: String = value.chars()
+        .map(|c| match c == '/' || c == '\\' || c.is_control() {
+            true => '_',
+            false => c,
+        })
+        .collect();
+
+    match value.chars().all(|c| c == '.') {
+        true => value.replace('.', "_"),
+        false => value,
+    }
+}
+
+fn parse_sequence(name: &str, prefix: &str, suffix: &str) -> Option<usize> {
+    let digits = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+
+    match !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        true => digits.parse::<usize>().ok(),
+        false => None,
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, Duration};
+
+    use crate::test_util::TestDir;
+
+    use super::*;
+
+    fn dark_image() -> RawImage {
+        let mut image = ccdi_common::test_image(2, 2, vec![0; 4]);
+        image.params.gain = 2000;
+        image.params.time = 120.0;
+        image.params.frame_type = FrameType::Dark;
+        image.params.start_time = SystemTime::UNIX_EPOCH + Duration::from_secs(86400 + 3661);
+        image.metadata.temperature = Some(-10.0);
+        image
+    }
+
+    #[test]
+    fn render_all_tokens() {
+        let template = NameTemplate::parse(
+            "{target}/{date}/{type}_{time}_{exposure}_{gain}_{temperature}_{filter}_{seq:3}"
+        ).expect("Valid template");
+
+        let pattern = template.render(&dark_image(), "m31");
+
+        assert_eq!(
+            pattern.file_name(7, "fits"),
+            "m31/1970-01-02/dark_010101_120s_2000_-10.0C_NA_007.fits"
+        );
+    }
+
+    #[test]
+    fn sequence_appended_when_missing() {
+        let template = NameTemplate::parse("{target}/{type}").expect("Valid template");
+        let pattern = template.render(&dark_image(), "a/b");
+        assert_eq!(pattern.file_name(1, "fits"), "a_b/dark_00001.fits");
+    }
+
+    #[test]
+    fn values_stay_inside_storage_directory() {
+        let template = NameTemplate::parse("{target}/{filter}/{type}").expect("Valid template");
+        let mut image = dark_image();
+        image.metadata.filter = Some(String::from("."));
+
+        let pattern = template.render(&image, "..");
+        assert_eq!(pattern.file_name(1, "fits"), "__/_/dark_00001.fits");
+
+        let pattern = template.render(&image, "m31\n..");
+        assert_eq!(pattern.file_name(1, "fits"), "m31_../_/dark_00001.fits");
+    }
+
+    #[test]
+    fn invalid_templates_rejected() {
+        assert!(NameTemplate::parse("{unknown}").is_err());
+        assert!(NameTemplate::parse("{seq}/{seq}").is_err());
+        assert!(NameTemplate::parse("{seq}/{type}").is_err());
+        assert!(NameTemplate::parse("{target").is_err());
+    }
+
+    #[test]
+    fn parse_sequence_from_file_name() {
+        assert_eq!(parse_sequence("dark_00012.fits", "dark_", ".fits"), Some(12));
+        assert_eq!(parse_sequence("dark_x.fits", "dark_", ".fits"), None);
+        assert_eq!(parse_sequence("flat_00012.fits", "dark_", ".fits"), None);
+    }
+
+    #[test]
+    fn next_free_sequence_follows_existing_files() {
+        let dir = TestDir::new("naming-sequence");
+        let pattern = NameTemplate::parse("{target}/{type}_{seq:3}").expect("Valid template")
+            .render(&dark_image(), "m31");
+
+        assert_eq!(pattern.next_free_sequence(dir.path(), "fits"), Ok(0));
+
+        std::fs::create_dir_all(dir.join("m31")).unwrap();
+        ["dark_003.fits", "dark_011.fits", "dark_012.xisf", "flat_020.fits", "dark_x.fits"]
+            .iter()
+            .for_each(|name| std::fs::write(dir.join("m31").join(name), b"").unwrap());
+
+        assert_eq!(pattern.next_free_sequence(dir.path(), "fits"), Ok(12));
+        assert_eq!(pattern.next_free_sequence(dir.path(), "xisf"), Ok(13));
+    }
+
+    #[test]
+    fn sequence_cache_evicts_oldest_pattern() {
+        let mut cache = SequenceCache::new(2);
+        cache.insert(String::from("a"), 1);
+        cache.insert(String::from("b"), 2);
+        cache.insert(String::from("a"), 3);
+        cache.insert(String::from("c"), 4);
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(2));
+        assert_eq!(cache.get("c"), Some(4));
+    }
+}
@@ -1,43 +1,162 @@
-use std::path::PathBuf;
-use std::time::SystemTime;
-use chrono::{Utc, DateTime};
+use std::{fs::File, io::ErrorKind, path::{Path, PathBuf}};
 
-use ccdi_common::{RawImage, to_string};
-use fitsio::FitsFile;
-use fitsio::images::{ImageDescription, ImageType};
+use ccdi_common::{RawImage, XisfCompression, to_string};
+
+use crate::config::FileFormat;
+
+use super::header::header_keys;
+use super::xisf::save_xisf_file;
 
 // ============================================ PUBLIC =============================================
 
-pub fn save_fits_file(image: &RawImage, file_name: &str) -> Result<(), String> {
+/// Save image in the configured file format, existing files are never overwritten.
+/// Image is written to a temporary file first and linked to its name after it is synced
+/// to the disk, so that a complete file or no file is present after a power loss.
+pub fn save_image_file(
+    image: &RawImage,
+    file_name: &str,
+    format: FileFormat,
+    compression: XisfCompression,
+) -> Result<(), String> {
     let path = PathBuf::from(file_name);
-    let prefix = path.parent().ok_or(format!("Invalid path parent"))?;
+    let prefix = path.parent().ok_or(String::from("Invalid path parent"))?;
     std::fs::create_dir_all(prefix).map_err(to_string)?;
 
-    let description = ImageDescription {
-        data_type: ImageType::UnsignedShort,
-        dimensions: &[image.params.area.height, image.params.area.width],
-    };
+    let keys = header_keys(image);
+    let temp_path = temp_file_path(&path);
+    let temp_name = temp_path.to_str().ok_or(String::from("Invalid temporary file name"))?;
 
-    let mut fitsfile = FitsFile::create(&file_name)
-        .with_custom_primary(&description)
-        .overwrite()
-        .open()
-        .map_err(to_string)?;
+    // Leftover of an interrupted write
+    if temp_path.exists() {
+        std::fs::remove_file(&temp_path).map_err(to_string)?;
+    }
 
-    let hdu = fitsfile.primary_hdu().map_err(to_string)?;
+    let result = match format {
+        FileFormat::Fits => fits::save_fits_file(image, temp_name, &keys),
+        FileFormat::Xisf => save_xisf_file(image, &temp_path, &keys, compression),
+    }.and_then(|_| sync_file(&temp_path)).and_then(|_| publish(&temp_path, &path));
 
-    let date_obs = format_iso8601(image.params.start_time);
-
-    hdu.write_key(&mut fitsfile, "DATE-OBS", date_obs).map_err(to_string)?;
-    hdu.write_key(&mut fitsfile, "EXPTIME", image.params.time.to_string()).map_err(to_string)?;
-    hdu.write_image(&mut fitsfile, &image.data).map_err(to_string)?;
+    let _ = std::fs::remove_file(&temp_path);
+    result.and_then(|_| sync_directory(prefix))
+}
 
-    Ok(())
+/// File name extension for the given format
+pub fn file_extension(format: FileFormat) -> &'static str {
+    match format {
+        FileFormat::Fits => "fits",
+        FileFormat::Xisf => "xisf",
+    }
 }
 
 // =========================================== PRIVATE =============================================
 
-fn format_iso8601(time: SystemTime) -> String {
-    let chrono_time: DateTime<Utc> = time.into();
-    format!("{}", chrono_time.format("%+"))
-}
\ No newline at end of file
+fn temp_file_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Unlike a rename, creating the link fails when the target exists, even when the file
+/// was created by another writer after the image was written
+fn publish(temp_path: &Path, path: &Path) -> Result<(), String> {
+    std::fs::hard_link(temp_path, path).map_err(|error| match error.kind() {
+        ErrorKind::AlreadyExists => format!("File {} already exists", path.display()),
+        _ => error.to_string(),
+    })
+}
+
+fn sync_file(path: &Path) -> Result<(), String> {
+    File::open(path).and_then(|file| file.sync_all()).map_err(to_string)
+}
+
+/// Make the new link durable, directories can not be opened for syncing on all platforms
+fn sync_directory(path: &Path) -> Result<(), String> {
+    match cfg!(unix) {
+        true => sync_file(path),
+        false => Ok(()),
+    }
+}
+
+#[cfg(feature = "fits")]
+mod fits {
+    use ccdi_common::{RawImage, to_string};
+    use fitsio::FitsFile;
+    use fitsio::hdu::FitsHdu;
+    use fitsio::images::{ImageDescription, ImageType};
+
+    use crate::storage::header::{HeaderKey, KeyValue};
+
+    pub fn save_fits_file(
+        image: &RawImage,
+        file_name: &str,
+        keys: &[HeaderKey]
+    ) -> Result<(), String> {
+        let description = ImageDescription {
+            data_type: ImageType::UnsignedShort,
+            dimensions: &[image.params.area.height, image.params.area.width],
+        };
+
+        let mut fitsfile = FitsFile::create(file_name)
+            .with_custom_primary(&description)
+            .open()
+            .map_err(to_string)?;
+
+        let hdu = fitsfile.primary_hdu().map_err(to_string)?;
+
+        for key in keys {
+            write_key(&hdu, &mut fitsfile, key).map_err(to_string)?;
+        }
+
+        hdu.write_image(&mut fitsfile, &image.data).map_err(to_string)?;
+        Ok(())
+    }
+
+    fn write_key(
+        hdu: &FitsHdu,
+        file: &mut FitsFile,
+        key: &HeaderKey
+    ) -> Result<(), fitsio::errors::Error> {
+        match &key.value {
+            KeyValue::Int(value) => hdu.write_key(file, key.name, (*value, key.comment)),
+            KeyValue::Float(value) => hdu.write_key(file, key.name, (*value, key.comment)),
+            KeyValue::Text(value) => hdu.write_key(file, key.name, (value.as_str(), key.comment)),
+        }
+    }
+}
+
+#[cfg(not(feature = "fits"))]
+mod fits {
+    use ccdi_common::RawImage;
+
+    use crate::storage::header::HeaderKey;
+
+    pub fn save_fits_file(_: &RawImage, _: &str, _: &[HeaderKey]) -> Result<(), String> {
+        Err(String::from("FITS support not compiled in, enable the 'fits' feature"))
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use ccdi_common::test_image;
+
+    use crate::test_util::TestDir;
+
+    use super::*;
+
+    #[test]
+    fn existing_file_is_kept() {
+        let dir = TestDir::new("save-existing");
+        let path = dir.join("light_00001.xisf");
+        std::fs::write(&path, b"kept").unwrap();
+
+        let image = test_image(2, 2, vec![1, 2, 3, 4]);
+        let name = path.to_str().unwrap();
+        let result = save_image_file(&image, name, FileFormat::Xisf, XisfCompression::None);
+
+        assert_eq!(result, Err(format!("File {} already exists", name)));
+        assert_eq!(std::fs::read(&path).unwrap(), b"kept");
+        assert!(!temp_file_path(&path).exists());
+    }
+}
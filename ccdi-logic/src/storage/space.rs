@@ -0,0 +1,114 @@
+use ccdi_common::{StorageState, StorageCapacity, SpaceLevel};
+
+use crate::config::LowSpaceConfig;
+
+// ============================================ PUBLIC =============================================
+
+/// File system statistics needed to determine the storage capacity
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FsStats {
+    pub fragment_size: u64,
+    pub blocks: u64,
+    /// Blocks available to unprivileged users
+    pub available_blocks: u64,
+}
+
+pub fn check_storage(path: &str, thresholds: &LowSpaceConfig) -> StorageState {
+    match read_fs_stats(path) {
+        Ok(stats) => StorageState::Available(storage_capacity(stats, thresholds)),
+        Err(error) => StorageState::Error(error),
+    }
+}
+
+pub fn storage_capacity(stats: FsStats, thresholds: &LowSpaceConfig) -> StorageCapacity {
+    let total_gigabytes = bytes_to_gb(stats.blocks.saturating_mul(stats.fragment_size));
+    let free_gigabytes = bytes_to_gb(stats.available_blocks.saturating_mul(stats.fragment_size));
+
+    let level = if free_gigabytes < thresholds.critical_gigabytes {
+        SpaceLevel::Critical
+    } else if free_gigabytes < thresholds.warning_gigabytes {
+        SpaceLevel::Low
+    } else {
+        SpaceLevel::Sufficient
+    };
+
+    StorageCapacity { total_gigabytes, free_gigabytes, level }
+}
+
+// =========================================== PRIVATE =============================================
+
+#[cfg(unix)]
+fn read_fs_stats(path: &str) -> Result<FsStats, String> {
+    use std::ffi::CString;
+    use ccdi_common::to_string;
+
+    let c_path = CString::new(path).map_err(to_string)?;
+    // Safety: statvfs only writes into the provided structure
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    match unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } {
+        0 => Ok(FsStats {
+            fragment_size: stats.f_frsize as u64,
+            blocks: stats.f_blocks as u64,
+            available_blocks: stats.f_bavail as u64,
+        }),
+        _ => Err(format!(
+            "Storage check of '{}' failed: {}", path, std::io::Error::last_os_error()
+        )),
+    }
+}
+
+#[cfg(not(unix))]
+fn read_fs_stats(_path: &str) -> Result<FsStats, String> {
+    Err(String::from("Storage check is not supported on this platform"))
+}
+
+fn bytes_to_gb(bytes: u64) -> f64 {
+    bytes as f64/1024.0/1024.0/1024.0
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024*1024*1024;
+
+    fn stats(total_gigabytes: u64, free_gigabytes: u64) -> FsStats {
+        FsStats {
+            fragment_size: 4096,
+            blocks: total_gigabytes*GB/4096,
+            available_blocks: free_gigabytes*GB/4096,
+        }
+    }
+
+    #[test]
+    fn capacity_from_fs_stats() {
+        let capacity = storage_capacity(stats(64, 20), &LowSpaceConfig::default());
+        assert_eq!(capacity.total_gigabytes, 64.0);
+        assert_eq!(capacity.free_gigabytes, 20.0);
+        assert_eq!(capacity.level, SpaceLevel::Sufficient);
+    }
+
+    #[test]
+    fn space_levels_follow_thresholds() {
+        let thresholds = LowSpaceConfig {
+            warning_gigabytes: 10.0,
+            critical_gigabytes: 2.0,
+            secondary_storage: None
+        };
+
+        let level = |free| storage_capacity(stats(64, free), &thresholds).level;
+        assert_eq!(level(10), SpaceLevel::Sufficient);
+        assert_eq!(level(9), SpaceLevel::Low);
+        assert_eq!(level(2), SpaceLevel::Low);
+        assert_eq!(level(1), SpaceLevel::Critical);
+    }
+
+    #[test]
+    fn missing_storage_reports_error() {
+        let state = check_storage("/nonexistent/ccdi/storage", &LowSpaceConfig::default());
+        assert!(matches!(state, StorageState::Error(_)));
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    path::PathBuf, thread, collections::VecDeque,
+    sync::{Arc, atomic::{AtomicUsize, Ordering}},
+    sync::mpsc::{sync_channel, channel, SyncSender, Receiver, Sender, TrySendError},
+};
+
+use ccdi_common::{RawImage, StorageLogRecord, StorageLogStatus, XisfCompression, log_err};
+use log::warn;
+
+use crate::{ServiceConfig, config::FileFormat};
+
+use super::{journal::Journal, save::save_image_file};
+
+// ============================================ PUBLIC =============================================
+
+pub struct WriteJob {
+    pub image: Arc<RawImage>,
+    pub root: PathBuf,
+    /// File name relative to the storage root
+    pub file_name: String,
+    pub compression: XisfCompression,
+}
+
+/// Queue of images written by a background thread, so that a slow storage does not
+/// block the storage thread and the number of pending images stays visible. Images
+/// above the capacity wait in memory until the writer catches up.
+pub struct WriteQueue {
+    jobs: SyncSender<WriteJob>,
+    results: Receiver<StorageLogRecord>,
+    /// Images sent to the writer thread and not written yet
+    depth: Arc<AtomicUsize>,
+    /// Images waiting for a free place in the queue
+    backlog: VecDeque<WriteJob>,
+    capacity: usize,
+}
+
+impl WriteQueue {
+    pub fn new(config: &ServiceConfig, journal: Journal) -> Result<Self, String> {
+        let capacity = config.write_queue_size.max(1);
+        // One more image is held by the writer thread while it is being written
+        let (jobs, jobs_rx) = sync_channel::<WriteJob>(capacity - 1);
+        let (results_tx, results) = channel();
+        let depth = Arc::new(AtomicUsize::new(0));
+        let writer = ImageWriter::new(config, journal);
+        let thread_depth = depth.clone();
+
+        thread::Builder::new()
+            .name("storage-writer".to_string())
+            .spawn(move || run_writer(writer, jobs_rx, results_tx, thread_depth))
+            .map_err(|err| format!("{:?}", err))?;
+
+        Ok(Self { jobs, results, depth, backlog: VecDeque::new(), capacity })
+    }
+
+    /// Enqueue the image, the image is kept in the backlog when the queue is full
+    pub fn push(&mut self, job: WriteJob) -> Result<(), String> {
+        if self.backlog.is_empty() && self.depth() >= self.capacity {
+            warn!("Write queue full, images are kept in memory until written");
+        }
+
+        self.backlog.push_back(job);
+        self.send_backlog()
+    }
+
+    /// Records of images written since the last call, waiting images are moved to the queue
+    pub fn finished(&mut self) -> Vec<StorageLogRecord> {
+        log_err("Send write backlog", self.send_backlog());
+        self.results.try_iter().collect()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::SeqCst) + self.backlog.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+impl WriteQueue {
+    fn send_backlog(&mut self) -> Result<(), String> {
+        while let Some(job) = self.backlog.pop_front() {
+            self.depth.fetch_add(1, Ordering::SeqCst);
+
+            if let Err(error) = self.jobs.try_send(job) {
+                self.depth.fetch_sub(1, Ordering::SeqCst);
+
+                match error {
+                    TrySendError::Full(job) => self.backlog.push_front(job),
+                    TrySendError::Disconnected(_) => {
+                        self.backlog.clear();
+                        return Err(String::from("Writer thread not running"))
+                    },
+                }
+
+                break
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes images to the job storage root, falling back to the secondary directory on failure
+struct ImageWriter {
+    fallback: Option<PathBuf>,
+    format: FileFormat,
+    journal: Journal,
+}
+
+impl ImageWriter {
+    fn new(config: &ServiceConfig, journal: Journal) -> Self {
+        Self {
+            fallback: config.fallback_storage.as_ref().map(PathBuf::from),
+            format: config.file_format,
+            journal,
+        }
+    }
+
+    fn write(&self, job: &WriteJob) -> StorageLogRecord {
+        let mut errors = Vec::new();
+
+        for root in Some(&job.root).into_iter().chain(self.fallback.as_ref()) {
+            let path = root.join(&job.file_name).to_string_lossy().to_string();
+            match save_image_file(&job.image, &path, self.format, job.compression) {
+                Ok(_) => {
+                    self.journal.saved(&path);
+                    return StorageLogRecord { name: path, status: StorageLogStatus::Success }
+                },
+                Err(error) => errors.push(format!("{}: {}", path, error)),
+            }
+        }
+
+        let reason = errors.join("; ");
+        self.journal.failed(&job.file_name, &reason);
+
+        StorageLogRecord {
+            name: job.file_name.clone(),
+            status: StorageLogStatus::Error(reason),
+        }
+    }
+}
+
+fn run_writer(
+    writer: ImageWriter,
+    jobs: Receiver<WriteJob>,
+    results: Sender<StorageLogRecord>,
+    depth: Arc<AtomicUsize>,
+) {
+    // Exits when the queue is dropped
+    for job in jobs.iter() {
+        let record = writer.write(&job);
+        depth.fetch_sub(1, Ordering::SeqCst);
+        log_err("Send write result", results.send(record));
+    }
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::{Duration, Instant}};
+
+    use ccdi_common::test_image;
+
+    use crate::test_util::TestDir;
+
+    use super::*;
+
+    fn test_job(root: &Path, file_name: &str) -> WriteJob {
+        WriteJob {
+            image: Arc::new(test_image(2, 2, vec![1, 2, 3, 4])),
+            root: root.to_path_buf(),
+            file_name: String::from(file_name),
+            compression: XisfCompression::None,
+        }
+    }
+
+    #[test]
+    fn failed_write_falls_back_to_secondary_directory() {
+        let dir = TestDir::new("writer-fallback");
+        // Primary storage path is a regular file, directories can not be created in it
+        let primary = dir.join("primary");
+        std::fs::write(&primary, b"").unwrap();
+        let journal_path = dir.join("journal.log");
+
+        let config = ServiceConfig {
+            fallback_storage: Some(dir.join("fallback").to_string_lossy().to_string()),
+            file_format: FileFormat::Xisf,
+            ..Default::default()
+        };
+
+        let writer = ImageWriter::new(&config, Journal::new(Some(journal_path.clone())));
+        let record = writer.write(&test_job(&primary, "m31/light_00001.xisf"));
+
+        assert_eq!(record.status, StorageLogStatus::Success);
+        assert!(dir.join("fallback/m31/light_00001.xisf").exists());
+        assert!(!dir.join("fallback/m31/light_00001.xisf.part").exists());
+
+        let record = writer.write(&test_job(&primary, "m31/light_00001.xisf"));
+        assert!(matches!(record.status, StorageLogStatus::Error(_)));
+
+        let journal = std::fs::read_to_string(&journal_path).unwrap();
+        let statuses = journal.lines()
+            .map(|line| line.split('\t').nth(1).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(statuses, vec!["SAVED", "FAILED"]);
+    }
+
+    #[test]
+    fn images_above_capacity_are_written() {
+        let dir = TestDir::new("writer-backlog");
+        let config = ServiceConfig {
+            write_queue_size: 1, file_format: FileFormat::Xisf, ..Default::default()
+        };
+        let mut queue = WriteQueue::new(&config, Journal::new(None)).unwrap();
+
+        for index in 0..5 {
+            queue.push(test_job(dir.path(), &format!("light_{}.xisf", index))).unwrap();
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut records = Vec::new();
+
+        while records.len() < 5 && Instant::now() < deadline {
+            records.extend(queue.finished());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(records.len(), 5);
+        assert!(records.iter().all(|record| record.status == StorageLogStatus::Success));
+        assert_eq!(queue.depth(), 0);
+    }
+}
@@ -0,0 +1,263 @@
+use std::{fs::OpenOptions, io::Write, path::Path, time::SystemTime};
+
+use ccdi_common::{RawImage, XisfCompression, to_string};
+use ccdi_imager_interface::FrameType;
+use flate2::{write::ZlibEncoder, Compression};
+
+use super::header::{HeaderKey, KeyValue, format_iso8601, SOFTWARE};
+
+// ============================================ PUBLIC =============================================
+
+/// Save image as a monolithic XISF 1.0 file, header keys are mirrored as FITS keywords
+pub fn save_xisf_file(
+    image: &RawImage,
+    path: &Path,
+    keys: &[HeaderKey],
+    compression: XisfCompression,
+) -> Result<(), String> {
+    let content = encode_xisf(image, keys, compression, SystemTime::now())?;
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(to_string)?;
+
+    file.write_all(&content).map_err(to_string)
+}
+
+// =========================================== PRIVATE =============================================
+
+const SIGNATURE: &[u8] = b"XISF0100";
+/// Signature, header length and reserved field
+const PREAMBLE_SIZE: usize = 16;
+/// Attached data blocks start at multiples of this size
+const BLOCK_ALIGNMENT: usize = 4096;
+
+fn encode_xisf(
+    image: &RawImage,
+    keys: &[HeaderKey],
+    compression: XisfCompression,
+    creation_time: SystemTime,
+) -> Result<Vec<u8>, String> {
+    let samples = image.data.iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect::<Vec<u8>>();
+
+    let block = compress(&samples, compression)?;
+
+    // Header length depends on the block position written in it, iterate until stable
+    let mut position = 0;
+    let header = loop {
+        let header = xml_header(image, keys, compression, creation_time, position, &block);
+        let next_position = align(PREAMBLE_SIZE + header.len());
+
+        if next_position == position {
+            break header
+        }
+
+        position = next_position;
+    };
+
+    let mut content = Vec::with_capacity(position + block.len());
+    content.extend_from_slice(SIGNATURE);
+    content.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    content.extend_from_slice(&0u32.to_le_bytes());
+    content.extend_from_slice(header.as_bytes());
+    content.resize(position, 0);
+    content.extend_from_slice(&block);
+    Ok(content)
+}
+
+fn compress(data: &[u8], compression: XisfCompression) -> Result<Vec<u8>, String> {
+    Ok(match compression {
+        XisfCompression::None => data.to_vec(),
+        XisfCompression::Lz4 => lz4_flex::block::compress(data),
+        XisfCompression::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).map_err(to_string)?;
+            encoder.finish().map_err(to_string)?
+        }
+    })
+}
+
+fn xml_header(
+    image: &RawImage,
+    keys: &[HeaderKey],
+    compression: XisfCompression,
+    creation_time: SystemTime,
+    position: usize,
+    block: &[u8],
+) -> String {
+    let area = &image.params.area;
+    let uncompressed_size = image.data.len()*std::mem::size_of::<u16>();
+
+    let compression_attribute = match compression {
+        XisfCompression::None => String::new(),
+        XisfCompression::Lz4 => format!(" compression=\"lz4:{}\"", uncompressed_size),
+        XisfCompression::Zlib => format!(" compression=\"zlib:{}\"", uncompressed_size),
+    };
+
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<xisf version=\"1.0\" xmlns=\"http://www.pixinsight.com/xisf\" ",
+        "xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" ",
+        "xsi:schemaLocation=\"http://www.pixinsight.com/xisf ",
+        "http://pixinsight.com/xisf/xisf-1.0.xsd\">\n"
+    ));
+
+    xml.push_str(&format!(
+        "<Image geometry=\"{}:{}:1\" sampleFormat=\"UInt16\" colorSpace=\"Gray\" \
+         imageType=\"{}\" location=\"attachment:{}:{}\"{}>\n",
+        area.width, area.height, image_type(image.params.frame_type),
+        position, block.len(), compression_attribute
+    ));
+
+    for key in keys {
+        xml.push_str(&format!(
+            "<FITSKeyword name=\"{}\" value=\"{}\" comment=\"{}\"/>\n",
+            escape(key.name), escape(&keyword_value(&key.value)), escape(key.comment)
+        ));
+    }
+
+    if let Some(pattern) = image.bayer_pattern() {
+        xml.push_str(&format!(
+            "<ColorFilterArray pattern=\"{}\" width=\"2\" height=\"2\"/>\n", pattern.name()
+        ));
+    }
+
+    xml.push_str("</Image>\n<Metadata>\n");
+
+    xml.push_str(&format!(
+        "<Property id=\"XISF:CreationTime\" type=\"TimePoint\" value=\"{}Z\"/>\n",
+        format_iso8601(creation_time)
+    ));
+
+    xml.push_str(&format!(
+        "<Property id=\"XISF:CreatorApplication\" type=\"String\" value=\"{}\"/>\n",
+        escape(SOFTWARE)
+    ));
+
+    xml.push_str("</Metadata>\n</xisf>\n");
+    xml
+}
+
+/// Image type names defined by the XISF specification
+fn image_type(frame_type: FrameType) -> &'static str {
+    match frame_type {
+        FrameType::Light => "Light",
+        FrameType::Dark => "Dark",
+        FrameType::Flat => "Flat",
+        FrameType::Bias => "Bias",
+    }
+}
+
+/// Keyword value in the FITS header card representation
+fn keyword_value(value: &KeyValue) -> String {
+    match value {
+        KeyValue::Int(value) => value.to_string(),
+        KeyValue::Float(value) => format!("{:?}", value),
+        KeyValue::Text(value) => format!("'{}'", value.replace('\'', "''")),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            other => result.push(other),
+        }
+    }
+
+    result
+}
+
+fn align(position: usize) -> usize {
+    position.div_ceil(BLOCK_ALIGNMENT)*BLOCK_ALIGNMENT
+}
+
+// ============================================= TEST ==============================================
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn flat_image() -> RawImage {
+        let mut image = ccdi_common::test_image(3, 2, vec![1, 2, 3, 400, 500, 65535]);
+        image.params.frame_type = FrameType::Flat;
+        image
+    }
+
+    fn test_keys() -> Vec<HeaderKey> {
+        vec![HeaderKey {
+            name: "OBJECT",
+            value: KeyValue::Text(String::from("M 42 <Orion's>")),
+            comment: "Name",
+        }]
+    }
+
+    /// Parse the preamble and return the header and the attached data block
+    fn split_file(content: &[u8]) -> (String, Vec<u8>) {
+        assert_eq!(&content[..8], SIGNATURE);
+        let length = u32::from_le_bytes(content[8..12].try_into().unwrap()) as usize;
+        let header = String::from_utf8(content[16..16 + length].to_vec()).unwrap();
+
+        let location = header.split("location=\"attachment:").nth(1).unwrap();
+        let location = location.split('"').next().unwrap();
+        let mut numbers = location.split(':').map(|value| value.parse::<usize>().unwrap());
+        let (position, size) = (numbers.next().unwrap(), numbers.next().unwrap());
+
+        assert_eq!(position % BLOCK_ALIGNMENT, 0);
+        assert_eq!(content.len(), position + size);
+        (header, content[position..].to_vec())
+    }
+
+    fn expected_samples() -> Vec<u8> {
+        flat_image().data.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn uncompressed_file_layout() {
+        let content = encode_xisf(
+            &flat_image(), &test_keys(), XisfCompression::None, SystemTime::UNIX_EPOCH
+        ).unwrap();
+
+        let (header, block) = split_file(&content);
+        assert!(header.contains("geometry=\"3:2:1\""));
+        assert!(header.contains("imageType=\"Flat\""));
+        assert!(header.contains("value=\"&apos;M 42 &lt;Orion&apos;&apos;s&gt;&apos;\""));
+        assert!(!header.contains("compression="));
+        assert_eq!(block, expected_samples());
+    }
+
+    #[test]
+    fn compressed_blocks_decode_to_samples() {
+        let image = flat_image();
+
+        let content = encode_xisf(
+            &image, &test_keys(), XisfCompression::Lz4, SystemTime::UNIX_EPOCH
+        ).unwrap();
+
+        let (header, block) = split_file(&content);
+        assert!(header.contains("compression=\"lz4:12\""));
+        assert_eq!(lz4_flex::block::decompress(&block, 12).unwrap(), expected_samples());
+
+        let content = encode_xisf(
+            &image, &test_keys(), XisfCompression::Zlib, SystemTime::UNIX_EPOCH
+        ).unwrap();
+
+        let (header, block) = split_file(&content);
+        assert!(header.contains("compression=\"zlib:12\""));
+        let mut decoded = Vec::new();
+        flate2::read::ZlibDecoder::new(&block[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, expected_samples());
+    }
+}
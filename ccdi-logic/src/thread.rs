@@ -1,11 +1,16 @@
-use std::{thread::{self, JoinHandle}, time::Duration, sync::{mpsc::RecvTimeoutError, Arc}};
+use std::{
+    thread::{self, JoinHandle}, time::Duration, sync::{mpsc::RecvTimeoutError, Arc},
+    path::PathBuf
+};
 use std::sync::mpsc::{Sender, Receiver};
 
-use ccdi_common::{StateMessage, ClientMessage, log_err, ProcessMessage, StorageMessage, IoMessage};
-use log::{error, debug};
+use ccdi_common::{
+    StateMessage, ClientMessage, log_err, ProcessMessage, StorageMessage, IoMessage, StorageState
+};
+use log::{error, debug, info};
 
 use crate::{
-    state::BackendState,
+    state::BackendState, camera::execute_command,
     convert::handle_process_message, ServiceConfig, storage::Storage,
     io::IoManager
 };
@@ -13,9 +18,21 @@ use crate::{
 // ============================================ PUBLIC =============================================
 
 pub struct LogicParams {
-    pub demo_mode: bool,
+    pub imager: ImagerSource,
+}
+
+/// Driver providing camera frames
+#[derive(Clone, PartialEq, Debug)]
+pub enum ImagerSource {
+    Moravian,
+    /// Simulated camera and focuser
+    Demo,
+    /// FITS files played back from the directory
+    Playback(PathBuf),
 }
 
+/// The thread returns true once the power off sequence finished, its channels to the other
+/// threads are closed then
 pub fn start_logic_thread(
     params: LogicParams,
     config: Arc<ServiceConfig>,
@@ -24,12 +41,12 @@ pub fn start_logic_thread(
     io_tx: Sender<IoMessage>,
     process_tx: Sender<ProcessMessage>,
     storage_tx: Sender<StorageMessage>,
-) -> Result<JoinHandle<()>, String> {
+) -> Result<JoinHandle<bool>, String> {
     thread::Builder::new()
         .name("logic".to_string())
         .spawn(move || {
             let mut state = BackendState::new(
-                params.demo_mode, process_tx, storage_tx.clone(), config
+                params.imager, process_tx, storage_tx.clone(), config
             );
 
             loop {
@@ -39,12 +56,17 @@ pub fn start_logic_thread(
                         &mut state, message, &clients_tx, &storage_tx, &io_tx
                     ),
                     // Last sender disconnected - exit thread
-                    Err(RecvTimeoutError::Disconnected) => return,
+                    Err(RecvTimeoutError::Disconnected) => return false,
                     // No messages received within timeout - perform periodic tasks
                     Err(RecvTimeoutError::Timeout) => periodic_tasks(
                         &mut state, &clients_tx, &storage_tx, &io_tx
                     ),
                 }
+
+                // Final view was already sent to the clients
+                if state.shutdown_finished() {
+                    return true;
+                }
             }
         })
         .map_err(|err| format!("{:?}", err))
@@ -64,17 +86,25 @@ pub fn start_process_thread(
                     Ok(message) => {
                         debug!("Handling image process request");
 
+                        let ProcessMessage::ConvertRawImage(ref convert) = message;
+                        let frame = convert.image.params.start_time;
                         let reply = handle_process_message(message);
 
                         debug!("Image process finished");
 
                         for message in reply.into_iter() {
-                            if let ClientMessage::RgbImage(ref image) = message {
+                            if let ClientMessage::Image(ref image) = message {
                                 log_err("Send process message to server", server_tx.send(
                                     StateMessage::ImageDisplayed(image.clone())
                                 ));
                             }
 
+                            if let ClientMessage::FocusMetrics(ref metrics) = message {
+                                log_err("Send focus metrics to server", server_tx.send(
+                                    StateMessage::FocusMeasured(frame, *metrics)
+                                ));
+                            }
+
                             log_err("Send process message to client", clients_tx.send(message));
                         }
                     },
@@ -94,7 +124,16 @@ pub fn start_storage_thread(
     thread::Builder::new()
         .name("logic".to_string())
         .spawn(move || {
-            let mut storage = Storage::new(config);
+            let mut storage = match Storage::new(config) {
+                Ok(storage) => storage,
+                Err(error) => {
+                    error!("Storage could not be started: {}", error);
+                    let state = StateMessage::UpdateStorageState(StorageState::Error(error));
+                    log_err("Send storage error", server_tx.send(state));
+                    // Images can not be stored, messages are dropped until the service exits
+                    return storage_rx.iter().for_each(drop)
+                },
+            };
 
             let send_results = |result: Result<Vec<StateMessage>, String>| match result {
                 Ok(messages) => {
@@ -108,7 +147,8 @@ pub fn start_storage_thread(
             };
 
             loop {
-                match storage_rx.recv_timeout(Duration::from_millis(1000)) {
+                // Short timeout so that finished writes are reported without delay
+                match storage_rx.recv_timeout(Duration::from_millis(100)) {
                     // Process the received message
                     Ok(message) => send_results(storage.process(message)),
                     // Last sender disconnected - exit thread
@@ -156,6 +196,37 @@ pub fn start_io_thread(
         .map_err(|err| format!("{:?}", err))
 }
 
+/// Waits for the power off sequence of the logic thread, the worker threads finish once
+/// the logic thread closed their channels, the power off command is executed after all
+/// of them finished and the process exits
+pub fn start_shutdown_thread(
+    config: Arc<ServiceConfig>,
+    logic_thread: JoinHandle<bool>,
+    worker_threads: Vec<JoinHandle<()>>,
+) -> Result<JoinHandle<()>, String> {
+    thread::Builder::new()
+        .name("shutdown".to_string())
+        .spawn(move || {
+            match logic_thread.join() {
+                Ok(true) => {},
+                Ok(false) => return,
+                Err(_) => return error!("Logic thread panicked"),
+            }
+
+            for worker in worker_threads {
+                if worker.join().is_err() {
+                    error!("Worker thread panicked during shutdown");
+                }
+            }
+
+            info!("Shutdown finished, executing power off command");
+            execute_command(&config.turn_off_command);
+            log::logger().flush();
+            std::process::exit(0);
+        })
+        .map_err(|err| format!("{:?}", err))
+}
+
 // =========================================== PRIVATE =============================================
 
 fn receive_message(
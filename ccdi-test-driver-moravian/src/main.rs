@@ -1,15 +1,18 @@
 use std::{thread, time::Duration};
 
 use ccdi_common::{to_string, log_err};
-use ccdi_driver_moravian::{get_any_camera_id, connect_usb_camera, CameraError, CameraDriver};
+use ccdi_driver_moravian::{
+    get_any_camera_id, connect_usb_camera, enumerate_usb_cameras, CameraError, CameraDriver
+};
 use fitsio::FitsFile;
 
 
 fn main() -> Result<(), String> {
+    println!("Connected cameras: {:?}", enumerate_usb_cameras());
     let camera_id = get_any_camera_id().ok_or("No camera connected")?;
 
     if let Ok(camera) = connect_usb_camera(camera_id) {
-        print_camera_info(&camera).map_err(|err| format!("{:?}", err))?;
+        print_camera_info(&camera).map_err(|err| err.to_string())?;
     }
 
     dbg!(camera_id);
@@ -25,6 +28,10 @@ fn print_camera_info(camera: &CameraDriver) -> Result<(), CameraError> {
         println!("Read mode {}: {}", index, mode)
     }
 
+    for (index, filter) in camera.enumerate_filters()?.iter().enumerate() {
+        println!("Filter {}: {} (offset {})", index, filter.name, filter.offset)
+    }
+
     let width = camera.read_chip_width()?;
     let height = camera.read_chip_height()?;
 
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use ccdi_imager_interface::{ImagerProperties, DeviceProperty, BasicProperties};
+use ccdi_imager_interface::{ImagerProperties, DeviceProperty, BasicProperties, CameraInfo};
 use yew::Properties;
 use super::*;
 
@@ -39,6 +39,7 @@ fn render_properties(properties: &ImagerProperties) -> Html {
     html!{
         <div class="div-table">
             {render_basic_rows(&properties.basic)}
+            {render_sensor_row(&properties.info)}
             {render_other_rows(properties)}
         </div>
     }
@@ -53,6 +54,13 @@ fn render_basic_rows(properties: &BasicProperties) -> Html {
     }
 }
 
+fn render_sensor_row(info: &CameraInfo) -> Html {
+    match info.bayer_pattern {
+        None => render_row("Sensor", "Mono"),
+        Some(pattern) => render_row("Sensor", &format!("Color ({})", pattern.name())),
+    }
+}
+
 fn render_other_rows(properties: &ImagerProperties) -> Html {
     properties.other.iter().map(|property| render_item(property)).collect::<Html>()
 }
@@ -0,0 +1,68 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ccdi_image::render_focus_plot_as_bmp;
+use yew::Properties;
+
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct FocusPlot;
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct FocusPlotData {
+    /// Metrics of recent frames, oldest first
+    pub history: Vec<FocusMetrics>,
+}
+
+impl Component for FocusPlot {
+    type Message = ();
+    type Properties = FocusPlotData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {}
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let history = &ctx.props().history;
+
+        html! {
+            <div>
+                <p>{"Focus"}</p>
+                { render_latest(history.last()) }
+                { render_plot(history) }
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+const PLOT_WIDTH: usize = 300;
+const PLOT_HEIGHT: usize = 120;
+
+fn render_latest(metrics: Option<&FocusMetrics>) -> Html {
+    match metrics {
+        None => html! { <div>{"No frames measured"}</div> },
+        Some(metrics) if metrics.star_count == 0 => html! { <div>{"No stars detected"}</div> },
+        Some(metrics) => html! {
+            <div>
+                <span>{format!("Stars {} ", metrics.star_count)}</span>
+                <span class="yellow">{format!("HFR {:.2} ", metrics.hfr)}</span>
+                <span class="cyan">{format!("FWHM {:.2} ", metrics.fwhm)}</span>
+                <span>{format!("Ecc {:.2}", metrics.eccentricity)}</span>
+            </div>
+        }
+    }
+}
+
+fn render_plot(history: &[FocusMetrics]) -> Html {
+    let payload = render_focus_plot_as_bmp(history, PLOT_WIDTH, PLOT_HEIGHT)
+        .map(|data| STANDARD.encode(data));
+
+    match payload {
+        Err(error) => html! { <p>{"Focus plot err:"} {error}</p> },
+        Ok(ref base64) => html! {
+            <img class={"gray-border"} src={format!("data:image/bmp;base64,{}", base64)} />
+        }
+    }
+}
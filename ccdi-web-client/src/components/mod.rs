@@ -7,4 +7,6 @@ pub mod menu;
 pub mod camera;
 pub mod text_input;
 pub mod shooting_details;
-pub mod system;
\ No newline at end of file
+pub mod system;
+pub mod focus;
+pub mod telemetry;
\ No newline at end of file
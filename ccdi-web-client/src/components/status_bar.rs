@@ -28,12 +28,13 @@ impl Component for StatusBar {
             <div class="status-bar-body float-container">
                 { state_view("Connection", main_state) }
                 { combined("Camera", main_state, ctx.props().logic.camera) }
-                { combined("Storage", main_state, ctx.props().logic.storage.as_connection_state()) }
+                { combined_class("Storage", main_state, storage_class(&ctx.props().logic.storage)) }
                 { combined("Loop", main_state, ctx.props().logic.loop_enabled) }
                 { combined("Trigger Needed", main_state, ctx.props().logic.required) }
                 { combined("Trigger On", main_state, ctx.props().logic.trigger) }
                 { combined("Exposure", main_state, ctx.props().logic.exposure) }
                 { combined("Save On", main_state, ctx.props().logic.save) }
+                { combined_class("Cooling", main_state, cooling_class(ctx.props().logic.cooling)) }
             </div>
         }
     }
@@ -42,14 +43,38 @@ impl Component for StatusBar {
 // =========================================== PRIVATE =============================================
 
 fn combined(name: &str, main: ConnectionState, state: ConnectionState) -> Html {
+    combined_class(name, main, status_to_class(state))
+}
+
+fn combined_class(name: &str, main: ConnectionState, class: &'static str) -> Html {
     let status_class = match main {
-        ConnectionState::Established => status_to_class(state),
+        ConnectionState::Established => class,
         _other => "unknown"
     };
 
     state_html(name, status_class)
 }
 
+/// Storage running out of space is shown as a warning
+fn storage_class(state: &StorageState) -> &'static str {
+    match state {
+        StorageState::Available(capacity) => match capacity.level {
+            SpaceLevel::Sufficient => "ok",
+            SpaceLevel::Low => "warn",
+            SpaceLevel::Critical => "error",
+        },
+        other => status_to_class(other.as_connection_state()),
+    }
+}
+
+/// Temperature settling towards the target is shown as a warning
+fn cooling_class(state: ConnectionState) -> &'static str {
+    match state {
+        ConnectionState::Connecting => "warn",
+        other => status_to_class(other),
+    }
+}
+
 fn state_view(name: &str, state: ConnectionState) -> Html {
     state_html(name, status_to_class(state))
 }
@@ -10,6 +10,8 @@ pub struct System {
 #[derive(Clone, PartialEq, Properties)]
 pub struct SystemData {
     pub on_action: Callback<StateMessage>,
+    /// Progress of the power off sequence, once requested
+    pub shutdown: Option<ShutdownProgress>,
 }
 
 pub enum Msg{
@@ -42,6 +44,10 @@ impl Component for System {
             move |_| Msg::ServerAction(action.clone())
         );
 
+        if let Some(progress) = ctx.props().shutdown.as_ref() {
+            return render_shutdown(progress)
+        }
+
         let show_confirm = || ctx.link().callback(move |_| Msg::ShowConfirmation);
 
         let confirmation_button = match self.confirmation_enabled {
@@ -62,3 +68,29 @@ impl Component for System {
         }
     }
 }
+
+// =========================================== PRIVATE =============================================
+
+fn render_shutdown(progress: &ShutdownProgress) -> Html {
+    let temperature = |value: Option<f64>| match value {
+        None => String::from("-"),
+        Some(value) => format!("{:.1} °C", value),
+    };
+
+    let stage = match progress.stage {
+        ShutdownStage::Flushing => format!("Writing {} queued images", progress.queue_depth),
+        ShutdownStage::WarmingUp => format!(
+            "Warming up, chip {} of {}",
+            temperature(progress.chip_temperature.map(f64::from)),
+            temperature(progress.target_temperature)
+        ),
+        ShutdownStage::PoweringOff => String::from("Camera closed, powering off"),
+    };
+
+    html!{
+        <div>
+            <p>{"Shutting down"}</p>
+            <div>{stage}</div>
+        </div>
+    }
+}
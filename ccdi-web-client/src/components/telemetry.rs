@@ -0,0 +1,97 @@
+use std::rc::Rc;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ccdi_image::{render_temperature_plot_as_bmp, render_cooler_plot_as_bmp};
+use yew::Properties;
+
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct TelemetryCharts;
+
+#[derive(Clone, Properties)]
+pub struct TelemetryChartsData {
+    /// Telemetry samples, oldest first, replaced only when new samples arrive
+    pub samples: Rc<Vec<TelemetrySample>>,
+}
+
+impl PartialEq for TelemetryChartsData {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.samples, &other.samples)
+    }
+}
+
+impl Component for TelemetryCharts {
+    type Message = ();
+    type Properties = TelemetryChartsData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {}
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let samples = &ctx.props().samples;
+        let temperature = render_temperature_plot_as_bmp(samples, WIDTH, HEIGHT);
+        let cooler = render_cooler_plot_as_bmp(samples, WIDTH, HEIGHT);
+
+        html! {
+            <div>
+                <p>{"Temperatures"}</p>
+                { render_latest(samples.last()) }
+                { render_plot("Temperature", temperature) }
+                <p>{"Cooler"}</p>
+                { render_plot("Cooler", cooler) }
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+const WIDTH: usize = 300;
+const HEIGHT: usize = 120;
+
+fn render_latest(sample: Option<&TelemetrySample>) -> Html {
+    let telemetry = match sample {
+        None => return html! { <div>{"No telemetry received"}</div> },
+        Some(sample) => sample.telemetry,
+    };
+
+    let value = |value: Option<f32>, unit: &str, scale: f32| match value {
+        None => String::from("-"),
+        Some(value) => format!("{:.1} {}", value*scale, unit),
+    };
+
+    html! {
+        <div>
+            <span class="yellow">
+                {format!("Chip {} ", value(Some(telemetry.chip_temperature), "°C", 1.0))}
+            </span>
+            <span class="red">
+                {format!("Hot {} ", value(telemetry.hot_temperature, "°C", 1.0))}
+            </span>
+            <span class="cyan">
+                {format!("Env {} ", value(telemetry.environment_temperature, "°C", 1.0))}
+            </span>
+            <div>
+                <span class="yellow">
+                    {format!("Power {} ", value(telemetry.cooler_power, "%", 100.0))}
+                </span>
+                <span class="cyan">
+                    {format!("Fan {} ", value(telemetry.fan_speed, "%", 100.0))}
+                </span>
+                <span>{format!("Supply {}", value(telemetry.supply_voltage, "V", 1.0))}</span>
+            </div>
+        </div>
+    }
+}
+
+fn render_plot(name: &str, payload: Result<Vec<u8>, String>) -> Html {
+    match payload.map(|data| STANDARD.encode(data)) {
+        Err(error) => html! { <p>{format!("{} plot err: {}", name, error)}</p> },
+        Ok(ref base64) => html! {
+            <img class={"gray-border"} src={format!("data:image/bmp;base64,{}", base64)} />
+        }
+    }
+}
@@ -2,9 +2,11 @@ mod components;
 mod connection;
 mod selectors;
 
+use std::rc::Rc;
 use std::sync::Arc;
 
 use ccdi_common::*;
+use ccdi_imager_interface::{Binning, ExposureArea, FrameType};
 use components::shooting_details::ShootingDetails;
 use connection::ConnectionService;
 use gloo::console;
@@ -13,12 +15,23 @@ use yew::html::Scope;
 use yew::{html, Component, Context, Html};
 
 use components::camera::CameraDetail;
+use components::focus::FocusPlot;
+use components::telemetry::TelemetryCharts;
 use components::footer::Footer;
 use components::menu::{Menu, MenuItem};
 use components::status_bar::StatusBar;
 use selectors::composition::CompositionDetail;
 use selectors::picture::Picture;
 use selectors::rendering::RenderingSelector;
+use selectors::binning::BinningSelector;
+use selectors::roi::RoiSelector;
+use selectors::read_mode::ReadModeSelector;
+use selectors::filter::FilterSelector;
+use selectors::sequence::SequenceEditor;
+use selectors::focuser::FocuserControl;
+use selectors::camera::CameraSelector;
+use selectors::frame_type::FrameTypeSelector;
+use selectors::demosaic::DemosaicSelector;
 
 use crate::components::system::System;
 use crate::selectors::float::FloatSelector;
@@ -26,6 +39,9 @@ use crate::selectors::shooting::ShootingDetail;
 
 // ============================================ PUBLIC =============================================
 
+/// Number of frames shown in the focus plot
+const FOCUS_HISTORY_LENGTH: usize = 100;
+
 pub enum Msg {
     RegisterConnectionService(Scope<ConnectionService>),
     ConnectionState(ConnectionState),
@@ -40,7 +56,13 @@ pub enum UserAction {
 }
 
 pub struct Main {
-    pub image: Option<Arc<RgbImage<u16>>>,
+    pub image: Option<Arc<PreviewImage>>,
+    /// Focus metrics of recent frames, oldest first
+    pub focus_history: Vec<FocusMetrics>,
+    /// Cooling telemetry of the night, oldest first
+    pub telemetry: TelemetryHistory,
+    /// Snapshot of `telemetry` passed to the charts, rebuilt when new samples arrive
+    pub telemetry_samples: Rc<Vec<TelemetrySample>>,
     pub view_state: ViewState,
     pub connection_state: ConnectionState,
     pub connection_context: Option<Scope<ConnectionService>>,
@@ -52,7 +74,18 @@ impl Main {
         match message {
             ClientMessage::Reconnect => {} // handled elsewhere
             ClientMessage::View(view) => self.view_state = view,
-            ClientMessage::RgbImage(image) => self.image = Some(image),
+            ClientMessage::Image(image) => self.image = Some(image),
+            ClientMessage::FocusMetrics(metrics) => {
+                if self.focus_history.len() >= FOCUS_HISTORY_LENGTH {
+                    self.focus_history.remove(0);
+                }
+
+                self.focus_history.push(metrics);
+            },
+            ClientMessage::Telemetry(samples) => {
+                self.telemetry.extend(samples);
+                self.telemetry_samples = Rc::new(self.telemetry.to_vec());
+            },
         }
 
         true
@@ -63,9 +96,8 @@ impl Main {
             MenuItem::Composition => self.render_composition(ctx),
             MenuItem::Cooling => self.render_cooling(ctx),
             MenuItem::Shoot => self.render_shoot(ctx),
-            MenuItem::Info => html!{
-                <CameraDetail data={self.view_state.camera_properties.clone()} />
-            },
+            MenuItem::Info => self.render_info(ctx),
+            MenuItem::Sequence => self.render_sequence(ctx),
             MenuItem::System => self.render_system(ctx),
         }
     }
@@ -86,6 +118,38 @@ impl Main {
             |value: RenderingType| Msg::ParamUpdate(CameraParamMessage::SetRenderingType(value))
         );
 
+        let quality_changed = ctx.link().callback(
+            |value: DemosaicQuality| Msg::ParamUpdate(CameraParamMessage::SetDemosaicQuality(value))
+        );
+
+        let binning_changed = ctx.link().callback(
+            |value: Binning| Msg::ParamUpdate(CameraParamMessage::SetBinning(value))
+        );
+
+        let roi_changed = ctx.link().callback(
+            |value: Option<ExposureArea>| Msg::ParamUpdate(CameraParamMessage::SetRoi(value))
+        );
+
+        let read_mode_changed = ctx.link().callback(
+            |value: usize| Msg::ParamUpdate(CameraParamMessage::SetReadMode(value))
+        );
+
+        let filter_changed = ctx.link().callback(
+            |value: usize| Msg::ParamUpdate(CameraParamMessage::SetFilter(value))
+        );
+
+        let frame_type_changed = ctx.link().callback(
+            |value: FrameType| Msg::ParamUpdate(CameraParamMessage::SetFrameType(value))
+        );
+
+        let read_modes = self.view_state.camera_properties.as_ref()
+            .map(|properties| properties.read_modes.clone())
+            .unwrap_or_default();
+
+        let filters: Vec<String> = self.view_state.camera_properties.as_ref()
+            .map(|properties| properties.filters.iter().map(|filter| filter.name.clone()).collect())
+            .unwrap_or_default();
+
         html!{
             <div>
                 <FloatSelector
@@ -104,10 +168,39 @@ impl Main {
                     rendering_changed={rendering_changed}
                     selected_value={self.view_state.camera_params.rendering}
                 />
+                <DemosaicSelector
+                    quality_changed={quality_changed}
+                    selected_value={self.view_state.camera_params.demosaic}
+                />
+                <FrameTypeSelector
+                    frame_type_changed={frame_type_changed}
+                    selected_value={self.view_state.camera_params.frame_type}
+                />
+                <BinningSelector
+                    binning_changed={binning_changed}
+                    selected_value={self.view_state.camera_params.binning}
+                />
+                <RoiSelector
+                    roi_changed={roi_changed}
+                    selected_value={self.view_state.camera_params.roi.clone()}
+                    camera_properties={self.view_state.camera_properties.clone()}
+                />
+                <ReadModeSelector
+                    read_mode_changed={read_mode_changed}
+                    selected_value={self.view_state.camera_params.read_mode}
+                    read_modes={read_modes}
+                />
+                <FilterSelector
+                    filter_changed={filter_changed}
+                    selected_value={self.view_state.camera_params.filter}
+                    filters={filters}
+                />
                 <CompositionDetail
-                    on_action={action}
+                    on_action={action.clone()}
                     camera_params={self.view_state.camera_params.clone()}
                 />
+                <FocusPlot history={self.focus_history.clone()} />
+                <FocuserControl on_action={action} focuser={self.view_state.focuser.clone()} />
             </div>
         }
     }
@@ -143,6 +236,7 @@ impl Main {
                     selected_value={self.view_state.camera_params.heating_pwm}
                     value_changed={heating_changed}
                 />
+                <TelemetryCharts samples={self.telemetry_samples.clone()} />
             </div>
         }
     }
@@ -156,6 +250,7 @@ impl Main {
                 <ShootingDetail
                     on_action={action.clone()}
                     storage_details={self.view_state.storage_detail.clone()}
+                    object={self.view_state.camera_params.object.clone()}
                 />
 
                 <CompositionDetail
@@ -166,12 +261,40 @@ impl Main {
         }
     }
 
+    fn render_info(&self, ctx: &Context<Self>) -> Html {
+        let camera_selected = ctx.link().callback(
+            |id: i32| Msg::SendMessage(StateMessage::SelectCamera(id))
+        );
+
+        let refresh = ctx.link().callback(|_| Msg::SendMessage(StateMessage::RefreshCameras));
+
+        html!{
+            <div>
+                <CameraSelector
+                    camera_selected={camera_selected}
+                    refresh={refresh}
+                    cameras={self.view_state.cameras.clone()}
+                />
+                <CameraDetail data={self.view_state.camera_properties.clone()} />
+            </div>
+        }
+    }
+
+    fn render_sequence(&self, ctx: &Context<Self>) -> Html {
+        let action = ctx.link()
+            .callback(|action: StateMessage| Msg::SendMessage(action));
+
+        html!{
+            <SequenceEditor on_action={action} sequence={self.view_state.sequence.clone()}/>
+        }
+    }
+
     fn render_system(&self, ctx: &Context<Self>) -> Html {
         let action = ctx.link()
             .callback(|action: StateMessage| Msg::SendMessage(action));
 
         html!{
-            <System on_action={action.clone()}/>
+            <System on_action={action.clone()} shutdown={self.view_state.shutdown.clone()}/>
         }
     }
 
@@ -198,6 +321,9 @@ impl Component for Main {
     fn create(_ctx: &Context<Self>) -> Self {
         Self {
             image: None,
+            focus_history: Vec::new(),
+            telemetry: Default::default(),
+            telemetry_samples: Default::default(),
             view_state: Default::default(),
             selected_menu: MenuItem::Composition,
             connection_state: ConnectionState::Disconnected,
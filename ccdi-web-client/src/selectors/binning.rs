@@ -0,0 +1,68 @@
+use ccdi_imager_interface::Binning;
+use yew::{Properties, Callback};
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct BinningSelector;
+
+pub enum Msg {
+    SetValue(Binning),
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct BinningData {
+    pub binning_changed: Callback<Binning>,
+    pub selected_value: Binning,
+}
+
+impl Component for BinningSelector {
+    type Message = Msg;
+    type Properties = BinningData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SetValue(value) => {
+                ctx.props().binning_changed.emit(value)
+            },
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let selected = ctx.props().selected_value;
+
+        html! {
+            <div>
+                <p>{"Binning"}</p>
+                { binning_button(selected, Binning::new(1, 1), "1x1", ctx)}
+                { binning_button(selected, Binning::new(2, 2), "2x2", ctx)}
+                { binning_button(selected, Binning::new(4, 4), "4x4", ctx)}
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+fn binning_button(
+    current: Binning,
+    value: Binning,
+    text: &str,
+    ctx: &Context<BinningSelector>
+) -> Html {
+    let binning_click = |action: Binning| ctx.link().callback(move |_| Msg::SetValue(action));
+
+    let selected_class = match value == current {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    html! {
+        <button class={classes!(selected_class)} onclick={binning_click(value)}>{text}</button>
+    }
+}
@@ -0,0 +1,90 @@
+use ccdi_imager_interface::DeviceDescriptor;
+use yew::{Properties, Callback};
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct CameraSelector;
+
+pub enum Msg {
+    Select(i32),
+    Refresh,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct CameraSelectorData {
+    pub camera_selected: Callback<i32>,
+    /// Cameras are listed again only on request while a camera is connected
+    pub refresh: Callback<()>,
+    pub cameras: CameraListView,
+}
+
+impl Component for CameraSelector {
+    type Message = Msg;
+    type Properties = CameraSelectorData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Select(id) => ctx.props().camera_selected.emit(id),
+            Msg::Refresh => ctx.props().refresh.emit(()),
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let cameras = &ctx.props().cameras;
+        let refresh = ctx.link().callback(|_| Msg::Refresh);
+        let refresh = html! { <button onclick={refresh}>{"Refresh"}</button> };
+
+        // Nothing to choose from with a single camera that is used anyway
+        if cameras.devices.len() < 2 && cameras.selected.is_none() {
+            return html! { <div>{refresh}</div> }
+        }
+
+        let buttons = cameras.devices.iter()
+            .map(|device| camera_button(cameras, device, ctx))
+            .collect::<Html>();
+
+        let missing = cameras.selected
+            .filter(|id| !cameras.devices.iter().any(|device| device.id == *id))
+            .map(|id| html! { <div>{format!("Selected camera {} not present", id)}</div> });
+
+        html! {
+            <div>
+                <p>{"Camera"}</p>
+                {buttons}
+                {refresh}
+                {missing}
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+fn camera_button(
+    cameras: &CameraListView,
+    device: &DeviceDescriptor,
+    ctx: &Context<CameraSelector>
+) -> Html {
+    let id = device.id;
+    let onclick = ctx.link().callback(move |_| Msg::Select(id));
+
+    let selected_class = match cameras.selected.or(cameras.connected) == Some(id) {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    let text = match cameras.connected == Some(id) {
+        true => format!("{} (connected)", device.name),
+        false => device.name.clone(),
+    };
+
+    html! {
+        <button class={classes!(selected_class)} onclick={onclick}>{text}</button>
+    }
+}
@@ -62,6 +62,9 @@ impl Component for CompositionDetail {
                         onclick={
                         server_action(ExposureMessage(ExposureCommand::Start))
                     }>{"Expose"}</button>
+                    <button onclick={
+                        server_action(ExposureMessage(ExposureCommand::Stop))
+                    }>{"Stop"}</button>
                 </div>
                 <div>
                     <button
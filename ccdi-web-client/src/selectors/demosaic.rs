@@ -0,0 +1,69 @@
+use yew::{Properties, Callback};
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct DemosaicSelector;
+
+pub enum Msg {
+    SetValue(DemosaicQuality),
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct DemosaicData {
+    pub quality_changed: Callback<DemosaicQuality>,
+    pub selected_value: DemosaicQuality,
+}
+
+impl Component for DemosaicSelector {
+    type Message = Msg;
+    type Properties = DemosaicData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SetValue(value) => {
+                ctx.props().quality_changed.emit(value)
+            },
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let selected = ctx.props().selected_value;
+
+        html! {
+            <div>
+                <p>{"Color reconstruction"}</p>
+                { quality_button(selected, DemosaicQuality::Fast, "Fast", ctx)}
+                { quality_button(selected, DemosaicQuality::Bilinear, "Bilinear", ctx)}
+                { quality_button(selected, DemosaicQuality::EdgeAware, "Edge Aware", ctx)}
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+fn quality_button(
+    current: DemosaicQuality,
+    value: DemosaicQuality,
+    text: &str,
+    ctx: &Context<DemosaicSelector>
+) -> Html {
+    let selected_class = match value == current {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    html! {
+        <button
+            class={classes!(selected_class)}
+            onclick={ctx.link().callback(move |_| Msg::SetValue(value))}
+            >{text}
+        </button>
+    }
+}
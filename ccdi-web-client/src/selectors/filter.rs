@@ -0,0 +1,78 @@
+use yew::{Properties, Callback};
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct FilterSelector;
+
+pub enum Msg {
+    SetValue(usize),
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct FilterData {
+    pub filter_changed: Callback<usize>,
+    pub selected_value: usize,
+    /// Filter names, empty when the camera has no filter wheel
+    pub filters: Vec<String>,
+}
+
+impl Component for FilterSelector {
+    type Message = Msg;
+    type Properties = FilterData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SetValue(value) => {
+                ctx.props().filter_changed.emit(value)
+            },
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let filters = &ctx.props().filters;
+
+        if filters.is_empty() {
+            return html! {}
+        }
+
+        let selected = ctx.props().selected_value;
+
+        let buttons = filters.iter()
+            .enumerate()
+            .map(|(index, name)| filter_button(selected, index, name, ctx))
+            .collect::<Html>();
+
+        html! {
+            <div>
+                <p>{"Filter"}</p>
+                {buttons}
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+fn filter_button(
+    current: usize,
+    value: usize,
+    text: &str,
+    ctx: &Context<FilterSelector>
+) -> Html {
+    let filter_click = |action: usize| ctx.link().callback(move |_| Msg::SetValue(action));
+
+    let selected_class = match value == current {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    html! {
+        <button class={classes!(selected_class)} onclick={filter_click(value)}>{text}</button>
+    }
+}
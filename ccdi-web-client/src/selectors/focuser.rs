@@ -0,0 +1,133 @@
+use ccdi_focuser_interface::FocuserStatus;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ccdi_image::render_autofocus_plot_as_bmp;
+use yew::{Properties, Callback};
+
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct FocuserControl;
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct FocuserControlData {
+    pub on_action: Callback<StateMessage>,
+    pub focuser: FocuserView,
+}
+
+pub enum Msg {
+    ServerAction(StateMessage)
+}
+
+impl Component for FocuserControl {
+    type Message = Msg;
+    type Properties = FocuserControlData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {}
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::ServerAction(action) => ctx.props().on_action.emit(action),
+        }
+        false
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        use FocuserMessage::*;
+
+        let focuser = &ctx.props().focuser;
+
+        let status = match (focuser.properties.as_ref(), focuser.status.as_ref()) {
+            (Some(_), Some(status)) => status,
+            _ => return html! {
+                <div>
+                    <p>{"Focuser"}</p>
+                    <div>{"Not connected"}</div>
+                </div>
+            }
+        };
+
+        let server_action = |action: FocuserMessage| ctx.link().callback(
+            move |_| Msg::ServerAction(StateMessage::FocuserMessage(action.clone()))
+        );
+
+        let running = focuser.autofocus.state == AutofocusState::Running;
+
+        html!{
+            <div>
+                <p>{"Focuser"}</p>
+                <div>{format_status(status)}</div>
+                <div>
+                    <button onclick={server_action(MoveBy(-LARGE_STEP))}>{"<<"}</button>
+                    <button onclick={server_action(MoveBy(-SMALL_STEP))}>{"<"}</button>
+                    <button onclick={server_action(MoveBy(SMALL_STEP))}>{">"}</button>
+                    <button onclick={server_action(MoveBy(LARGE_STEP))}>{">>"}</button>
+                    <button onclick={server_action(Halt)}>{"Halt"}</button>
+                </div>
+                <div>
+                    <button
+                        class={classes!(if running { Some("button-selected") } else { None })}
+                        onclick={server_action(StartAutofocus)}
+                        >{"Autofocus"}
+                    </button>
+                    <button onclick={server_action(StopAutofocus)}>{"Stop"}</button>
+                </div>
+                { render_autofocus(&focuser.autofocus) }
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+const SMALL_STEP: i32 = 50;
+const LARGE_STEP: i32 = 500;
+const PLOT_WIDTH: usize = 300;
+const PLOT_HEIGHT: usize = 120;
+
+fn format_status(status: &FocuserStatus) -> String {
+    let temperature = status.temperature
+        .map(|temperature| format!(", {:.1} °C", temperature))
+        .unwrap_or_default();
+
+    let moving = match status.moving {
+        true => ", moving",
+        false => "",
+    };
+
+    format!("Position {}{}{}", status.position, moving, temperature)
+}
+
+fn render_autofocus(autofocus: &AutofocusView) -> Html {
+    if autofocus.state == AutofocusState::Idle && autofocus.points.is_empty() {
+        return html! {}
+    }
+
+    let progress = format!(
+        "{:?}, {} of {} points: {}",
+        autofocus.state, autofocus.points.len(), autofocus.total_points, autofocus.detail
+    );
+
+    let payload = render_autofocus_plot_as_bmp(
+        &autofocus.points, autofocus.curve.as_ref(), PLOT_WIDTH, PLOT_HEIGHT
+    ).map(|data| STANDARD.encode(data));
+
+    html! {
+        <div>
+            <div>{progress}</div>
+            {
+                match payload {
+                    Err(error) => html! { <p>{"Autofocus plot err:"} {error}</p> },
+                    Ok(ref base64) => html! {
+                        <img
+                            class={"gray-border"}
+                            src={format!("data:image/bmp;base64,{}", base64)}
+                        />
+                    }
+                }
+            }
+        </div>
+    }
+}
@@ -0,0 +1,69 @@
+use ccdi_imager_interface::FrameType;
+use yew::{Properties, Callback};
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct FrameTypeSelector;
+
+pub enum Msg {
+    SetValue(FrameType),
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct FrameTypeData {
+    pub frame_type_changed: Callback<FrameType>,
+    pub selected_value: FrameType,
+}
+
+impl Component for FrameTypeSelector {
+    type Message = Msg;
+    type Properties = FrameTypeData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SetValue(value) => {
+                ctx.props().frame_type_changed.emit(value)
+            },
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let selected = ctx.props().selected_value;
+
+        html! {
+            <div>
+                <p>{"Frame type"}</p>
+                { frame_type_button(selected, FrameType::Light, "Light", ctx)}
+                { frame_type_button(selected, FrameType::Dark, "Dark", ctx)}
+                { frame_type_button(selected, FrameType::Flat, "Flat", ctx)}
+                { frame_type_button(selected, FrameType::Bias, "Bias", ctx)}
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+fn frame_type_button(
+    current: FrameType,
+    value: FrameType,
+    text: &str,
+    ctx: &Context<FrameTypeSelector>
+) -> Html {
+    let frame_type_click = |action: FrameType| ctx.link().callback(move |_| Msg::SetValue(action));
+
+    let selected_class = match value == current {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    html! {
+        <button class={classes!(selected_class)} onclick={frame_type_click(value)}>{text}</button>
+    }
+}
@@ -5,4 +5,13 @@ pub mod picture;
 pub mod rendering;
 pub mod composition;
 pub mod shooting;
-pub mod float;
\ No newline at end of file
+pub mod float;
+pub mod binning;
+pub mod roi;
+pub mod read_mode;
+pub mod filter;
+pub mod sequence;
+pub mod frame_type;
+pub mod demosaic;
+pub mod focuser;
+pub mod camera;
\ No newline at end of file
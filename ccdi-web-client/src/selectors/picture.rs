@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
 use base64::{engine::general_purpose::STANDARD, Engine};
-use ccdi_common::RgbImage;
+use ccdi_common::PreviewImage;
 use ccdi_image::{
-    Transform, TransformFunction, rgb_image_to_bmp, compute_image_stats, ImageStats,
-    render_histogram_as_bmp
+    Transform, TransformFunction, preview_image_to_bmp, compute_image_stats, ImageStats,
+    render_histogram_as_bmp, Histogram, Channel
 };
 use yew::Properties;
 
@@ -24,7 +24,7 @@ pub struct Picture {
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct PictureData {
-    pub image: Option<Arc<RgbImage<u16>>>,
+    pub image: Option<Arc<PreviewImage>>,
     pub hist_width: usize,
     pub hist_height: usize,
 }
@@ -81,7 +81,7 @@ impl Component for Picture {
                         { function_button(ctx, self.function, TransformFunction::Log2, "Log2") }
                     </div>
                     <div class="image-content">
-                        {rgb_image_to_html(ctx.props().image.as_deref(), transform)}
+                        {image_to_html(ctx.props().image.as_deref(), transform)}
                         {histogram_table(stats.as_ref(), hist_h)}
                     </div>
                 </div>
@@ -130,8 +130,8 @@ fn gain_button(ctx: &Context<Picture>, current_gain: i32, button_gain: i32) -> H
     }
 }
 
-fn rgb_image_to_html(image: Option<&RgbImage<u16>>, transform: Transform) -> Html {
-    match image.and_then(|image| rgb_to_jpeg_base64(image, transform)) {
+fn image_to_html(image: Option<&PreviewImage>, transform: Transform) -> Html {
+    match image.and_then(|image| image_to_jpeg_base64(image, transform)) {
         None => html! { },
         Some(ref base64) => html! {
             <img class="image-element" src={format!("data:image/bmp;base64,{}", base64)} />
@@ -146,13 +146,13 @@ fn histogram_table(stats: Option<&ImageStats>, height: usize) -> Html {
             <div class="hist-table">
                 <div class="div-table-row">
                     <div class="hist-table-col">
-                        {limits(stats.total.min, stats.r.min, stats.g.min, stats.b.min)}
+                        {limits(stats.total.min, &stats.channels, |channel| channel.min)}
                     </div>
                     <div class="hist-table-col">
                         {histogram_image(stats, height)}
                     </div>
                     <div class="hist-table-col">
-                        {limits(stats.total.max, stats.r.max, stats.g.max, stats.b.max)}
+                        {limits(stats.total.max, &stats.channels, |channel| channel.max)}
                     </div>
                 </div>
             </div>
@@ -171,20 +171,31 @@ fn histogram_image(stats: &ImageStats, height: usize) -> Html {
     }
 }
 
-fn rgb_to_jpeg_base64(image: &RgbImage<u16>, transform: Transform) -> Option<String> {
-    let encoded_jpeg = rgb_image_to_bmp(image, transform).ok()?;
+fn image_to_jpeg_base64(image: &PreviewImage, transform: Transform) -> Option<String> {
+    let encoded_jpeg = preview_image_to_bmp(image, transform).ok()?;
     let encoded_base64 = STANDARD.encode(&encoded_jpeg);
     Some(encoded_base64)
 }
 
-fn limits(all: u16, r: u16, g: u16, b: u16) -> Html {
+fn limits(all: u16, channels: &[Histogram], value: fn(&Histogram) -> u16) -> Html {
     html!{
         <>
             <div>{all}</div>
             <hr/>
-            <div class="red">{r}</div>
-            <div class="green">{g}</div>
-            <div class="blue">{b}</div>
+            {
+                channels.iter().map(|channel| html! {
+                    <div class={channel_class(channel.channel)}>{value(channel)}</div>
+                }).collect::<Html>()
+            }
         </>
     }
 }
+
+fn channel_class(channel: Channel) -> Option<&'static str> {
+    match channel {
+        Channel::Red => Some("red"),
+        Channel::Green => Some("green"),
+        Channel::Blue => Some("blue"),
+        Channel::Luminance => None,
+    }
+}
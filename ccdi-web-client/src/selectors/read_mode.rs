@@ -0,0 +1,71 @@
+use yew::{Properties, Callback};
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct ReadModeSelector;
+
+pub enum Msg {
+    SetValue(usize),
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct ReadModeData {
+    pub read_mode_changed: Callback<usize>,
+    pub selected_value: usize,
+    pub read_modes: Vec<String>,
+}
+
+impl Component for ReadModeSelector {
+    type Message = Msg;
+    type Properties = ReadModeData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SetValue(value) => {
+                ctx.props().read_mode_changed.emit(value)
+            },
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let selected = ctx.props().selected_value;
+
+        let buttons = ctx.props().read_modes.iter()
+            .enumerate()
+            .map(|(index, name)| read_mode_button(selected, index, name, ctx))
+            .collect::<Html>();
+
+        html! {
+            <div>
+                <p>{"Read mode"}</p>
+                {buttons}
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+fn read_mode_button(
+    current: usize,
+    value: usize,
+    text: &str,
+    ctx: &Context<ReadModeSelector>
+) -> Html {
+    let mode_click = |action: usize| ctx.link().callback(move |_| Msg::SetValue(action));
+
+    let selected_class = match value == current {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    html! {
+        <button class={classes!(selected_class)} onclick={mode_click(value)}>{text}</button>
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use ccdi_imager_interface::{ExposureArea, ImagerProperties};
+use yew::{Properties, Callback};
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct RoiSelector;
+
+pub enum Msg {
+    SetValue(Option<ExposureArea>),
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct RoiData {
+    pub roi_changed: Callback<Option<ExposureArea>>,
+    pub selected_value: Option<ExposureArea>,
+    pub camera_properties: Option<Arc<ImagerProperties>>,
+}
+
+impl Component for RoiSelector {
+    type Message = Msg;
+    type Properties = RoiData;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::SetValue(value) => {
+                ctx.props().roi_changed.emit(value)
+            },
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let selected = ctx.props().selected_value.clone();
+        let properties = ctx.props().camera_properties.as_ref();
+        let (width, height) = properties
+            .map(|prop| (prop.basic.width, prop.basic.height))
+            .unwrap_or((0, 0));
+
+        html! {
+            <div>
+                <p>{"Region of interest"}</p>
+                { roi_button(&selected, None, "Full", ctx)}
+                { roi_button(&selected, center_area(width, height, 2), "Center 1/2", ctx)}
+                { roi_button(&selected, center_area(width, height, 4), "Center 1/4", ctx)}
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+fn center_area(width: usize, height: usize, divider: usize) -> Option<ExposureArea> {
+    match width > 0 && height > 0 {
+        false => None,
+        true => Some(ExposureArea {
+            x: (width - width/divider)/2,
+            y: (height - height/divider)/2,
+            width: width/divider,
+            height: height/divider,
+        })
+    }
+}
+
+fn roi_button(
+    current: &Option<ExposureArea>,
+    value: Option<ExposureArea>,
+    text: &str,
+    ctx: &Context<RoiSelector>
+) -> Html {
+    let selected_class = match &value == current {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    let roi_click = ctx.link().callback(move |_| Msg::SetValue(value.clone()));
+
+    html! {
+        <button class={classes!(selected_class)} onclick={roi_click}>{text}</button>
+    }
+}
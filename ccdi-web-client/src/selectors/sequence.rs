@@ -0,0 +1,314 @@
+use ccdi_imager_interface::{Binning, FrameType};
+use yew::{Properties, Callback};
+use crate::components::text_input::TextInput;
+
+use super::*;
+
+// ============================================ PUBLIC =============================================
+
+pub struct SequenceEditor {
+    steps: Vec<PlanStep>,
+    edited: EditedStep,
+    /// Local plan differs from the plan on the server
+    modified: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct SequenceEditorData {
+    pub on_action: Callback<StateMessage>,
+    pub sequence: SequenceView,
+}
+
+pub enum Msg {
+    EditCount(String),
+    EditTime(String),
+    EditGain(String),
+    EditDirectory(String),
+    SetBinning(Binning),
+    SetFrameType(FrameType),
+    AddStep,
+    RemoveStep(usize),
+    UploadPlan,
+    ServerAction(StateMessage),
+}
+
+impl Component for SequenceEditor {
+    type Message = Msg;
+    type Properties = SequenceEditorData;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            steps: ctx.props().sequence.plan.steps.clone(),
+            edited: EditedStep::default(),
+            modified: false,
+            error: None,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        if !self.modified {
+            self.steps = ctx.props().sequence.plan.steps.clone();
+        }
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::EditCount(value) => self.edited.count = value,
+            Msg::EditTime(value) => self.edited.time = value,
+            Msg::EditGain(value) => self.edited.gain = value,
+            Msg::EditDirectory(value) => self.edited.directory = value,
+            Msg::SetBinning(binning) => self.edited.binning = binning,
+            Msg::SetFrameType(frame_type) => self.edited.frame_type = frame_type,
+            Msg::AddStep => match self.edited.parse() {
+                Ok(step) => {
+                    self.steps.push(step);
+                    self.modified = true;
+                    self.error = None;
+                },
+                Err(error) => self.error = Some(error),
+            },
+            Msg::RemoveStep(index) => {
+                if index < self.steps.len() {
+                    self.steps.remove(index);
+                    self.modified = true;
+                }
+            },
+            Msg::UploadPlan => {
+                let plan = ExposurePlan { steps: self.steps.clone() };
+                ctx.props().on_action.emit(
+                    StateMessage::SequenceMessage(SequenceMessage::SetPlan(plan))
+                );
+                self.modified = false;
+            },
+            Msg::ServerAction(action) => ctx.props().on_action.emit(action),
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        use SequenceMessage::*;
+
+        let link = ctx.link();
+        let progress = &ctx.props().sequence.progress;
+
+        let server_action = |action: SequenceMessage| link.callback(
+            move |_| Msg::ServerAction(StateMessage::SequenceMessage(action.clone()))
+        );
+
+        html!{
+            <div>
+                <div>
+                    <p>{format_progress(progress, &ctx.props().sequence.plan)}</p>
+                    <button onclick={server_action(Start)}>{"Start"}</button>
+                    <button onclick={server_action(Pause)}>{"Pause"}</button>
+                    <button onclick={server_action(Resume)}>{"Resume"}</button>
+                    <button onclick={server_action(Skip)}>{"Skip"}</button>
+                    <button onclick={server_action(Stop)}>{"Stop"}</button>
+                </div>
+                <div class="div-table w100p">
+                    {self.render_steps(progress, ctx)}
+                </div>
+                <div>
+                    <p>{"New step: count, time [s], gain, directory"}</p>
+                    <TextInput
+                        on_change={link.callback(Msg::EditCount)}
+                        value={self.edited.count.clone()}
+                    />
+                    <TextInput
+                        on_change={link.callback(Msg::EditTime)}
+                        value={self.edited.time.clone()}
+                    />
+                    <TextInput
+                        on_change={link.callback(Msg::EditGain)}
+                        value={self.edited.gain.clone()}
+                    />
+                    <TextInput
+                        on_change={link.callback(Msg::EditDirectory)}
+                        value={self.edited.directory.clone()}
+                    />
+                </div>
+                <div>
+                    {binning_button(self.edited.binning, Binning::new(1, 1), "1x1", ctx)}
+                    {binning_button(self.edited.binning, Binning::new(2, 2), "2x2", ctx)}
+                    {binning_button(self.edited.binning, Binning::new(4, 4), "4x4", ctx)}
+                </div>
+                <div>
+                    {frame_type_button(self.edited.frame_type, FrameType::Light, ctx)}
+                    {frame_type_button(self.edited.frame_type, FrameType::Dark, ctx)}
+                    {frame_type_button(self.edited.frame_type, FrameType::Flat, ctx)}
+                    {frame_type_button(self.edited.frame_type, FrameType::Bias, ctx)}
+                </div>
+                <div>
+                    <button onclick={link.callback(|_| Msg::AddStep)}>{"Add step"}</button>
+                    <button
+                        class={classes!(if self.modified { Some("button-selected") } else { None })}
+                        onclick={link.callback(|_| Msg::UploadPlan)}
+                        >{"Upload plan"}
+                    </button>
+                </div>
+                <p>{self.error.clone().unwrap_or_default()}</p>
+            </div>
+        }
+    }
+}
+
+// =========================================== PRIVATE =============================================
+
+struct EditedStep {
+    count: String,
+    time: String,
+    gain: String,
+    directory: String,
+    binning: Binning,
+    frame_type: FrameType,
+}
+
+impl Default for EditedStep {
+    fn default() -> Self {
+        let step = PlanStep::default();
+
+        Self {
+            count: step.count.to_string(),
+            time: step.time.to_string(),
+            gain: step.gain.to_string(),
+            directory: step.directory,
+            binning: step.binning,
+            frame_type: step.frame_type,
+        }
+    }
+}
+
+impl EditedStep {
+    fn parse(&self) -> Result<PlanStep, String> {
+        let count = self.count.trim().parse::<usize>()
+            .map_err(|_| format!("Invalid count: {}", self.count))?;
+
+        let time = self.time.trim().parse::<f64>()
+            .map_err(|_| format!("Invalid exposure time: {}", self.time))?;
+
+        let gain = self.gain.trim().parse::<u16>()
+            .map_err(|_| format!("Invalid gain: {}", self.gain))?;
+
+        if self.directory.trim().is_empty() {
+            return Err(String::from("Directory must not be empty"))
+        }
+
+        Ok(PlanStep {
+            count,
+            time,
+            gain,
+            binning: self.binning,
+            frame_type: self.frame_type,
+            directory: self.directory.trim().to_owned(),
+        })
+    }
+}
+
+impl SequenceEditor {
+    fn render_steps(&self, progress: &SequenceProgress, ctx: &Context<Self>) -> Html {
+        let active = match progress.state {
+            SequenceState::Running | SequenceState::Paused => Some(progress.step),
+            SequenceState::Idle | SequenceState::Finished => None,
+        };
+
+        self.steps.iter()
+            .enumerate()
+            .map(|(index, step)| render_step(index, step, active == Some(index), ctx))
+            .collect::<Html>()
+    }
+}
+
+fn render_step(
+    index: usize,
+    step: &PlanStep,
+    active: bool,
+    ctx: &Context<SequenceEditor>
+) -> Html {
+    let text = format!(
+        "{} x {} s, gain {}, bin {}x{}, {:?}, {}",
+        step.count, step.time, step.gain, step.binning.x, step.binning.y,
+        step.frame_type, step.directory
+    );
+
+    let active_class = match active {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    html! {
+        <div class={classes!("div-table-row", "w100p", active_class)}>
+            <div class="div-table-col w60p">{text}</div>
+            <div class="div-table-col w39p">
+                <button onclick={ctx.link().callback(move |_| Msg::RemoveStep(index))}>
+                    {"Remove"}
+                </button>
+            </div>
+        </div>
+    }
+}
+
+fn format_progress(progress: &SequenceProgress, plan: &ExposurePlan) -> String {
+    let step_count = plan.steps.get(progress.step).map(|step| step.count).unwrap_or(0);
+
+    let eta = progress.eta
+        .map(format_duration)
+        .unwrap_or(String::from("-"));
+
+    format!(
+        "{:?}, step {} of {}, frame {} of {}, total {} of {}, ETA {}",
+        progress.state,
+        (progress.step + 1).min(plan.steps.len()),
+        plan.steps.len(),
+        progress.frame,
+        step_count,
+        progress.frames_done,
+        progress.frames_total,
+        eta
+    )
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{}:{:02}:{:02}", seconds/3600, (seconds/60)%60, seconds%60)
+}
+
+fn binning_button(
+    current: Binning,
+    value: Binning,
+    text: &str,
+    ctx: &Context<SequenceEditor>
+) -> Html {
+    let selected_class = match value == current {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    html! {
+        <button
+            class={classes!(selected_class)}
+            onclick={ctx.link().callback(move |_| Msg::SetBinning(value))}
+            >{text}
+        </button>
+    }
+}
+
+fn frame_type_button(
+    current: FrameType,
+    value: FrameType,
+    ctx: &Context<SequenceEditor>
+) -> Html {
+    let selected_class = match value == current {
+        true => Some("button-selected"),
+        false => None,
+    };
+
+    html! {
+        <button
+            class={classes!(selected_class)}
+            onclick={ctx.link().callback(move |_| Msg::SetFrameType(value))}
+            >{format!("{:?}", value)}
+        </button>
+    }
+}
@@ -7,17 +7,22 @@ use super::*;
 
 pub struct ShootingDetail {
     pub edited_name: String,
+    pub edited_object: String,
 }
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct ShootingDetailData {
     pub on_action: Callback<StateMessage>,
     pub storage_details: StorageDetail,
+    /// Name of the imaged object written to saved images
+    pub object: Option<String>,
 }
 
 pub enum Msg{
     UpdateEditedName(String),
     SetDirectory,
+    UpdateEditedObject(String),
+    SetObject,
     ServerAction(StateMessage),
 }
 
@@ -27,7 +32,8 @@ impl Component for ShootingDetail {
 
     fn create(_ctx: &Context<Self>) -> Self {
         Self {
-            edited_name: String::new()
+            edited_name: String::new(),
+            edited_object: String::new(),
         }
     }
 
@@ -39,6 +45,12 @@ impl Component for ShootingDetail {
                     StorageMessage::SetDirectory(self.edited_name.clone())
                 )
             ),
+            Msg::UpdateEditedObject(object) => self.edited_object = object,
+            Msg::SetObject => ctx.props().on_action.emit(
+                StateMessage::CameraParam(CameraParamMessage::SetObject(
+                    Some(self.edited_object.trim().to_owned()).filter(|object| !object.is_empty())
+                ))
+            ),
             Msg::ServerAction(action) => ctx.props().on_action.emit(action),
         }
         false
@@ -49,6 +61,8 @@ impl Component for ShootingDetail {
 
         let on_change = ctx.link().callback(Msg::UpdateEditedName);
         let set_dir_click = || ctx.link().callback(move |_| Msg::SetDirectory);
+        let on_object_change = ctx.link().callback(Msg::UpdateEditedObject);
+        let set_object_click = || ctx.link().callback(move |_| Msg::SetObject);
         let details = &ctx.props().storage_details;
         let enabled = ctx.props().storage_details.storage_enabled;
 
@@ -56,18 +70,44 @@ impl Component for ShootingDetail {
             move |_| Msg::ServerAction(action.clone())
         );
 
+        let compression_button = |compression: XisfCompression, label: &str| {
+            let selected = details.compression == compression;
+
+            html! {
+                <button
+                    class={classes!(if selected { Some("button-selected") } else { None })}
+                    onclick={server_action(
+                        StateMessage::StorageMessage(SetCompression(compression))
+                    )}
+                    >{label}
+                </button>
+            }
+        };
+
         html!{
             <div>
                 <div>
                     <p>{format_capacity(&details.state)}</p>
                     <p>{format!("Counter: {}", &details.counter)}</p>
+                    <p>{format!(
+                        "Write queue: {} of {}", details.queue_depth, details.queue_capacity
+                    )}</p>
                     <p>{format!("Directory: {}", &details.storage_name)}</p>
+                    <p>{format!(
+                        "Object: {}", ctx.props().object.as_deref().unwrap_or("not set")
+                    )}</p>
+                    <p>{format!("Storage: {}", &details.storage_root)}</p>
                 </div>
                 <div>
                     <p>{"Change Directory"}</p>
                     <TextInput {on_change} value={self.edited_name.clone()}/>
                     <button onclick={set_dir_click()}>{"Set dir"}</button>
                 </div>
+                <div>
+                    <p>{"Change Object"}</p>
+                    <TextInput on_change={on_object_change} value={self.edited_object.clone()}/>
+                    <button onclick={set_object_click()}>{"Set object"}</button>
+                </div>
                 <div>
                     <button
                         class={classes!(if !enabled { Some("button-selected") } else { None })}
@@ -80,6 +120,12 @@ impl Component for ShootingDetail {
                         >{"Save ON"}
                     </button>
                 </div>
+                <div>
+                    <p>{"XISF compression"}</p>
+                    {compression_button(XisfCompression::None, "None")}
+                    {compression_button(XisfCompression::Lz4, "LZ4")}
+                    {compression_button(XisfCompression::Zlib, "Zlib")}
+                </div>
             </div>
         }
     }
@@ -90,7 +136,14 @@ fn format_capacity(capacity: &StorageState) -> String {
         StorageState::Unknown => String::from("?"),
         StorageState::Error(error) => format!("Storage not available: {:?}", error),
         StorageState::Available(details) => format!(
-            "Storage: {:1.1}G of {:1.1}G free", details.free_gigabytes, details.total_gigabytes
+            "Storage: {:1.1}G of {:1.1}G free{}",
+            details.free_gigabytes,
+            details.total_gigabytes,
+            match details.level {
+                SpaceLevel::Sufficient => "",
+                SpaceLevel::Low => " - LOW SPACE",
+                SpaceLevel::Critical => " - OUT OF SPACE, capture stopped",
+            }
         ),
     }
 }
\ No newline at end of file
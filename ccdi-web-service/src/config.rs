@@ -9,6 +9,10 @@ pub struct ServiceConfig {
     #[argh(switch)]
     pub demo: bool,
 
+    /// play back FITS files from a directory instead of using a camera
+    #[argh(option)]
+    pub playback: Option<String>,
+
     /// enable debug logging
     #[argh(switch)]
     pub debug: bool,
@@ -4,17 +4,22 @@ mod config;
 mod static_files;
 mod logger;
 
+use std::path::PathBuf;
+
 use ccdi_common::ClientMessage;
 use ccdi_common::IoMessage;
 use ccdi_common::ProcessMessage;
 use ccdi_common::StateMessage;
 use ccdi_common::StorageMessage;
+use ccdi_common::log_err;
 use ccdi_logic::LogicParams;
+use ccdi_logic::ImagerSource;
 use ccdi_logic::create_default_config_file;
 use ccdi_logic::load_config_file;
 use ccdi_logic::start_io_thread;
 use ccdi_logic::start_logic_thread;
 use ccdi_logic::start_process_thread;
+use ccdi_logic::start_shutdown_thread;
 use ccdi_logic::start_storage_thread;
 use config::ServiceConfig;
 use log::debug;
@@ -36,10 +41,18 @@ fn main() {
     let config: ServiceConfig = argh::from_env();
     init_logger(config.debug, config.log.as_ref());
 
-    let params = LogicParams {
-        demo_mode: config.demo,
+    let imager = match (config.demo, config.playback) {
+        (true, Some(_)) => {
+            error!("Options --demo and --playback can not be combined");
+            return;
+        },
+        (true, None) => ImagerSource::Demo,
+        (false, Some(directory)) => ImagerSource::Playback(PathBuf::from(directory)),
+        (false, None) => ImagerSource::Moravian,
     };
 
+    let params = LogicParams { imager };
+
     match create_default_config_file() {
         Ok(path) => info!(
             "Created default config in '{}'. Rename it to config.json to use it.",
@@ -64,14 +77,23 @@ fn main() {
     let (storage_tx, storage_rx) = std::sync::mpsc::channel::<StorageMessage>();
     let (io_tx, io_rx) = std::sync::mpsc::channel::<IoMessage>();
 
-    let _storage_thread = start_storage_thread(config.clone(), storage_rx, server_tx.clone());
-    let _process_thread = start_process_thread(process_rx, clients_tx.clone(), server_tx.clone());
-    let _io_thread = start_io_thread(config.clone(), io_rx, server_tx.clone());
+    let storage_thread = start_storage_thread(config.clone(), storage_rx, server_tx.clone());
+    let process_thread = start_process_thread(process_rx, clients_tx.clone(), server_tx.clone());
+    let io_thread = start_io_thread(config.clone(), io_rx, server_tx.clone());
 
-    let _server_thread = start_logic_thread(
+    let worker_threads = [storage_thread, process_thread, io_thread].into_iter()
+        .filter_map(|thread| log_err("Start worker thread", thread))
+        .collect();
+
+    let server_thread = start_logic_thread(
         params, config.clone(), server_rx, clients_tx, io_tx, process_tx, storage_tx,
     );
 
+    let _shutdown_thread = match server_thread {
+        Ok(thread) => start_shutdown_thread(config.clone(), thread, worker_threads),
+        Err(error) => return error!("Logic thread could not be started: {}", error),
+    };
+
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
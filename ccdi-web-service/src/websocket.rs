@@ -1,4 +1,4 @@
-use ccdi_common::{log_err, to_string, StateMessage, ClientMessage, rgb_image_to_bytes};
+use ccdi_common::{log_err, to_string, StateMessage, ClientMessage, preview_image_to_bytes};
 use log::*;
 use std::collections::HashMap;
 use std::convert::Infallible;
@@ -72,7 +72,7 @@ pub fn create_websocket_service(
 
 fn serialize(message: &ClientMessage) -> Result<Message, String> {
     match message {
-        ClientMessage::RgbImage(image) => Ok(Message::binary(rgb_image_to_bytes(image))),
+        ClientMessage::Image(image) => Ok(Message::binary(preview_image_to_bytes(image))),
         _other => Ok(Message::text(serde_json::to_string(&message).map_err(to_string)?))
     }
 }